@@ -1,8 +1,8 @@
 use casbin::{error::AdapterError, Error as CasbinError, Filter, Result};
 use sea_orm::{
-    ActiveModelTrait,
+    sea_query::{Expr, OnConflict},
     ActiveValue::{NotSet, Set},
-    ColumnTrait, Condition, ConnectionTrait, EntityTrait, QueryFilter,
+    ColumnTrait, Condition, ConnectionTrait, DbErr, EntityTrait, QueryFilter, SqlErr,
 };
 
 use crate::{
@@ -10,6 +10,9 @@ use crate::{
     ext::{ConditionExt, QueryFilterExt},
 };
 
+// 批量写入时每条语句包含的规则数，每条规则最多 7 个参数，低于旧版本 SQLite 999 个参数的限制
+const BATCH_SIZE: usize = 100;
+
 #[derive(Debug, Default)]
 pub(crate) struct Rule<'a> {
     pub v0: &'a str,
@@ -65,41 +68,53 @@ pub(crate) struct RuleWithType<'a> {
     pub v5: &'a str,
 }
 
-pub(crate) async fn remove_policy<'conn, 'rule, C: ConnectionTrait>(
-    conn: &'conn C,
-    ptype: &'rule str,
-    rule: &'rule Rule<'rule>,
+pub(crate) async fn remove_policy<C: ConnectionTrait>(
+    conn: &C,
+    ptype: &str,
+    rule: &Rule<'_>,
 ) -> Result<bool> {
-    Entity::delete_many()
-        .filter(Column::Ptype.eq(ptype))
-        .filter(Column::V0.eq(rule.v0))
-        .filter(Column::V1.eq(rule.v1))
-        .filter(Column::V2.eq(rule.v2))
-        .filter(Column::V3.eq(rule.v3))
-        .filter(Column::V4.eq(rule.v4))
-        .filter(Column::V5.eq(rule.v5))
-        .exec(conn)
+    remove_policies(conn, ptype, std::slice::from_ref(rule))
         .await
-        .map(|count| count.rows_affected == 1)
-        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))
+        .map(|count| count == 1)
 }
 
-pub(crate) async fn remove_policies<'conn, 'rule, C: ConnectionTrait>(
-    conn: &'conn C,
-    ptype: &'rule str,
-    rules: &'rule [Rule<'rule>],
-) -> Result<bool> {
-    for rule in rules {
-        remove_policy(conn, ptype, rule).await?;
+// 按批删除，返回实际删除的行数
+pub(crate) async fn remove_policies<C: ConnectionTrait>(
+    conn: &C,
+    ptype: &str,
+    rules: &[Rule<'_>],
+) -> Result<u64> {
+    let mut removed = 0;
+    for chunk in rules.chunks(BATCH_SIZE) {
+        let condition = chunk.iter().fold(Condition::any(), |cond, rule| {
+            cond.add(rule_condition(rule))
+        });
+        removed += Entity::delete_many()
+            .filter(Column::Ptype.eq(ptype))
+            .filter(condition)
+            .exec(conn)
+            .await
+            .map(|res| res.rows_affected)
+            .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
     }
-    Ok(true)
+    Ok(removed)
+}
+
+fn rule_condition(rule: &Rule<'_>) -> Condition {
+    Condition::all()
+        .add(Column::V0.eq(rule.v0))
+        .add(Column::V1.eq(rule.v1))
+        .add(Column::V2.eq(rule.v2))
+        .add(Column::V3.eq(rule.v3))
+        .add(Column::V4.eq(rule.v4))
+        .add(Column::V5.eq(rule.v5))
 }
 
-pub(crate) async fn remove_filtered_policy<'conn, 'rule, C: ConnectionTrait>(
-    conn: &'conn C,
-    ptype: &'rule str,
+pub(crate) async fn remove_filtered_policy<C: ConnectionTrait>(
+    conn: &C,
+    ptype: &str,
     index_of_match_start: usize,
-    rule: &'rule [&'rule str; 6],
+    rule: &[&str; 6],
 ) -> Result<bool> {
     let mut stmt = Entity::delete_many().filter(Column::Ptype.eq(ptype));
 
@@ -150,9 +165,9 @@ pub(crate) async fn load_policy<C: ConnectionTrait>(conn: &C) -> Result<Vec<enti
         .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))
 }
 
-pub(crate) async fn load_filtered_policy<'conn, 'filter, C: ConnectionTrait>(
-    conn: &'conn C,
-    filter: &'filter Filter<'filter>,
+pub(crate) async fn load_filtered_policy<C: ConnectionTrait>(
+    conn: &C,
+    filter: &Filter<'_>,
 ) -> Result<Vec<entity::Model>> {
     let (g_filter, p_filter) = filtered_where_values(filter);
 
@@ -210,95 +225,119 @@ fn filtered_where_values<'a>(filter: &'a Filter<'a>) -> ([&'a str; 6], [&'a str;
     (g_filter, p_filter)
 }
 
-pub(crate) async fn save_policy<'conn, 'rule, C: ConnectionTrait>(
-    conn: &'conn C,
-    rule: &'rule RuleWithType<'rule>,
-) -> Result<()> {
-    let models = entity::Entity::find()
-        .filter(Column::Ptype.eq(rule.ptype))
-        .filter(Column::V0.eq(rule.v0))
-        .filter(Column::V1.eq(rule.v1))
-        .filter(Column::V2.eq(rule.v2))
-        .filter(Column::V3.eq(rule.v3))
-        .filter(Column::V4.eq(rule.v4))
-        .filter(Column::V5.eq(rule.v5))
-        .all(conn)
+pub(crate) async fn add_policy<C: ConnectionTrait>(
+    conn: &C,
+    rule: &RuleWithType<'_>,
+) -> Result<bool> {
+    add_policies(conn, std::slice::from_ref(rule))
         .await
-        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+        .map(|count| count == 1)
+}
 
-    if !models.is_empty() {
-        return Ok(());
+// 按批插入，已存在的规则会被跳过，返回实际插入的行数
+pub(crate) async fn add_policies<C: ConnectionTrait>(
+    conn: &C,
+    rules: &[RuleWithType<'_>],
+) -> Result<u64> {
+    let mut added = 0;
+    for chunk in rules.chunks(BATCH_SIZE) {
+        let models = chunk.iter().map(|rule| entity::ActiveModel {
+            id: NotSet,
+            ptype: Set(rule.ptype.to_string()),
+            v0: Set(rule.v0.to_string()),
+            v1: Set(rule.v1.to_string()),
+            v2: Set(rule.v2.to_string()),
+            v3: Set(rule.v3.to_string()),
+            v4: Set(rule.v4.to_string()),
+            v5: Set(rule.v5.to_string()),
+        });
+        added += Entity::insert_many(models)
+            .on_conflict(
+                OnConflict::columns([
+                    Column::Ptype,
+                    Column::V0,
+                    Column::V1,
+                    Column::V2,
+                    Column::V3,
+                    Column::V4,
+                    Column::V5,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .exec_without_returning(conn)
+            .await
+            .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
     }
+    Ok(added)
+}
 
-    let model = entity::ActiveModel {
-        id: NotSet,
-        ptype: Set(rule.ptype.to_string()),
-        v0: Set(rule.v0.to_string()),
-        v1: Set(rule.v1.to_string()),
-        v2: Set(rule.v2.to_string()),
-        v3: Set(rule.v3.to_string()),
-        v4: Set(rule.v4.to_string()),
-        v5: Set(rule.v5.to_string()),
-    };
-
-    model
-        .insert(conn)
+pub(crate) async fn clear_policy<C: ConnectionTrait>(conn: &C) -> Result<()> {
+    entity::Entity::delete_many()
+        .exec(conn)
         .await
         .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
 
     Ok(())
 }
-
-pub(crate) async fn save_policies<'conn, 'rule, C: ConnectionTrait>(
-    conn: &'conn C,
-    rules: &'rule [RuleWithType<'rule>],
-) -> Result<()> {
-    for rule in rules {
-        save_policy(conn, rule).await?;
+// 原地更新一条规则，旧规则不存在或新规则已存在时返回 false
+pub(crate) async fn update_policy<C: ConnectionTrait>(
+    conn: &C,
+    ptype: &str,
+    old_rule: &Rule<'_>,
+    new_rule: &Rule<'_>,
+) -> Result<bool> {
+    let result = Entity::update_many()
+        .col_expr(Column::V0, Expr::value(new_rule.v0))
+        .col_expr(Column::V1, Expr::value(new_rule.v1))
+        .col_expr(Column::V2, Expr::value(new_rule.v2))
+        .col_expr(Column::V3, Expr::value(new_rule.v3))
+        .col_expr(Column::V4, Expr::value(new_rule.v4))
+        .col_expr(Column::V5, Expr::value(new_rule.v5))
+        .filter(Column::Ptype.eq(ptype))
+        .filter(rule_condition(old_rule))
+        .exec(conn)
+        .await;
+    match result {
+        Ok(res) => Ok(res.rows_affected == 1),
+        Err(err) if is_unique_violation(&err) => Ok(false),
+        Err(err) => Err(CasbinError::from(AdapterError(Box::new(err)))),
     }
-
-    Ok(())
 }
 
-pub(crate) async fn add_policy<'conn, 'rule, C: ConnectionTrait>(
-    conn: &'conn C,
-    rule: &'rule RuleWithType<'rule>,
-) -> Result<()> {
-    let model = entity::ActiveModel {
-        id: NotSet,
-        ptype: Set(rule.ptype.to_string()),
-        v0: Set(rule.v0.to_string()),
-        v1: Set(rule.v1.to_string()),
-        v2: Set(rule.v2.to_string()),
-        v3: Set(rule.v3.to_string()),
-        v4: Set(rule.v4.to_string()),
-        v5: Set(rule.v5.to_string()),
-    };
+// 查出并删除从 field_index 开始匹配 field_values 的规则，空字符串匹配任意值
+pub(crate) async fn take_filtered_policy<C: ConnectionTrait>(
+    conn: &C,
+    ptype: &str,
+    field_index: usize,
+    field_values: &[String],
+) -> Result<Vec<entity::Model>> {
+    let columns = [
+        Column::V0,
+        Column::V1,
+        Column::V2,
+        Column::V3,
+        Column::V4,
+        Column::V5,
+    ];
+    let condition = columns.iter().skip(field_index).zip(field_values).fold(
+        Condition::all().add(Column::Ptype.eq(ptype)),
+        |cond, (col, value)| cond.add_maybe(!value.is_empty(), col.eq(value.as_str())),
+    );
 
-    model
-        .insert(conn)
+    let models = Entity::find()
+        .filter(condition.clone())
+        .all(conn)
         .await
         .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
-
-    Ok(())
-}
-
-pub(crate) async fn add_policies<'conn, 'rule, C: ConnectionTrait>(
-    conn: &'conn C,
-    rules: &'rule [RuleWithType<'rule>],
-) -> Result<()> {
-    for rule in rules {
-        add_policy(conn, rule).await?;
-    }
-
-    Ok(())
-}
-
-pub(crate) async fn clear_policy<C: ConnectionTrait>(conn: &C) -> Result<()> {
-    entity::Entity::delete_many()
+    Entity::delete_many()
+        .filter(condition)
         .exec(conn)
         .await
         .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    Ok(models)
+}
 
-    Ok(())
-}
\ No newline at end of file
+fn is_unique_violation(err: &DbErr) -> bool {
+    matches!(err.sql_err(), Some(SqlErr::UniqueConstraintViolation(_)))
+}
@@ -1,16 +1,19 @@
 use async_trait::async_trait;
 use casbin::{error::AdapterError, Adapter, Error as CasbinError, Filter, Model, Result};
-use sea_orm::ConnectionTrait;
+use sea_orm::{ConnectionTrait, DatabaseTransaction, TransactionTrait};
 
 use crate::{
     action::{self, Rule, RuleWithType},
     entity, migration,
+    watcher::{self, OP_ADD, OP_RELOAD, OP_REMOVE},
+    SeaOrmWatcher, UpdatableAdapter,
 };
 
-
 pub struct SeaOrmAdapter<C> {
     conn: C,
     is_filtered: bool,
+    // 设置后在修改规则的同一个事务中写入修改记录，供其他实例拉取
+    instance: Option<String>,
 }
 
 impl<C: ConnectionTrait> SeaOrmAdapter<C> {
@@ -20,10 +23,17 @@ impl<C: ConnectionTrait> SeaOrmAdapter<C> {
             .map(|_| Self {
                 conn,
                 is_filtered: false,
+                instance: None,
             })
             .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))
     }
 
+    // 和 SeaOrmWatcher 一起使用时调用，规则修改和修改记录一起提交，不会丢失或乱序
+    pub fn with_watcher<W>(mut self, watcher: &SeaOrmWatcher<W>) -> Self {
+        self.instance = Some(watcher.instance().to_string());
+        self
+    }
+
     fn save_policy_line<'a>(ptype: &'a str, rule: &'a [String]) -> Option<RuleWithType<'a>> {
         if ptype.trim().is_empty() || rule.is_empty() {
             return None;
@@ -91,8 +101,43 @@ impl<C: ConnectionTrait> SeaOrmAdapter<C> {
     }
 }
 
+impl<C: ConnectionTrait + TransactionTrait> SeaOrmAdapter<C> {
+    async fn begin(&self) -> Result<DatabaseTransaction> {
+        self.conn
+            .begin()
+            .await
+            .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))
+    }
+
+    // 批量操作只有全部规则都写入成功才提交，否则回滚并返回 false，和内存模型的批量操作语义一致
+    async fn commit_if(txn: DatabaseTransaction, complete: bool) -> Result<bool> {
+        let result = match complete {
+            true => txn.commit().await,
+            false => txn.rollback().await,
+        };
+        result
+            .map(|_| complete)
+            .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))
+    }
+
+    // 没有关联 watcher 时不写修改记录
+    async fn record(
+        &self,
+        txn: &DatabaseTransaction,
+        op: &str,
+        sec: &str,
+        ptype: &str,
+        rules: &[Vec<String>],
+    ) -> Result<()> {
+        match &self.instance {
+            Some(instance) => watcher::record(txn, instance, op, sec, ptype, rules).await,
+            None => Ok(()),
+        }
+    }
+}
+
 #[async_trait]
-impl<C: ConnectionTrait + Send + Sync> Adapter for SeaOrmAdapter<C> {
+impl<C: ConnectionTrait + TransactionTrait + Send + Sync> Adapter for SeaOrmAdapter<C> {
     async fn load_policy(&mut self, m: &mut dyn Model) -> Result<()> {
         let rules = action::load_policy(&self.conn).await?;
 
@@ -162,67 +207,108 @@ impl<C: ConnectionTrait + Send + Sync> Adapter for SeaOrmAdapter<C> {
                 rules.extend(new_rules);
             }
         }
-        action::save_policies(&self.conn, &rules).await
+
+        // 用模型中的规则整体替换表中的规则，清空和写入在同一个事务中完成
+        let txn = self.begin().await?;
+        action::clear_policy(&txn).await?;
+        action::add_policies(&txn, &rules).await?;
+        self.record(&txn, OP_RELOAD, "", "", &[]).await?;
+        Self::commit_if(txn, true).await.map(|_| ())
     }
 
     async fn clear_policy(&mut self) -> Result<()> {
-        action::clear_policy(&self.conn).await
+        let txn = self.begin().await?;
+        action::clear_policy(&txn).await?;
+        self.record(&txn, OP_RELOAD, "", "", &[]).await?;
+        Self::commit_if(txn, true).await.map(|_| ())
     }
 
     fn is_filtered(&self) -> bool {
         self.is_filtered
     }
 
-    async fn add_policy(&mut self, _sec: &str, ptype: &str, rule: Vec<String>) -> Result<bool> {
+    async fn add_policy(&mut self, sec: &str, ptype: &str, rule: Vec<String>) -> Result<bool> {
         let Some(rule_with_type) = Self::save_policy_line(ptype, rule.as_slice()) else {
             return Ok(false);
         };
 
-        action::add_policy(&self.conn, &rule_with_type)
-            .await
-            .map(|_| true)
+        let txn = self.begin().await?;
+        let added = action::add_policy(&txn, &rule_with_type).await?;
+        if added {
+            self.record(&txn, OP_ADD, sec, ptype, std::slice::from_ref(&rule))
+                .await?;
+        }
+        Self::commit_if(txn, added).await
     }
 
     async fn add_policies(
         &mut self,
-        _sec: &str,
+        sec: &str,
         ptype: &str,
         rules: Vec<Vec<String>>,
     ) -> Result<bool> {
-        let rules = rules
+        let rows = rules
             .iter()
             .filter_map(|x| Self::save_policy_line(ptype, x))
             .collect::<Vec<_>>();
 
-        action::add_policies(&self.conn, &rules).await.map(|_| true)
+        let txn = self.begin().await?;
+        let added = action::add_policies(&txn, &rows).await? == rows.len() as u64;
+        if added {
+            self.record(&txn, OP_ADD, sec, ptype, &rules).await?;
+        }
+        Self::commit_if(txn, added).await
     }
 
-    async fn remove_policy(&mut self, _sec: &str, ptype: &str, rule: Vec<String>) -> Result<bool> {
-        action::remove_policy(&self.conn, ptype, &Rule::from(rule.as_ref())).await
+    async fn remove_policy(&mut self, sec: &str, ptype: &str, rule: Vec<String>) -> Result<bool> {
+        let txn = self.begin().await?;
+        let removed = action::remove_policy(&txn, ptype, &Rule::from(rule.as_ref())).await?;
+        if removed {
+            self.record(&txn, OP_REMOVE, sec, ptype, std::slice::from_ref(&rule))
+                .await?;
+        }
+        Self::commit_if(txn, removed).await
     }
 
     async fn remove_policies(
         &mut self,
-        _sec: &str,
+        sec: &str,
         ptype: &str,
         rules: Vec<Vec<String>>,
     ) -> Result<bool> {
-        let rules = rules
+        let rows = rules
             .iter()
             .map(|r| Rule::from(r.as_ref()))
             .collect::<Vec<_>>();
-        action::remove_policies(&self.conn, ptype, &rules).await
+        let txn = self.begin().await?;
+        let removed = action::remove_policies(&txn, ptype, &rows).await? == rows.len() as u64;
+        if removed {
+            self.record(&txn, OP_REMOVE, sec, ptype, &rules).await?;
+        }
+        Self::commit_if(txn, removed).await
     }
 
     async fn remove_filtered_policy(
         &mut self,
-        _sec: &str,
+        sec: &str,
         ptype: &str,
         field_index: usize,
         field_values: Vec<String>,
     ) -> Result<bool> {
         if field_index <= 5 && !field_values.is_empty() && field_values.len() >= 6 - field_index {
             Ok(false)
+        } else if self.instance.is_some() {
+            // 查出实际删除的规则写入修改记录，其他实例只需要删除这些规则
+            let txn = self.begin().await?;
+            let removed = action::take_filtered_policy(&txn, ptype, field_index, &field_values)
+                .await?
+                .iter()
+                .filter_map(Self::normalize_policy)
+                .collect::<Vec<_>>();
+            if !removed.is_empty() {
+                self.record(&txn, OP_REMOVE, sec, ptype, &removed).await?;
+            }
+            Self::commit_if(txn, !removed.is_empty()).await
         } else {
             let field_values = if field_values.len() < 6 {
                 let mut temp = field_values.clone();
@@ -240,4 +326,80 @@ impl<C: ConnectionTrait + Send + Sync> Adapter for SeaOrmAdapter<C> {
             action::remove_filtered_policy(&self.conn, ptype, field_index, &new_rule).await
         }
     }
-}
\ No newline at end of file
+}
+#[async_trait]
+impl<C: ConnectionTrait + TransactionTrait + Send + Sync> UpdatableAdapter for SeaOrmAdapter<C> {
+    async fn update_policy(
+        &mut self,
+        sec: &str,
+        ptype: &str,
+        old_rule: Vec<String>,
+        new_rule: Vec<String>,
+    ) -> Result<bool> {
+        self.update_policies(sec, ptype, vec![old_rule], vec![new_rule])
+            .await
+    }
+
+    async fn update_policies(
+        &mut self,
+        sec: &str,
+        ptype: &str,
+        old_rules: Vec<Vec<String>>,
+        new_rules: Vec<Vec<String>>,
+    ) -> Result<bool> {
+        if old_rules.len() != new_rules.len() || new_rules.iter().any(Vec::is_empty) {
+            return Ok(false);
+        }
+
+        let txn = self.begin().await?;
+        let mut complete = true;
+        for (old_rule, new_rule) in old_rules.iter().zip(&new_rules) {
+            let old_rule = Rule::from(old_rule.as_ref());
+            let new_rule = Rule::from(new_rule.as_ref());
+            if !action::update_policy(&txn, ptype, &old_rule, &new_rule).await? {
+                complete = false;
+                break;
+            }
+        }
+        if complete {
+            self.record(&txn, OP_REMOVE, sec, ptype, &old_rules).await?;
+            self.record(&txn, OP_ADD, sec, ptype, &new_rules).await?;
+        }
+        Self::commit_if(txn, complete).await
+    }
+
+    async fn update_filtered_policies(
+        &mut self,
+        sec: &str,
+        ptype: &str,
+        new_rules: Vec<Vec<String>>,
+        field_index: usize,
+        field_values: Vec<String>,
+    ) -> Result<Vec<Vec<String>>> {
+        // 先校验新规则，有不合法的规则时不删除过滤到的旧规则
+        let Some(rows) = new_rules
+            .iter()
+            .map(|x| Self::save_policy_line(ptype, x))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Ok(vec![]);
+        };
+
+        let txn = self.begin().await?;
+        let old_rules =
+            action::take_filtered_policy(&txn, ptype, field_index, &field_values).await?;
+        let added = action::add_policies(&txn, &rows).await? == rows.len() as u64;
+        let old_rules = old_rules
+            .iter()
+            .filter_map(Self::normalize_policy)
+            .collect::<Vec<_>>();
+        if added {
+            self.record(&txn, OP_REMOVE, sec, ptype, &old_rules).await?;
+            self.record(&txn, OP_ADD, sec, ptype, &new_rules).await?;
+        }
+        if !Self::commit_if(txn, added).await? {
+            return Ok(vec![]);
+        }
+        Ok(old_rules)
+    }
+}
@@ -1,7 +1,12 @@
-mod adapter;
 mod action;
+mod adapter;
 mod entity;
-mod migration;
 mod ext;
+mod migration;
+mod updatable;
+mod version;
+mod watcher;
 
-pub use adapter::SeaOrmAdapter;
\ No newline at end of file
+pub use adapter::SeaOrmAdapter;
+pub use updatable::UpdatableAdapter;
+pub use watcher::{SeaOrmWatcher, WatcherSync};
@@ -3,6 +3,18 @@ use sea_orm::{
     ConnectionTrait, DbErr, DeriveIden, ExecResult,
 };
 
+#[derive(DeriveIden)]
+enum CasbinRuleVersion {
+    Table,
+    Id,
+    Instance,
+    Op,
+    Sec,
+    Ptype,
+    Rules,
+    CreatedAt,
+}
+
 #[derive(DeriveIden)]
 enum CasbinRule {
     Table,
@@ -49,8 +61,47 @@ pub async fn up<C: ConnectionTrait>(conn: &C) -> Result<ExecResult, DbErr> {
         )
         .to_owned();
 
+    let create_version_table = Table::create()
+        .if_not_exists()
+        .table(CasbinRuleVersion::Table)
+        .col(
+            ColumnDef::new(CasbinRuleVersion::Id)
+                .integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(CasbinRuleVersion::Instance)
+                .string_len(64)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(CasbinRuleVersion::Op)
+                .string_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(CasbinRuleVersion::Sec)
+                .string_len(12)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(CasbinRuleVersion::Ptype)
+                .string_len(12)
+                .not_null(),
+        )
+        .col(ColumnDef::new(CasbinRuleVersion::Rules).text().not_null())
+        .col(
+            ColumnDef::new(CasbinRuleVersion::CreatedAt)
+                .big_integer()
+                .not_null(),
+        )
+        .to_owned();
+
     let builder = conn.get_database_backend();
-    conn.execute(builder.build(&create_table)).await
+    conn.execute(builder.build(&create_table)).await?;
+    conn.execute(builder.build(&create_version_table)).await
 }
 
 pub async fn down<C: ConnectionTrait>(conn: &C) -> Result<ExecResult, DbErr> {
@@ -58,7 +109,12 @@ pub async fn down<C: ConnectionTrait>(conn: &C) -> Result<ExecResult, DbErr> {
         .if_exists()
         .table(CasbinRule::Table)
         .to_owned();
+    let drop_version_table = Table::drop()
+        .if_exists()
+        .table(CasbinRuleVersion::Table)
+        .to_owned();
 
     let builder = conn.get_database_backend();
+    conn.execute(builder.build(&drop_version_table)).await?;
     conn.execute(builder.build(&drop_table)).await
-}
\ No newline at end of file
+}
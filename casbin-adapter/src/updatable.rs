@@ -0,0 +1,34 @@
+use async_trait::async_trait;
+use casbin::{Adapter, Result};
+
+// casbin 2.x 的 Adapter 没有更新接口，这里按其他语言版本 casbin 的 UpdatableAdapter 补上，
+// 规则直接在存储中原地更新，不需要先删除再添加
+#[async_trait]
+pub trait UpdatableAdapter: Adapter {
+    async fn update_policy(
+        &mut self,
+        sec: &str,
+        ptype: &str,
+        old_rule: Vec<String>,
+        new_rule: Vec<String>,
+    ) -> Result<bool>;
+
+    // old_rules 和 new_rules 按位置一一对应，任何一条更新失败则全部不生效
+    async fn update_policies(
+        &mut self,
+        sec: &str,
+        ptype: &str,
+        old_rules: Vec<Vec<String>>,
+        new_rules: Vec<Vec<String>>,
+    ) -> Result<bool>;
+
+    // 用 new_rules 替换所有匹配过滤条件的规则，返回被替换的规则；新规则和其他规则冲突时不做修改，返回空
+    async fn update_filtered_policies(
+        &mut self,
+        sec: &str,
+        ptype: &str,
+        new_rules: Vec<Vec<String>>,
+        field_index: usize,
+        field_values: Vec<String>,
+    ) -> Result<Vec<Vec<String>>>;
+}
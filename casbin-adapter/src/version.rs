@@ -0,0 +1,23 @@
+use sea_orm::entity::prelude::*;
+
+// 规则修改记录，自增 id 即版本号，其他实例按 id 顺序拉取并应用
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "casbin_rule_version")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    // 产生修改的实例
+    pub instance: String,
+    // add、remove 或 reload
+    pub op: String,
+    pub sec: String,
+    pub ptype: String,
+    // JSON 编码的规则列表
+    pub rules: String,
+    pub created_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,269 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use casbin::{
+    error::AdapterError, CachedApi, CachedEnforcer, CoreApi, Error as CasbinError, EventData,
+    MgmtApi, Result, Watcher,
+};
+use sea_orm::{
+    ActiveValue::NotSet, ActiveValue::Set, ColumnTrait, Condition, ConnectionTrait, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect,
+};
+use tokio::sync::RwLock;
+
+use crate::{migration, version};
+
+// 修改记录保留的时间，单位秒，超过后由拉取方清理
+const RETENTION: i64 = 3600;
+
+// 等待缺失的 id 的时间，单位秒，超过后认为对应的事务已经回滚
+const GAP_TIMEOUT: i64 = 60;
+
+pub(crate) const OP_ADD: &str = "add";
+pub(crate) const OP_REMOVE: &str = "remove";
+pub(crate) const OP_RELOAD: &str = "reload";
+
+type Callback = Box<dyn FnMut() + Send + Sync>;
+
+// 通过 casbin_rule_version 表在多个实例之间同步规则修改：
+// 本实例的修改由 SeaOrmAdapter::with_watcher 返回的 adapter 在修改规则的事务中写入表中，
+// WatcherSync 拉取其他实例的修改并应用到本地 enforcer。
+// 自增 id 不一定按提交顺序出现（sqlite 的写事务是串行的，不会有这个问题），
+// 拉取时记录跳过的 id，在 GAP_TIMEOUT 内提交的修改仍会被拉取到
+pub struct SeaOrmWatcher<C> {
+    conn: C,
+    instance: String,
+    callback: Arc<Mutex<Option<Callback>>>,
+}
+
+pub struct WatcherSync<C> {
+    conn: C,
+    instance: String,
+    last_id: i32,
+    // 小于 last_id 但还没有读到的 id 及发现的时间
+    gaps: BTreeMap<i32, i64>,
+    callback: Arc<Mutex<Option<Callback>>>,
+}
+
+impl<C: ConnectionTrait + Clone + Send + Sync + 'static> SeaOrmWatcher<C> {
+    // 需要在 tokio 运行时中创建；应在 enforcer 加载规则之前创建，避免漏掉加载期间其他实例的修改
+    pub async fn new(conn: C) -> Result<Self> {
+        migration::up(&conn)
+            .await
+            .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+
+        Ok(Self {
+            conn,
+            instance: uuid::Uuid::new_v4().to_string(),
+            callback: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    // 从当前最新的版本开始拉取
+    pub async fn sync(&self) -> Result<WatcherSync<C>> {
+        let last_id = version::Entity::find()
+            .select_only()
+            .column_as(version::Column::Id.max(), "id")
+            .into_tuple::<Option<i32>>()
+            .one(&self.conn)
+            .await
+            .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?
+            .flatten()
+            .unwrap_or(0);
+        Ok(WatcherSync {
+            conn: self.conn.clone(),
+            instance: self.instance.clone(),
+            last_id,
+            gaps: BTreeMap::new(),
+            callback: self.callback.clone(),
+        })
+    }
+}
+
+impl<C> SeaOrmWatcher<C> {
+    pub fn instance(&self) -> &str {
+        &self.instance
+    }
+}
+
+impl<C: Send + Sync> Watcher for SeaOrmWatcher<C> {
+    // 应用其他实例的修改后调用
+    fn set_update_callback(&mut self, cb: Callback) {
+        *self.callback.lock().unwrap() = Some(cb);
+    }
+
+    // 修改记录已经由 adapter 写入
+    fn update(&mut self, _d: EventData) {}
+}
+
+impl<C: ConnectionTrait> WatcherSync<C> {
+    // 拉取并应用其他实例的修改，返回应用的修改记录数
+    pub async fn poll(&mut self, enforcer: &RwLock<CachedEnforcer>) -> Result<usize> {
+        let now = now();
+        self.gaps.retain(|_, seen| now - *seen < GAP_TIMEOUT);
+        let mut condition = Condition::any().add(version::Column::Id.gt(self.last_id));
+        if !self.gaps.is_empty() {
+            condition = condition.add(version::Column::Id.is_in(self.gaps.keys().copied()));
+        }
+        let changes = version::Entity::find()
+            .filter(condition)
+            .order_by_asc(version::Column::Id)
+            .all(&self.conn)
+            .await
+            .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+        if changes.is_empty() {
+            return Ok(0);
+        }
+
+        let mut late = HashSet::new();
+        for change in &changes {
+            if self.gaps.remove(&change.id).is_some() {
+                late.insert(change.id);
+            } else {
+                self.gaps
+                    .extend((self.last_id + 1..change.id).map(|id| (id, now)));
+                self.last_id = change.id;
+            }
+        }
+
+        let changes: Vec<version::Model> = changes
+            .into_iter()
+            .filter(|c| c.instance != self.instance)
+            .collect();
+        if !changes.is_empty() {
+            let mut enforcer = enforcer.write().await;
+            // 晚提交的修改已经不能按顺序应用，直接重新加载
+            let reload = changes
+                .iter()
+                .any(|c| c.op == OP_RELOAD || late.contains(&c.id));
+            if reload || apply(&mut enforcer, &changes).await.is_err() {
+                enforcer.load_policy().await?;
+                enforcer.get_mut_cache().clear();
+            }
+            drop(enforcer);
+            if let Some(cb) = self.callback.lock().unwrap().as_mut() {
+                cb();
+            }
+        }
+
+        version::Entity::delete_many()
+            .filter(version::Column::CreatedAt.lt(now - RETENTION))
+            .exec(&self.conn)
+            .await
+            .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+        Ok(changes.len())
+    }
+
+    pub async fn run(mut self, enforcer: Arc<RwLock<CachedEnforcer>>, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            if let Err(err) = self.poll(&enforcer).await {
+                log::warn!("failed to sync casbin policy changes: {}", err);
+            }
+        }
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+// 整体保存或清空规则时写入 reload，其他实例直接重新加载
+pub(crate) async fn record<C: ConnectionTrait>(
+    conn: &C,
+    instance: &str,
+    op: &str,
+    sec: &str,
+    ptype: &str,
+    rules: &[Vec<String>],
+) -> Result<()> {
+    let rules = serde_json::to_string(rules)
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+
+    version::Entity::insert(version::ActiveModel {
+        id: NotSet,
+        instance: Set(instance.to_string()),
+        op: Set(op.to_string()),
+        sec: Set(sec.to_string()),
+        ptype: Set(ptype.to_string()),
+        rules: Set(rules),
+        created_at: Set(now()),
+    })
+    .exec_without_returning(conn)
+    .await
+    .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    Ok(())
+}
+
+// 应用时不写回数据库也不再通知，只跳过本地已经一致的规则
+async fn apply(enforcer: &mut CachedEnforcer, changes: &[version::Model]) -> Result<()> {
+    let auto_save = enforcer.has_auto_save_enabled();
+    let auto_notify = enforcer.has_auto_notify_watcher_enabled();
+    enforcer.enable_auto_save(false);
+    enforcer.enable_auto_notify_watcher(false);
+
+    let mut result = Ok(());
+    for change in changes {
+        result = apply_change(enforcer, change).await;
+        if result.is_err() {
+            break;
+        }
+    }
+
+    enforcer.enable_auto_save(auto_save);
+    enforcer.enable_auto_notify_watcher(auto_notify);
+    result
+}
+
+async fn apply_change(enforcer: &mut CachedEnforcer, change: &version::Model) -> Result<()> {
+    let rules: Vec<Vec<String>> = serde_json::from_str(&change.rules)
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    let ptype = change.ptype.as_str();
+    let grouping = change.sec == "g";
+    let exists = |enforcer: &CachedEnforcer, rule: &Vec<String>| match grouping {
+        true => enforcer.has_grouping_named_policy(ptype, rule.clone()),
+        false => enforcer.has_named_policy(ptype, rule.clone()),
+    };
+
+    match change.op.as_str() {
+        OP_ADD => {
+            let rules: Vec<Vec<String>> =
+                rules.into_iter().filter(|r| !exists(enforcer, r)).collect();
+            if rules.is_empty() {
+                return Ok(());
+            }
+            match grouping {
+                true => enforcer.add_named_grouping_policies(ptype, rules).await?,
+                false => enforcer.add_named_policies(ptype, rules).await?,
+            };
+        }
+        OP_REMOVE => {
+            let rules: Vec<Vec<String>> =
+                rules.into_iter().filter(|r| exists(enforcer, r)).collect();
+            if rules.is_empty() {
+                return Ok(());
+            }
+            match grouping {
+                true => {
+                    enforcer
+                        .remove_named_grouping_policies(ptype, rules)
+                        .await?
+                }
+                false => enforcer.remove_named_policies(ptype, rules).await?,
+            };
+        }
+        op => {
+            return Err(CasbinError::from(AdapterError(
+                format!("unknown policy change {}", op).into(),
+            )))
+        }
+    }
+    Ok(())
+}
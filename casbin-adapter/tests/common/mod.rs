@@ -0,0 +1,53 @@
+// 集成测试共用的数据库和规则构造
+#![allow(dead_code)]
+
+use casbin::Adapter;
+use casbin_adapter::SeaOrmAdapter;
+use sea_orm::{ConnectionTrait, Database, DatabaseConnection, Statement};
+
+pub async fn setup(name: &str) -> (SeaOrmAdapter<DatabaseConnection>, DatabaseConnection) {
+    let path =
+        std::env::temp_dir().join(format!("casbin-adapter-{}-{}.db", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let conn = Database::connect(format!("sqlite://{}?mode=rwc", path.display()))
+        .await
+        .unwrap();
+    let mut adapter = SeaOrmAdapter::new(conn.clone()).await.unwrap();
+    adapter
+        .add_policies(
+            "p",
+            "p",
+            vec![
+                rule(&["alice", "default", "/api/users", "GET"]),
+                rule(&["alice", "default", "/api/roles", "GET"]),
+                rule(&["bob", "default", "/api/users", "GET"]),
+            ],
+        )
+        .await
+        .unwrap();
+    (adapter, conn)
+}
+
+pub fn rule(values: &[&str]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+// 按 id 排序的 (id, v0, v1, v2, v3)
+pub async fn rows(conn: &DatabaseConnection) -> Vec<(i32, Vec<String>)> {
+    let stmt = Statement::from_string(
+        conn.get_database_backend(),
+        "SELECT id, v0, v1, v2, v3 FROM casbin_rule ORDER BY id",
+    );
+    conn.query_all(stmt)
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| {
+            let values = ["v0", "v1", "v2", "v3"]
+                .iter()
+                .map(|col| row.try_get::<String>("", col).unwrap())
+                .collect();
+            (row.try_get::<i32>("", "id").unwrap(), values)
+        })
+        .collect()
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use casbin::Adapter;
+use common::{rows, rule, setup};
+
+// 超过一个批次的规则，最后一条与已有规则重复
+fn batch_with_duplicate() -> Vec<Vec<String>> {
+    let mut rules: Vec<Vec<String>> = (0..250)
+        .map(|i| rule(&["carol", "default", &format!("/api/items/{}", i), "GET"]))
+        .collect();
+    rules.push(rule(&["alice", "default", "/api/users", "GET"]));
+    rules
+}
+
+#[tokio::test]
+async fn add_policies_rolls_back_partial_writes() {
+    let (mut adapter, conn) = setup("tx-add").await;
+    let before = rows(&conn).await;
+
+    let added = adapter
+        .add_policies(
+            "p",
+            "p",
+            vec![
+                rule(&["carol", "default", "/api/users", "GET"]),
+                rule(&["alice", "default", "/api/users", "GET"]),
+            ],
+        )
+        .await
+        .unwrap();
+    assert!(!added);
+    assert_eq!(rows(&conn).await, before);
+
+    let added = adapter
+        .add_policies("p", "p", batch_with_duplicate())
+        .await
+        .unwrap();
+    assert!(!added);
+    assert_eq!(rows(&conn).await, before);
+
+    let mut rules = batch_with_duplicate();
+    rules.pop();
+    assert!(adapter.add_policies("p", "p", rules).await.unwrap());
+    assert_eq!(rows(&conn).await.len(), before.len() + 250);
+}
+
+#[tokio::test]
+async fn remove_policies_rolls_back_partial_writes() {
+    let (mut adapter, conn) = setup("tx-remove").await;
+    let before = rows(&conn).await;
+
+    let removed = adapter
+        .remove_policies(
+            "p",
+            "p",
+            vec![
+                rule(&["alice", "default", "/api/users", "GET"]),
+                rule(&["carol", "default", "/api/users", "GET"]),
+            ],
+        )
+        .await
+        .unwrap();
+    assert!(!removed);
+    assert_eq!(rows(&conn).await, before);
+
+    let mut rules = batch_with_duplicate();
+    rules.pop();
+    assert!(adapter.add_policies("p", "p", rules).await.unwrap());
+    let mut rules = batch_with_duplicate();
+    rules.push(rule(&["carol", "default", "/api/missing", "GET"]));
+    let removed = adapter.remove_policies("p", "p", rules).await.unwrap();
+    assert!(!removed);
+    assert_eq!(rows(&conn).await.len(), before.len() + 250);
+
+    let removed = adapter
+        .remove_policies("p", "p", batch_with_duplicate())
+        .await
+        .unwrap();
+    assert!(removed);
+    let after: Vec<Vec<String>> = rows(&conn).await.into_iter().map(|(_, r)| r).collect();
+    assert_eq!(
+        after,
+        vec![
+            rule(&["alice", "default", "/api/roles", "GET"]),
+            rule(&["bob", "default", "/api/users", "GET"]),
+        ]
+    );
+}
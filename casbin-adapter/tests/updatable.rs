@@ -0,0 +1,180 @@
+mod common;
+
+use casbin_adapter::UpdatableAdapter;
+use common::{rows, rule, setup};
+
+#[tokio::test]
+async fn update_policy_in_place() {
+    let (mut adapter, conn) = setup("update-policy").await;
+    let before = rows(&conn).await;
+
+    let updated = adapter
+        .update_policy(
+            "p",
+            "p",
+            rule(&["alice", "default", "/api/users", "GET"]),
+            rule(&["alice", "default", "/api/users", "PUT"]),
+        )
+        .await
+        .unwrap();
+    assert!(updated);
+
+    let after = rows(&conn).await;
+    assert_eq!(after.len(), before.len());
+    assert_eq!(after[0].0, before[0].0);
+    assert_eq!(after[0].1, rule(&["alice", "default", "/api/users", "PUT"]));
+    assert_eq!(after[1..], before[1..]);
+}
+
+#[tokio::test]
+async fn update_policy_missing_or_conflicting() {
+    let (mut adapter, conn) = setup("update-policy-conflict").await;
+    let before = rows(&conn).await;
+
+    let missing = adapter
+        .update_policy(
+            "p",
+            "p",
+            rule(&["carol", "default", "/api/users", "GET"]),
+            rule(&["carol", "default", "/api/users", "PUT"]),
+        )
+        .await
+        .unwrap();
+    assert!(!missing);
+
+    let conflicting = adapter
+        .update_policy(
+            "p",
+            "p",
+            rule(&["alice", "default", "/api/users", "GET"]),
+            rule(&["bob", "default", "/api/users", "GET"]),
+        )
+        .await
+        .unwrap();
+    assert!(!conflicting);
+    assert_eq!(rows(&conn).await, before);
+}
+
+#[tokio::test]
+async fn update_policies_is_atomic() {
+    let (mut adapter, conn) = setup("update-policies").await;
+    let before = rows(&conn).await;
+
+    let updated = adapter
+        .update_policies(
+            "p",
+            "p",
+            vec![
+                rule(&["alice", "default", "/api/users", "GET"]),
+                rule(&["carol", "default", "/api/users", "GET"]),
+            ],
+            vec![
+                rule(&["alice", "default", "/api/users", "PUT"]),
+                rule(&["carol", "default", "/api/users", "PUT"]),
+            ],
+        )
+        .await
+        .unwrap();
+    assert!(!updated);
+    assert_eq!(rows(&conn).await, before);
+
+    let updated = adapter
+        .update_policies(
+            "p",
+            "p",
+            vec![
+                rule(&["alice", "default", "/api/users", "GET"]),
+                rule(&["alice", "default", "/api/roles", "GET"]),
+            ],
+            vec![
+                rule(&["admin", "default", "/api/users", "GET"]),
+                rule(&["admin", "default", "/api/roles", "GET"]),
+            ],
+        )
+        .await
+        .unwrap();
+    assert!(updated);
+    let after = rows(&conn).await;
+    assert_eq!(
+        after[0],
+        (
+            before[0].0,
+            rule(&["admin", "default", "/api/users", "GET"])
+        )
+    );
+    assert_eq!(
+        after[1],
+        (
+            before[1].0,
+            rule(&["admin", "default", "/api/roles", "GET"])
+        )
+    );
+    assert_eq!(after[2], before[2]);
+}
+
+#[tokio::test]
+async fn update_filtered_policies_returns_old_rules() {
+    let (mut adapter, conn) = setup("update-filtered").await;
+
+    let mut old_rules = adapter
+        .update_filtered_policies(
+            "p",
+            "p",
+            vec![rule(&["alice", "default", "/api/*", "GET"])],
+            0,
+            rule(&["alice"]),
+        )
+        .await
+        .unwrap();
+    old_rules.sort();
+    assert_eq!(
+        old_rules,
+        vec![
+            rule(&["alice", "default", "/api/roles", "GET"]),
+            rule(&["alice", "default", "/api/users", "GET"]),
+        ]
+    );
+
+    let mut after: Vec<Vec<String>> = rows(&conn).await.into_iter().map(|(_, r)| r).collect();
+    after.sort();
+    assert_eq!(
+        after,
+        vec![
+            rule(&["alice", "default", "/api/*", "GET"]),
+            rule(&["bob", "default", "/api/users", "GET"]),
+        ]
+    );
+
+    // 新规则和过滤范围外的规则冲突时不做修改
+    let old_rules = adapter
+        .update_filtered_policies(
+            "p",
+            "p",
+            vec![rule(&["bob", "default", "/api/users", "GET"])],
+            0,
+            rule(&["alice"]),
+        )
+        .await
+        .unwrap();
+    assert!(old_rules.is_empty());
+    assert_eq!(rows(&conn).await.len(), 2);
+}
+
+#[tokio::test]
+async fn update_filtered_policies_rejects_invalid_rules() {
+    let (mut adapter, conn) = setup("update-filtered-invalid").await;
+    let before = rows(&conn).await;
+
+    let old_rules = adapter
+        .update_filtered_policies(
+            "p",
+            "p",
+            vec![rule(&["alice", "default", "/api/*", "GET"]), vec![]],
+            0,
+            rule(&["alice"]),
+        )
+        .await
+        .unwrap();
+    assert!(old_rules.is_empty());
+    assert_eq!(rows(&conn).await, before);
+}
@@ -0,0 +1,228 @@
+mod common;
+
+use std::sync::Arc;
+
+use casbin::{Adapter, CachedEnforcer, CoreApi, DefaultModel, MgmtApi};
+use casbin_adapter::{SeaOrmAdapter, SeaOrmWatcher, UpdatableAdapter, WatcherSync};
+use sea_orm::{ConnectionTrait, Database, DatabaseConnection};
+use tokio::sync::RwLock;
+
+use common::rule;
+
+const MODEL: &str = "[request_definition]
+r = sub, dom, obj, act
+
+[policy_definition]
+p = sub, dom, obj, act
+
+[role_definition]
+g = _, _, _
+
+[policy_effect]
+e = some(where (p.eft == allow))
+
+[matchers]
+m = g(r.sub, p.sub, r.dom) && r.dom == p.dom && keyMatch2(r.obj, p.obj) && r.act == p.act";
+
+type Shared = Arc<RwLock<CachedEnforcer>>;
+
+// 每个实例使用独立的连接池打开同一个 sqlite 文件
+async fn instance(path: &str) -> (Shared, WatcherSync<DatabaseConnection>) {
+    let conn = Database::connect(format!("sqlite://{}?mode=rwc", path))
+        .await
+        .unwrap();
+    let watcher = SeaOrmWatcher::new(conn.clone()).await.unwrap();
+    let sync = watcher.sync().await.unwrap();
+    let model = DefaultModel::from_str(MODEL).await.unwrap();
+    let adapter = SeaOrmAdapter::new(conn)
+        .await
+        .unwrap()
+        .with_watcher(&watcher);
+    let mut enforcer = CachedEnforcer::new(model, adapter).await.unwrap();
+    enforcer.set_watcher(Box::new(watcher));
+    (Arc::new(RwLock::new(enforcer)), sync)
+}
+
+async fn pair(
+    name: &str,
+) -> (
+    (Shared, WatcherSync<DatabaseConnection>),
+    (Shared, WatcherSync<DatabaseConnection>),
+) {
+    let path =
+        std::env::temp_dir().join(format!("casbin-watcher-{}-{}.db", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let path = path.display().to_string();
+    (instance(&path).await, instance(&path).await)
+}
+
+async fn allowed(enforcer: &Shared, sub: &str, obj: &str, act: &str) -> bool {
+    enforcer
+        .write()
+        .await
+        .enforce_mut((sub, "default", obj, act))
+        .unwrap()
+}
+
+// 修改记录和规则在同一个事务中提交，修改返回后对端立即可以拉取到
+async fn poll(sync: &mut WatcherSync<DatabaseConnection>, enforcer: &Shared) -> usize {
+    sync.poll(enforcer).await.unwrap()
+}
+
+#[tokio::test]
+async fn policy_changes_reach_peer() {
+    let ((a, mut a_sync), (b, mut b_sync)) = pair("policy").await;
+
+    // 先访问一次，确认对端的缓存会被清理
+    assert!(!allowed(&b, "alice", "/api/users/1", "GET").await);
+
+    a.write()
+        .await
+        .add_policies(vec![
+            rule(&["alice", "default", "/api/users/*", "GET"]),
+            rule(&["alice", "default", "/api/roles/*", "GET"]),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(poll(&mut b_sync, &b).await, 1);
+    assert!(allowed(&b, "alice", "/api/users/1", "GET").await);
+    assert!(allowed(&b, "alice", "/api/roles/1", "GET").await);
+
+    a.write()
+        .await
+        .remove_policy(rule(&["alice", "default", "/api/users/*", "GET"]))
+        .await
+        .unwrap();
+    assert_eq!(poll(&mut b_sync, &b).await, 1);
+    assert!(!allowed(&b, "alice", "/api/users/1", "GET").await);
+    assert!(allowed(&b, "alice", "/api/roles/1", "GET").await);
+
+    // 自己的修改不会重复应用，对端应用的修改也不会再次写入记录
+    assert_eq!(poll(&mut a_sync, &a).await, 0);
+    assert_eq!(poll(&mut b_sync, &b).await, 0);
+}
+
+#[tokio::test]
+async fn grouping_changes_reach_peer() {
+    let ((a, _a_sync), (b, mut b_sync)) = pair("grouping").await;
+
+    {
+        let mut a = a.write().await;
+        a.add_policy(rule(&["admin", "default", "/api/*", "DELETE"]))
+            .await
+            .unwrap();
+        a.add_grouping_policy(rule(&["bob", "admin", "default"]))
+            .await
+            .unwrap();
+    }
+    assert_eq!(poll(&mut b_sync, &b).await, 2);
+    assert!(allowed(&b, "bob", "/api/users", "DELETE").await);
+
+    a.write()
+        .await
+        .remove_grouping_policy(rule(&["bob", "admin", "default"]))
+        .await
+        .unwrap();
+    assert_eq!(poll(&mut b_sync, &b).await, 1);
+    assert!(!allowed(&b, "bob", "/api/users", "DELETE").await);
+}
+
+#[tokio::test]
+async fn saved_policy_reloads_peer() {
+    let ((a, _a_sync), (b, mut b_sync)) = pair("save").await;
+
+    b.write()
+        .await
+        .add_policy(rule(&["carol", "default", "/api/menus", "GET"]))
+        .await
+        .unwrap();
+    {
+        let mut a = a.write().await;
+        a.load_policy().await.unwrap();
+        a.get_mut_model().clear_policy();
+        a.get_mut_model()
+            .add_policy("p", "p", rule(&["dave", "default", "/api/menus", "GET"]));
+        a.save_policy().await.unwrap();
+    }
+    assert_eq!(poll(&mut b_sync, &b).await, 1);
+    assert!(!allowed(&b, "carol", "/api/menus", "GET").await);
+    assert!(allowed(&b, "dave", "/api/menus", "GET").await);
+}
+
+#[tokio::test]
+async fn adapter_changes_reach_peer() {
+    let path =
+        std::env::temp_dir().join(format!("casbin-watcher-adapter-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let path = path.display().to_string();
+    let (b, mut b_sync) = instance(&path).await;
+    let conn = Database::connect(format!("sqlite://{}?mode=rwc", path))
+        .await
+        .unwrap();
+    let watcher = SeaOrmWatcher::new(conn.clone()).await.unwrap();
+    let mut adapter = SeaOrmAdapter::new(conn)
+        .await
+        .unwrap()
+        .with_watcher(&watcher);
+
+    let get = rule(&["alice", "default", "/api/users", "GET"]);
+    let put = rule(&["alice", "default", "/api/users", "PUT"]);
+    assert!(adapter.add_policy("p", "p", get.clone()).await.unwrap());
+    assert_eq!(poll(&mut b_sync, &b).await, 1);
+    assert!(allowed(&b, "alice", "/api/users", "GET").await);
+
+    // 回滚的批量修改不会留下修改记录
+    let batch = vec![rule(&["bob", "default", "/api/users", "GET"]), get.clone()];
+    assert!(!adapter.add_policies("p", "p", batch).await.unwrap());
+    assert_eq!(poll(&mut b_sync, &b).await, 0);
+    assert!(!allowed(&b, "bob", "/api/users", "GET").await);
+
+    // 更新记录为先删除再添加
+    assert!(adapter.update_policy("p", "p", get, put).await.unwrap());
+    assert_eq!(poll(&mut b_sync, &b).await, 2);
+    assert!(!allowed(&b, "alice", "/api/users", "GET").await);
+    assert!(allowed(&b, "alice", "/api/users", "PUT").await);
+
+    let removed = adapter
+        .remove_filtered_policy("p", "p", 0, vec!["alice".to_string()])
+        .await
+        .unwrap();
+    assert!(removed);
+    assert_eq!(poll(&mut b_sync, &b).await, 1);
+    assert!(!allowed(&b, "alice", "/api/users", "PUT").await);
+}
+
+#[tokio::test]
+async fn late_commits_are_not_skipped() {
+    let path = std::env::temp_dir().join(format!("casbin-watcher-gap-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let path = path.display().to_string();
+    let (b, mut b_sync) = instance(&path).await;
+    let conn = Database::connect(format!("sqlite://{}?mode=rwc", path))
+        .await
+        .unwrap();
+
+    // 模拟其他实例的两个事务，id 较小的晚提交
+    let commit = |id: i32, sub: &'static str| {
+        let conn = conn.clone();
+        async move {
+            let sql = format!(
+                "INSERT INTO casbin_rule (ptype, v0, v1, v2, v3, v4, v5) \
+                 VALUES ('p', '{sub}', 'default', '/api/users', 'GET', '', '');
+                 INSERT INTO casbin_rule_version (id, instance, op, sec, ptype, rules, created_at) \
+                 VALUES ({id}, 'other', 'add', 'p', 'p', \
+                 '[[\"{sub}\",\"default\",\"/api/users\",\"GET\"]]', 0)"
+            );
+            conn.execute_unprepared(&sql).await.unwrap();
+        }
+    };
+    commit(2, "alice").await;
+    assert_eq!(b_sync.poll(&b).await.unwrap(), 1);
+    assert!(allowed(&b, "alice", "/api/users", "GET").await);
+
+    commit(1, "bob").await;
+    assert_eq!(b_sync.poll(&b).await.unwrap(), 1);
+    assert!(allowed(&b, "bob", "/api/users", "GET").await);
+    assert!(allowed(&b, "alice", "/api/users", "GET").await);
+    assert_eq!(b_sync.poll(&b).await.unwrap(), 0);
+}
@@ -1,3 +1,16 @@
 pub mod cipher_slot;
+pub mod sys_api_key;
+pub mod sys_dept;
+pub mod sys_domain;
 pub mod sys_menu;
-pub mod sys_user;
\ No newline at end of file
+pub mod sys_menu_operation;
+pub mod sys_password_history;
+pub mod sys_recovery_code;
+pub mod sys_refresh_token;
+pub mod sys_revoked_token;
+pub mod sys_role;
+pub mod sys_role_dept;
+pub mod sys_user;
+pub mod sys_user_dept;
+pub mod sys_user_role;
+pub mod sys_user_totp;
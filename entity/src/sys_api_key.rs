@@ -0,0 +1,31 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "sys_api_key")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub name: String,
+    #[sea_orm(unique)]
+    pub prefix: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    // 用户级 key 对应的账号，域级 key 为空
+    pub account: String,
+    pub domain: String,
+    // casbin 中使用的 subject
+    pub subject: String,
+    // 逗号分隔，为空时不额外限制
+    pub scopes: String,
+    // 0 表示永不过期
+    pub expires_at: i64,
+    pub revoked: bool,
+    pub last_used_at: i64,
+    pub created_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,15 +1,38 @@
 use sea_orm::entity::prelude::*;
+use sea_orm::Iterable;
 use serde::{Deserialize, Serialize};
 
-
-#[derive(Debug,Deserialize, Serialize)]
+// 菜单上的操作，授权给角色时对应 casbin 策略中的请求方法
+#[derive(
+    Copy, Clone, Debug, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, Deserialize, Serialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
 pub enum OperatorType {
+    #[sea_orm(string_value = "GET")]
     GET,
+    #[sea_orm(string_value = "POST")]
     POST,
+    #[sea_orm(string_value = "UPDATE")]
     UPDATE,
+    #[sea_orm(string_value = "DELETE")]
     DELETE,
 }
 
+impl OperatorType {
+    pub fn method(&self) -> &'static str {
+        match self {
+            OperatorType::GET => "GET",
+            OperatorType::POST => "POST",
+            OperatorType::UPDATE => "PUT",
+            OperatorType::DELETE => "DELETE",
+        }
+    }
+
+    pub fn from_method(method: &str) -> Option<Self> {
+        OperatorType::iter().find(|op| op.method() == method)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Deserialize, Serialize)]
 #[sea_orm(table_name = "sys_menu")]
 pub struct Model {
@@ -22,9 +45,11 @@ pub struct Model {
     pub icon: String,
     pub state: bool,
     pub parent_id: i32,
+    // 同级菜单按 sort 升序排列
+    pub sort: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {}
 
-impl ActiveModelBehavior for ActiveModel {}
\ No newline at end of file
+impl ActiveModelBehavior for ActiveModel {}
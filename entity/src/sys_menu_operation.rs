@@ -0,0 +1,21 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::sys_menu::OperatorType;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "sys_menu_operation")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub menu_id: i32,
+    pub operator: OperatorType,
+    // 按钮级权限标识，例如 "system:user:add"，前端据此控制按钮显示
+    pub code: String,
+    pub title: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,6 +1,31 @@
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
+// 角色的数据范围，决定列表接口能看到哪些行
+#[derive(
+    Copy, Clone, Debug, Default, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Serialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(32))")]
+#[serde(rename_all = "snake_case")]
+pub enum DataScope {
+    // 全部数据
+    #[default]
+    #[sea_orm(string_value = "all")]
+    All,
+    // 本部门
+    #[sea_orm(string_value = "dept")]
+    Dept,
+    // 本部门及以下
+    #[sea_orm(string_value = "dept_and_children")]
+    DeptAndChildren,
+    // 仅本人
+    #[sea_orm(string_value = "only_self")]
+    OnlySelf,
+    // 自定义部门，见 sys_role_dept
+    #[sea_orm(string_value = "custom")]
+    Custom,
+}
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Deserialize, Serialize)]
 #[sea_orm(table_name = "sys_role")]
 pub struct Model {
@@ -10,9 +35,10 @@ pub struct Model {
     pub state: bool,
     pub parent_id: i32,
     pub domain: String,
+    pub data_scope: DataScope,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {}
 
-impl ActiveModelBehavior for ActiveModel {}
\ No newline at end of file
+impl ActiveModelBehavior for ActiveModel {}
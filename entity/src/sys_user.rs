@@ -7,6 +7,7 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     pub account: String,
+    #[serde(skip_serializing)]
     pub password: String,
     pub name: String,
     pub domain: String,
@@ -15,9 +16,12 @@ pub struct Model {
     pub phone: String,
     pub pw_reset_count: i32,
     pub state: bool,
+    pub login_fail_count: i32,
+    pub locked_until: i64,
+    pub must_change_password: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {}
 
-impl ActiveModelBehavior for ActiveModel {}
\ No newline at end of file
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,12 +1,38 @@
 pub use sea_orm_migration::prelude::*;
 
-mod m20220101_000001_create_table;
+mod m20220101_000000_rename_legacy_tables;
+mod m20220101_000001_create_sys_user;
+mod m20220101_000002_create_sys_refresh_token;
+mod m20220101_000003_create_sys_revoked_token;
+mod m20220101_000004_add_sys_user_lockout;
+mod m20220101_000005_create_sys_user_totp;
+mod m20220101_000006_create_sys_api_key;
+mod m20220101_000007_create_sys_role;
+mod m20220101_000008_create_sys_menu;
+mod m20220101_000009_create_sys_menu_operation;
+mod m20220101_000010_create_sys_dept;
+mod m20220101_000011_add_sys_role_data_scope;
+mod m20220101_000012_create_sys_domain;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000000_rename_legacy_tables::Migration),
+            Box::new(m20220101_000001_create_sys_user::Migration),
+            Box::new(m20220101_000002_create_sys_refresh_token::Migration),
+            Box::new(m20220101_000003_create_sys_revoked_token::Migration),
+            Box::new(m20220101_000004_add_sys_user_lockout::Migration),
+            Box::new(m20220101_000005_create_sys_user_totp::Migration),
+            Box::new(m20220101_000006_create_sys_api_key::Migration),
+            Box::new(m20220101_000007_create_sys_role::Migration),
+            Box::new(m20220101_000008_create_sys_menu::Migration),
+            Box::new(m20220101_000009_create_sys_menu_operation::Migration),
+            Box::new(m20220101_000010_create_sys_dept::Migration),
+            Box::new(m20220101_000011_add_sys_role_data_scope::Migration),
+            Box::new(m20220101_000012_create_sys_domain::Migration),
+        ]
     }
 }
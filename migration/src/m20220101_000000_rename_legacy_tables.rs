@@ -0,0 +1,90 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+// 旧版本 sqlite 数据库中与现在同名但结构不兼容的表（id 为 TEXT，没有 domain 等列）
+const LEGACY_TABLES: [&str; 4] = ["sys_user", "sys_role", "sys_user_role", "casbin_rule"];
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    // 把旧表重命名为 legacy_ 开头的表保留数据，后续的迁移重新创建这些表
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let conn = manager.get_connection();
+        if conn.get_database_backend() != DbBackend::Sqlite {
+            return Ok(());
+        }
+        for table in LEGACY_TABLES {
+            let stmt = Statement::from_string(
+                DbBackend::Sqlite,
+                format!("SELECT type FROM pragma_table_info('{table}') WHERE name = 'id'"),
+            );
+            let legacy = match conn.query_one(stmt).await? {
+                Some(row) => row
+                    .try_get::<String>("", "type")?
+                    .eq_ignore_ascii_case("TEXT"),
+                None => false,
+            };
+            if legacy {
+                manager
+                    .rename_table(
+                        Table::rename()
+                            .table(Alias::new(table), Alias::new(format!("legacy_{table}")))
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    // 重命名后的旧表不再恢复
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm_migration::sea_orm::{ConnectionTrait, Database, DbBackend, Statement};
+
+    use crate::{Migrator, MigratorTrait};
+
+    async fn columns(conn: &impl ConnectionTrait, table: &str) -> Vec<String> {
+        let stmt = Statement::from_string(
+            DbBackend::Sqlite,
+            format!("SELECT name FROM pragma_table_info('{table}')"),
+        );
+        let rows = conn.query_all(stmt).await.unwrap();
+        rows.iter()
+            .map(|row| row.try_get::<String>("", "name").unwrap())
+            .collect()
+    }
+
+    // 发布的 resource/db/sqlite.db 仍是旧的表结构
+    #[async_std::test]
+    async fn migrate_shipped_database() {
+        let shipped = concat!(env!("CARGO_MANIFEST_DIR"), "/../resource/db/sqlite.db");
+        let path = std::env::temp_dir().join(format!("migration-{}.db", std::process::id()));
+        std::fs::copy(shipped, &path).unwrap();
+        let conn = Database::connect(format!("sqlite://{}?mode=rwc", path.display()))
+            .await
+            .unwrap();
+
+        Migrator::up(&conn, None).await.unwrap();
+        for table in ["sys_user", "sys_role"] {
+            assert!(columns(&conn, table).await.contains(&"domain".to_string()));
+            assert!(!columns(&conn, &format!("legacy_{table}")).await.is_empty());
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[async_std::test]
+    async fn new_database_has_no_legacy_tables() {
+        let conn = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&conn, None).await.unwrap();
+        assert!(columns(&conn, "sys_user").await.contains(&"domain".to_string()));
+        assert!(columns(&conn, "legacy_sys_user").await.is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysUser::Table)
+                    .col(
+                        ColumnDef::new(SysUser::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SysUser::Account).string_len(64).not_null())
+                    .col(ColumnDef::new(SysUser::Password).string_len(255).not_null())
+                    .col(ColumnDef::new(SysUser::Name).string_len(64).not_null())
+                    .col(ColumnDef::new(SysUser::Domain).string_len(64).not_null())
+                    .col(
+                        ColumnDef::new(SysUser::Avatar)
+                            .string_len(255)
+                            .not_null()
+                            .default(""),
+                    )
+                    .col(
+                        ColumnDef::new(SysUser::Email)
+                            .string_len(128)
+                            .not_null()
+                            .default(""),
+                    )
+                    .col(
+                        ColumnDef::new(SysUser::Phone)
+                            .string_len(32)
+                            .not_null()
+                            .default(""),
+                    )
+                    .col(
+                        ColumnDef::new(SysUser::PwResetCount)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(SysUser::State)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_sys_user_domain_account")
+                    .table(SysUser::Table)
+                    .col(SysUser::Domain)
+                    .col(SysUser::Account)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SysUser::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SysUser {
+    Table,
+    Id,
+    Account,
+    Password,
+    Name,
+    Domain,
+    Avatar,
+    Email,
+    Phone,
+    PwResetCount,
+    State,
+}
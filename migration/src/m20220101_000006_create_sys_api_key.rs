@@ -0,0 +1,95 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysApiKey::Table)
+                    .col(
+                        ColumnDef::new(SysApiKey::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SysApiKey::Name).string_len(64).not_null())
+                    .col(
+                        ColumnDef::new(SysApiKey::Prefix)
+                            .string_len(32)
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(SysApiKey::KeyHash)
+                            .string_len(128)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SysApiKey::Account)
+                            .string_len(64)
+                            .not_null()
+                            .default(""),
+                    )
+                    .col(ColumnDef::new(SysApiKey::Domain).string_len(64).not_null())
+                    .col(
+                        ColumnDef::new(SysApiKey::Subject)
+                            .string_len(125)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(SysApiKey::Scopes).text().not_null())
+                    .col(
+                        ColumnDef::new(SysApiKey::ExpiresAt)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(SysApiKey::Revoked)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(
+                        ColumnDef::new(SysApiKey::LastUsedAt)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(SysApiKey::CreatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SysApiKey::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SysApiKey {
+    Table,
+    Id,
+    Name,
+    Prefix,
+    KeyHash,
+    Account,
+    Domain,
+    Subject,
+    Scopes,
+    ExpiresAt,
+    Revoked,
+    LastUsedAt,
+    CreatedAt,
+}
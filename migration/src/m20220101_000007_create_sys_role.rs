@@ -0,0 +1,112 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysRole::Table)
+                    .col(
+                        ColumnDef::new(SysRole::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SysRole::Name).string_len(64).not_null())
+                    .col(
+                        ColumnDef::new(SysRole::State)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(
+                        ColumnDef::new(SysRole::ParentId)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(ColumnDef::new(SysRole::Domain).string_len(64).not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_sys_role_domain_name")
+                    .table(SysRole::Table)
+                    .col(SysRole::Domain)
+                    .col(SysRole::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysUserRole::Table)
+                    .col(
+                        ColumnDef::new(SysUserRole::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SysUserRole::UserId).integer().not_null())
+                    .col(ColumnDef::new(SysUserRole::RoleId).integer().not_null())
+                    .col(
+                        ColumnDef::new(SysUserRole::CreatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_sys_user_role_user_role")
+                    .table(SysUserRole::Table)
+                    .col(SysUserRole::UserId)
+                    .col(SysUserRole::RoleId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SysUserRole::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(SysRole::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SysRole {
+    Table,
+    Id,
+    Name,
+    State,
+    ParentId,
+    Domain,
+}
+
+#[derive(DeriveIden)]
+enum SysUserRole {
+    Table,
+    Id,
+    UserId,
+    RoleId,
+    CreatedAt,
+}
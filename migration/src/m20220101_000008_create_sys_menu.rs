@@ -0,0 +1,83 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysMenu::Table)
+                    .col(
+                        ColumnDef::new(SysMenu::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SysMenu::Path).string_len(255).not_null())
+                    .col(ColumnDef::new(SysMenu::Name).string_len(64).not_null())
+                    .col(ColumnDef::new(SysMenu::Title).string_len(64).not_null())
+                    .col(ColumnDef::new(SysMenu::Domain).string_len(64).not_null())
+                    .col(
+                        ColumnDef::new(SysMenu::Icon)
+                            .string_len(64)
+                            .not_null()
+                            .default(""),
+                    )
+                    .col(
+                        ColumnDef::new(SysMenu::State)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(
+                        ColumnDef::new(SysMenu::ParentId)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(SysMenu::Sort)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_sys_menu_domain_parent")
+                    .table(SysMenu::Table)
+                    .col(SysMenu::Domain)
+                    .col(SysMenu::ParentId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SysMenu::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SysMenu {
+    Table,
+    Id,
+    Path,
+    Name,
+    Title,
+    Domain,
+    Icon,
+    State,
+    ParentId,
+    Sort,
+}
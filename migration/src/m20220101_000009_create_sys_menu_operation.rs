@@ -0,0 +1,72 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysMenuOperation::Table)
+                    .col(
+                        ColumnDef::new(SysMenuOperation::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(SysMenuOperation::MenuId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SysMenuOperation::Operator)
+                            .string_len(16)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SysMenuOperation::Code)
+                            .string_len(128)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SysMenuOperation::Title)
+                            .string_len(64)
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_sys_menu_operation_menu_operator")
+                    .table(SysMenuOperation::Table)
+                    .col(SysMenuOperation::MenuId)
+                    .col(SysMenuOperation::Operator)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SysMenuOperation::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SysMenuOperation {
+    Table,
+    Id,
+    MenuId,
+    Operator,
+    Code,
+    Title,
+}
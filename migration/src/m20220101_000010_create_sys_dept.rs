@@ -0,0 +1,125 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysDept::Table)
+                    .col(
+                        ColumnDef::new(SysDept::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SysDept::Name).string_len(64).not_null())
+                    .col(
+                        ColumnDef::new(SysDept::ParentId)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(SysDept::Leader)
+                            .string_len(64)
+                            .not_null()
+                            .default(""),
+                    )
+                    .col(
+                        ColumnDef::new(SysDept::Sort)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(SysDept::State)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(ColumnDef::new(SysDept::Domain).string_len(64).not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_sys_dept_domain_parent")
+                    .table(SysDept::Table)
+                    .col(SysDept::Domain)
+                    .col(SysDept::ParentId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysUserDept::Table)
+                    .col(
+                        ColumnDef::new(SysUserDept::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SysUserDept::UserId).integer().not_null())
+                    .col(ColumnDef::new(SysUserDept::DeptId).integer().not_null())
+                    .col(
+                        ColumnDef::new(SysUserDept::CreatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_sys_user_dept_user_dept")
+                    .table(SysUserDept::Table)
+                    .col(SysUserDept::UserId)
+                    .col(SysUserDept::DeptId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SysUserDept::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(SysDept::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SysDept {
+    Table,
+    Id,
+    Name,
+    ParentId,
+    Leader,
+    Sort,
+    State,
+    Domain,
+}
+
+#[derive(DeriveIden)]
+enum SysUserDept {
+    Table,
+    Id,
+    UserId,
+    DeptId,
+    CreatedAt,
+}
@@ -0,0 +1,81 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 已有角色默认为全部数据，保持升级前的行为
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SysRole::Table)
+                    .add_column(
+                        ColumnDef::new(SysRole::DataScope)
+                            .string_len(32)
+                            .not_null()
+                            .default("all"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysRoleDept::Table)
+                    .col(
+                        ColumnDef::new(SysRoleDept::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SysRoleDept::RoleId).integer().not_null())
+                    .col(ColumnDef::new(SysRoleDept::DeptId).integer().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_sys_role_dept_role_dept")
+                    .table(SysRoleDept::Table)
+                    .col(SysRoleDept::RoleId)
+                    .col(SysRoleDept::DeptId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SysRoleDept::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SysRole::Table)
+                    .drop_column(SysRole::DataScope)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SysRole {
+    Table,
+    DataScope,
+}
+
+#[derive(DeriveIden)]
+enum SysRoleDept {
+    Table,
+    Id,
+    RoleId,
+    DeptId,
+}
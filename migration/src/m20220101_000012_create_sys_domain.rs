@@ -0,0 +1,87 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysDomain::Table)
+                    .col(
+                        ColumnDef::new(SysDomain::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(SysDomain::Code)
+                            .string_len(64)
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(SysDomain::Name).string_len(64).not_null())
+                    .col(
+                        ColumnDef::new(SysDomain::State)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(
+                        ColumnDef::new(SysDomain::CreatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // 为已有用户所在的域补齐记录，升级后这些域保持可用
+        let backfill = Query::insert()
+            .into_table(SysDomain::Table)
+            .columns([
+                SysDomain::Code,
+                SysDomain::Name,
+                SysDomain::State,
+                SysDomain::CreatedAt,
+            ])
+            .select_from(
+                Query::select()
+                    .distinct()
+                    .column(SysUser::Domain)
+                    .column(SysUser::Domain)
+                    .expr(Expr::val(true))
+                    .expr(Expr::val(0i64))
+                    .from(SysUser::Table)
+                    .to_owned(),
+            )
+            .map_err(|e| DbErr::Migration(e.to_string()))?
+            .to_owned();
+        manager.exec_stmt(backfill).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SysDomain::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SysDomain {
+    Table,
+    Id,
+    Code,
+    Name,
+    State,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum SysUser {
+    Table,
+    Domain,
+}
@@ -6,7 +6,9 @@ use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use std::{ffi::OsString, path::PathBuf, vec};
 
-// casbin rbac model
+use crate::constants::{AUTHENTICATED_ROUTES, IGNORE_ROUTES};
+
+// casbin rbac model，p 策略的 obj 按 keyMatch2 匹配，":id" 和 "*" 为通配符
 pub static CASBIN_MODEL: &str = "[request_definition]
 r = sub, dom, obj, act
 
@@ -20,7 +22,7 @@ g = _, _, _
 e = some(where (p.eft == allow))
 
 [matchers]
-m = g(r.sub, p.sub, r.dom) && r.dom == p.dom && r.obj == p.obj && r.act == p.act";
+m = g(r.sub, p.sub, r.dom) && r.dom == p.dom && keyMatch2(r.obj, p.obj) && r.act == p.act";
 
 // 只要是配置文件中的配置项，都可以通过这个结构体来获取，
 // 只要读取一次值后保存到内存，一直可供使用
@@ -34,6 +36,11 @@ pub struct Config {
     pub server: Server,
     pub log: Log,
     pub db: DB,
+    pub jwt: Jwt,
+    #[serde(default)]
+    pub auth: Auth,
+    pub password: Password,
+    pub totp: Totp,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
@@ -48,6 +55,63 @@ pub struct Server {
     pub tls: bool,
     pub pem_cert_path: String,
     pub pem_key_path: String,
+    // 检查证书文件是否变化的间隔，单位秒，0 表示不监听
+    #[serde(default)]
+    pub reload_interval: u64,
+    #[serde(default)]
+    pub mtls: Mtls,
+    #[serde(default)]
+    pub http: Http,
+}
+
+// tls 开启时额外监听的明文端口
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Http {
+    pub enabled: bool,
+    pub port: u16,
+    // 是否用 308 跳转到 https
+    #[serde(default)]
+    pub redirect: bool,
+    // 不跳转、直接在明文端口处理的路由，例如健康检查
+    #[serde(default)]
+    pub redirect_exclude: Vec<IgnoreRoute>,
+}
+
+// 客户端证书认证，只在 tls 开启时生效
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Mtls {
+    pub enabled: bool,
+    // 签发客户端证书的 CA，pem 格式，可以包含多个证书
+    pub client_ca_path: String,
+    // 为 true 时握手阶段拒绝没有客户端证书的连接，否则客户端证书是可选的
+    #[serde(default)]
+    pub required: bool,
+    // 从证书中取身份的位置
+    #[serde(default)]
+    pub identity: CertIdentity,
+    // 证书身份映射为 sys_user 账号，或直接作为 casbin subject
+    #[serde(default)]
+    pub mapping: CertMapping,
+    // 证书身份所属的域
+    pub domain: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CertIdentity {
+    #[default]
+    CommonName,
+    Dns,
+    Email,
+    Uri,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CertMapping {
+    #[default]
+    User,
+    Subject,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
@@ -66,7 +130,151 @@ pub struct DB {
     pub log_level: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Jwt {
+    // HS256 RS256 ES256
+    pub algorithm: String,
+    // HS256 使用的密钥
+    pub secret: String,
+    // RS256/ES256 使用的 pem 私钥和公钥
+    pub private_key_path: String,
+    pub public_key_path: String,
+    pub issuer: String,
+    pub audience: String,
+    // token 有效期，单位秒
+    pub expire: u64,
+    // 刷新令牌有效期，单位秒
+    pub refresh_expire: u64,
+    // 两步验证中间 token 有效期，单位秒
+    pub mfa_expire: u64,
+    // 校验 exp/nbf 时允许的时钟误差，单位秒
+    pub leeway: u64,
+}
+
+// Argon2id 参数，不同 profile 可以配置不同的强度
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Password {
+    // 内存开销，单位 KiB
+    pub memory_cost: u32,
+    // 迭代次数
+    pub time_cost: u32,
+    // 并行度
+    pub parallelism: u32,
+    pub policy: PasswordPolicy,
+    pub lockout: Lockout,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    // 至少包含的字符种类数：小写、大写、数字、符号
+    pub min_classes: usize,
+    // 不能与最近几次使用过的密码相同，0 表示不检查
+    pub history: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Lockout {
+    // 连续登录失败多少次后锁定，0 表示不锁定
+    pub max_failures: i32,
+    // 锁定时长，单位秒
+    pub duration: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Totp {
+    // 认证器 App 中显示的发行方
+    pub issuer: String,
+    // 允许前后偏差的时间片数量
+    pub skew: u64,
+    // 启用两步验证时生成的恢复码数量
+    pub recovery_codes: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Auth {
+    // 不需要登录和鉴权即可访问的路由
+    #[serde(default = "default_ignore_routes")]
+    pub ignore_routes: Vec<IgnoreRoute>,
+    // 登录后即可访问、不做 casbin 鉴权的路由
+    #[serde(default = "default_authenticated_routes")]
+    pub authenticated_routes: Vec<IgnoreRoute>,
+    // 平台域，超级管理员所在的域，不能被禁用或删除
+    #[serde(default = "default_platform_domain")]
+    pub platform_domain: String,
+    // 平台域中拥有该角色的用户为超级管理员，可以管理域并通过 X-Domain 头跨域操作
+    #[serde(default = "default_super_admin_role")]
+    pub super_admin_role: String,
+    // 启动时加入超级管理员角色的平台域账号
+    #[serde(default)]
+    pub super_admins: Vec<String>,
+    // 多实例部署时拉取其他实例规则修改的间隔，单位秒，0 表示不同步
+    #[serde(default = "default_policy_sync_interval")]
+    pub policy_sync_interval: u64,
+}
+
+impl Default for Auth {
+    fn default() -> Self {
+        Auth {
+            ignore_routes: default_ignore_routes(),
+            authenticated_routes: default_authenticated_routes(),
+            platform_domain: default_platform_domain(),
+            super_admin_role: default_super_admin_role(),
+            super_admins: vec![],
+            policy_sync_interval: default_policy_sync_interval(),
+        }
+    }
+}
+
+fn default_platform_domain() -> String {
+    "default".to_string()
+}
+
+fn default_super_admin_role() -> String {
+    "super_admin".to_string()
+}
+
+fn default_policy_sync_interval() -> u64 {
+    5
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IgnoreRoute {
+    pub path: String,
+    #[serde(default, rename = "match")]
+    pub match_type: MatchType,
+    // 为空时匹配所有请求方法
+    #[serde(default)]
+    pub methods: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchType {
+    #[default]
+    Exact,
+    Prefix,
+    KeyMatch2,
+}
+
+fn default_ignore_routes() -> Vec<IgnoreRoute> {
+    exact_routes(&IGNORE_ROUTES)
+}
+
+fn default_authenticated_routes() -> Vec<IgnoreRoute> {
+    exact_routes(&AUTHENTICATED_ROUTES)
+}
 
+fn exact_routes(paths: &[&str]) -> Vec<IgnoreRoute> {
+    paths
+        .iter()
+        .map(|path| IgnoreRoute {
+            path: path.to_string(),
+            match_type: MatchType::Exact,
+            methods: vec![],
+        })
+        .collect()
+}
 
 impl Config {
     pub fn init() -> Config {
@@ -74,8 +282,7 @@ impl Config {
         let path: Vec<&str> = vec!["./config/", "./resource/config/"];
         let mut config = Config::default();
         for p in path {
-            let config_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-                .join(p);
+            let config_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(p);
             let config_file = config_path.join("application.yaml");
             if config_file.exists() {
                 config = Figment::new()
@@ -83,15 +290,15 @@ impl Config {
                     .extract()
                     .unwrap();
 
-                let config_path_active = config_path
-                    .join(format!("application-{}.yaml", config.profiles.active));
+                let config_path_active =
+                    config_path.join(format!("application-{}.yaml", config.profiles.active));
 
                 if config_path_active.exists() {
                     config = Figment::new()
-                    .merge(Yaml::file(&config_file))
-                    .merge(Yaml::file(&config_path_active))
-                    .extract()
-                    .unwrap();
+                        .merge(Yaml::file(&config_file))
+                        .merge(Yaml::file(&config_path_active))
+                        .extract()
+                        .unwrap();
                 }
                 config.path = Some(config_path.into_os_string());
                 break;
@@ -100,3 +307,47 @@ impl Config {
         config
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use casbin::{CoreApi, DefaultModel, Enforcer, MemoryAdapter, MgmtApi};
+
+    use super::CASBIN_MODEL;
+
+    fn rule(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn casbin_object_matching() {
+        let m = DefaultModel::from_str(CASBIN_MODEL).await.unwrap();
+        let mut e = Enforcer::new(m, MemoryAdapter::default()).await.unwrap();
+        e.add_policies(vec![
+            rule(&["alice", "d1", "/api/users", "GET"]),
+            rule(&["alice", "d1", "/api/users/:id", "PUT"]),
+            rule(&["admin", "d1", "/api/*", "DELETE"]),
+        ])
+        .await
+        .unwrap();
+        e.add_grouping_policy(rule(&["bob", "admin", "d1"]))
+            .await
+            .unwrap();
+
+        let cases = [
+            // (主体, 域, 路径, 方法, 是否允许)
+            ("alice", "d1", "/api/users", "GET", true),
+            ("alice", "d1", "/api/users/1", "GET", false),
+            ("alice", "d1", "/api/users/1", "PUT", true),
+            ("alice", "d1", "/api/users/1/roles", "PUT", false),
+            ("alice", "d1", "/api/users", "PUT", false),
+            ("alice", "d2", "/api/users", "GET", false),
+            ("bob", "d1", "/api/roles/1/users/alice", "DELETE", true),
+            ("bob", "d1", "/api/roles/1", "GET", false),
+            ("bob", "d2", "/api/roles/1", "DELETE", false),
+        ];
+        for (sub, dom, obj, act, expected) in cases {
+            let allowed = e.enforce((sub, dom, obj, act)).unwrap();
+            assert_eq!(allowed, expected, "{} {} {} {}", sub, dom, obj, act);
+        }
+    }
+}
@@ -8,6 +8,65 @@ pub const MESSAGE_INTERNAL_SERVER_ERROR: &str = "Internal Server Error";
 pub const MESSAGE_SIGNIN_FAILED: &str = "Wrong email or password, please try again";
 pub const MESSAGE_SIGNIN_SUCCESS: &str = "Signin successfully";
 pub const MESSAGE_SIGNUP_SUCCESS: &str = "Signup successfully";
+pub const MESSAGE_SIGNOUT_SUCCESS: &str = "Signout successfully";
+pub const MESSAGE_USER_DISABLED: &str = "User is disabled, please contact the administrator";
+pub const MESSAGE_ACCOUNT_EXISTS: &str = "Account already exists";
+pub const MESSAGE_INVALID_PARAMS: &str = "Invalid request parameters";
+pub const MESSAGE_KICK_SESSION_SUCCESS: &str = "Session logged out successfully";
+pub const MESSAGE_SESSION_NOT_FOUND: &str = "Session not found";
+pub const MESSAGE_ACCOUNT_LOCKED: &str =
+    "Account is locked due to too many failed attempts, please try again later";
+pub const MESSAGE_PASSWORD_CHANGE_REQUIRED: &str = "Password must be changed before signing in";
+pub const MESSAGE_PASSWORD_TOO_WEAK: &str =
+    "Password does not meet the policy, use a longer password with more character classes";
+pub const MESSAGE_PASSWORD_REUSED: &str = "Password has been used recently, choose another one";
+pub const MESSAGE_PASSWORD_CHANGE_SUCCESS: &str = "Password changed successfully";
+pub const MESSAGE_PASSWORD_RESET_SUCCESS: &str = "Password reset successfully";
+pub const MESSAGE_SIGNIN_REQUIRED: &str = "Please sign in to change the password";
+pub const MESSAGE_MFA_REQUIRED: &str = "Two-factor authentication code required";
+pub const MESSAGE_TOTP_INVALID: &str = "Invalid two-factor authentication code";
+pub const MESSAGE_TOTP_NOT_ENROLLED: &str = "Two-factor authentication is not enrolled";
+pub const MESSAGE_TOTP_ALREADY_ENABLED: &str = "Two-factor authentication is already enabled";
+pub const MESSAGE_TOTP_RESET_SUCCESS: &str = "Two-factor authentication reset successfully";
+pub const MESSAGE_TOKEN_REUSED: &str = "Refresh token has already been used, please login again";
+pub const MESSAGE_API_KEY_CREATE_SUCCESS: &str =
+    "API key created, save it now as it will not be shown again";
+pub const MESSAGE_API_KEY_REVOKE_SUCCESS: &str = "API key revoked successfully";
+pub const MESSAGE_API_KEY_NOT_FOUND: &str = "API key not found";
+pub const MESSAGE_API_KEY_SCOPE: &str = "API key is not allowed to access this resource";
+pub const MESSAGE_API_KEY_ACCOUNT: &str =
+    "Only super administrators can create API keys for other users";
+pub const MESSAGE_TLS_RELOAD_SUCCESS: &str = "TLS certificate reloaded successfully";
+pub const MESSAGE_TLS_RELOAD_FAILED: &str =
+    "Failed to reload TLS certificate, the old one is still in use";
+pub const MESSAGE_TLS_DISABLED: &str = "TLS is not enabled";
+pub const MESSAGE_USER_STATE_SUCCESS: &str = "User state changed successfully";
+pub const MESSAGE_DELETE_USER_SUCCESS: &str = "User deleted successfully";
+pub const MESSAGE_CAN_NOT_DELETE_SELF: &str = "Can not disable or delete the current user";
+pub const MESSAGE_ROLE_NOT_FOUND: &str = "Role not found";
+pub const MESSAGE_ROLE_EXISTS: &str = "Role name is already used by a role or user";
+pub const MESSAGE_ROLE_PARENT_INVALID: &str = "Parent role does not exist or would create a cycle";
+pub const MESSAGE_DELETE_ROLE_SUCCESS: &str = "Role deleted successfully";
+pub const MESSAGE_MENU_NOT_FOUND: &str = "Menu not found";
+pub const MESSAGE_MENU_PARENT_INVALID: &str = "Parent menu does not exist or would create a cycle";
+pub const MESSAGE_MENU_HAS_CHILDREN: &str = "Menu has children, remove or move them first";
+pub const MESSAGE_DELETE_MENU_SUCCESS: &str = "Menu deleted successfully";
+pub const MESSAGE_DEPT_NOT_FOUND: &str = "Department not found";
+pub const MESSAGE_DEPT_PARENT_INVALID: &str =
+    "Parent department does not exist or would create a cycle";
+pub const MESSAGE_DEPT_HAS_CHILDREN: &str =
+    "Department has sub-departments, remove or move them first";
+pub const MESSAGE_DELETE_DEPT_SUCCESS: &str = "Department deleted successfully";
+pub const MESSAGE_DOMAIN_NOT_FOUND: &str = "Domain not found";
+pub const MESSAGE_DOMAIN_DISABLED: &str = "Domain is disabled";
+pub const MESSAGE_DOMAIN_EXISTS: &str = "Domain already exists";
+pub const MESSAGE_DOMAIN_NOT_EMPTY: &str = "Domain still has users, remove them first";
+pub const MESSAGE_DOMAIN_PROTECTED: &str = "The platform domain can not be disabled or deleted";
+pub const MESSAGE_NOT_SUPER_ADMIN: &str = "Only super administrators can manage domains";
+pub const MESSAGE_DELETE_DOMAIN_SUCCESS: &str = "Domain deleted successfully";
+pub const MESSAGE_POLICY_MANAGED: &str =
+    "Members of managed roles can only be changed through the role API";
+pub const MESSAGE_POLICY_FILE_INVALID: &str = "Invalid policy file";
 pub const MESSAGE_UPDATE_USER_SUCCESS: &str = "User updated successfully";
 pub const MESSAGE_NEW_USER_ADD_PERMISSION_ERROR: &str =
     "Can not add new user when adding new permissions, maybe user is already present";
@@ -15,17 +74,27 @@ pub const MESSAGE_UPDATE_USER_ERROR: &str = "Can not update user";
 
 // HEADERS
 pub const AUTHORIZATION: &str = "Authorization";
+pub const API_KEY: &str = "X-API-Key";
+// 超级管理员跨域操作时指定目标域
+pub const X_DOMAIN: &str = "X-Domain";
+
+// 新建域时创建的管理员角色，以及授予它的 /api 下的资源
+pub const TENANT_ADMIN_ROLE: &str = "tenant_admin";
+pub const TENANT_ADMIN_RESOURCES: [&str; 7] = [
+    "auth", "users", "roles", "menus", "depts", "sessions", "api-keys",
+];
 
 // Misc
 pub const EMPTY: &str = "";
 
 // IGNORE ROUTES
-pub const IGNORE_ROUTES: [&str; 4] = [
-    "api/auth/register",
-    "api/auth/signin",
-    "api/users",
-    "api/user",
-];
+pub const IGNORE_ROUTES: [&str; 2] = ["api/auth/register", "api/auth/signin"];
 
-//JWT Secret
-pub const SECRET:&str = "secret";
\ No newline at end of file
+// 登录后即可访问的路由，不需要 casbin 策略
+pub const AUTHENTICATED_ROUTES: [&str; 5] = [
+    "api/auth/signout",
+    "api/auth/password",
+    "api/auth/2fa/enroll",
+    "api/auth/2fa/activate",
+    "api/menus/routes",
+];
@@ -1,29 +1,115 @@
-use std::{str::FromStr, time::Duration};
+use std::{str::FromStr, sync::Arc, time::Duration};
 
+use axum_server::tls_rustls::RustlsConfig;
+use casbin::CachedEnforcer;
 use log::{self, LevelFilter};
 
 use sea_orm::{ConnectOptions, Database, DatabaseConnection};
+use tokio::sync::RwLock;
 
 use crate::config::CFG;
 
+// 与 casbin 中间件共用的 enforcer，修改策略后立即对鉴权生效
+pub type SharedEnforcer = Arc<RwLock<CachedEnforcer>>;
+
 #[derive(Clone)]
 pub struct AppState {
-   pub conn: DatabaseConnection,
+    pub conn: DatabaseConnection,
+    pub enforcer: SharedEnforcer,
+    // 未开启 tls 时为空
+    pub tls: Option<RustlsConfig>,
 }
 
 pub async fn db_init() -> DatabaseConnection {
     let mut opt = ConnectOptions::new(CFG.db.url.as_str());
     opt.max_connections(100)
-    .min_connections(5)
-    .connect_timeout(Duration::from_secs(8))
-    .acquire_timeout(Duration::from_secs(8))
-    .idle_timeout(Duration::from_secs(8))
-    .max_lifetime(Duration::from_secs(8))
-    .sqlx_logging(CFG.db.log)
-    .sqlx_logging_level(LevelFilter::from_str(&CFG.db.log_level).unwrap())
-    .set_schema_search_path("my_schema"); // Setting default PostgreSQL schema
+        .min_connections(5)
+        .connect_timeout(Duration::from_secs(8))
+        .acquire_timeout(Duration::from_secs(8))
+        .idle_timeout(Duration::from_secs(8))
+        .max_lifetime(Duration::from_secs(8))
+        .sqlx_logging(CFG.db.log)
+        .sqlx_logging_level(LevelFilter::from_str(&CFG.db.log_level).unwrap())
+        .set_schema_search_path("my_schema"); // Setting default PostgreSQL schema
 
     // Database::connect(opt).await.unwrap()
     let db = Database::connect(opt).await.unwrap();
     db
-}
\ No newline at end of file
+}
+// 单元测试使用的内存数据库，已执行全部迁移并启用平台域
+// 域状态缓存是全局的，测试之间并行运行，不要用 load_domains 覆盖
+#[cfg(test)]
+pub async fn test_db() -> DatabaseConnection {
+    use migration::{Migrator, MigratorTrait};
+
+    let conn = Database::connect("sqlite::memory:").await.unwrap();
+    Migrator::up(&conn, None).await.unwrap();
+    let platform = &CFG.auth.platform_domain;
+    crate::service::domain::insert_domain(&conn, platform, platform)
+        .await
+        .unwrap();
+    conn
+}
+
+// 带有 casbin enforcer 的应用状态，已创建超级管理员角色
+#[cfg(test)]
+pub async fn test_state() -> AppState {
+    use casbin::{function_map::key_match2, CoreApi, DefaultModel};
+
+    let conn = test_db().await;
+    let m = DefaultModel::from_str(crate::config::CASBIN_MODEL)
+        .await
+        .unwrap();
+    let a = casbin_adapter::SeaOrmAdapter::new(conn.clone())
+        .await
+        .unwrap();
+    let enforcer = CachedEnforcer::new(m, a).await.unwrap();
+    enforcer
+        .get_role_manager()
+        .write()
+        .matching_fn(Some(key_match2), None);
+    let enforcer = Arc::new(RwLock::new(enforcer));
+    crate::service::domain::bootstrap(&conn, &enforcer)
+        .await
+        .unwrap();
+    AppState {
+        conn,
+        enforcer,
+        tls: None,
+    }
+}
+
+// 平台域中的超级管理员
+#[cfg(test)]
+pub async fn test_super_admin(state: &AppState, account: &str) -> crate::util::jwt::Claims {
+    use casbin::RbacApi;
+
+    let user = test_user(&state.conn, account).await;
+    let platform = CFG.auth.platform_domain.as_str();
+    state
+        .enforcer
+        .write()
+        .await
+        .add_role_for_user(account, &CFG.auth.super_admin_role, Some(platform))
+        .await
+        .unwrap();
+    crate::util::jwt::JWT.claims_for(&user)
+}
+
+// 在平台域中创建测试用户，密码为 "Secret-123"
+#[cfg(test)]
+pub async fn test_user(conn: &DatabaseConnection, account: &str) -> entity::sys_user::Model {
+    crate::service::auth::create_user(
+        conn,
+        crate::service::auth::SignupReq {
+            account: account.to_string(),
+            password: "Secret-123".to_string(),
+            name: account.to_string(),
+            domain: CFG.auth.platform_domain.clone(),
+            email: String::new(),
+            phone: String::new(),
+        },
+    )
+    .await
+    .unwrap()
+}
@@ -1,6 +1,113 @@
-pub struct Error<> {
+use sea_orm::DbErr;
+
+use crate::constants::*;
+use crate::util::jwt::JwtError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Error {
     code: u32,
     msg: &'static str,
 }
 
-pub const ERR_OK: Error =  Error{code: 0, msg: "success"};
+impl Error {
+    pub const fn new(code: u32, msg: &'static str) -> Self {
+        Error { code, msg }
+    }
+
+    pub fn code(&self) -> u32 {
+        self.code
+    }
+
+    pub fn msg(&self) -> &'static str {
+        self.msg
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+pub const ERR_OK: Error = Error {
+    code: 0,
+    msg: "success",
+};
+pub const OK_SIGNIN: Error = Error::new(0, MESSAGE_SIGNIN_SUCCESS);
+pub const OK_SIGNUP: Error = Error::new(0, MESSAGE_SIGNUP_SUCCESS);
+pub const OK_SIGNOUT: Error = Error::new(0, MESSAGE_SIGNOUT_SUCCESS);
+pub const OK_KICK_SESSION: Error = Error::new(0, MESSAGE_KICK_SESSION_SUCCESS);
+pub const OK_PASSWORD_CHANGE: Error = Error::new(0, MESSAGE_PASSWORD_CHANGE_SUCCESS);
+pub const OK_PASSWORD_RESET: Error = Error::new(0, MESSAGE_PASSWORD_RESET_SUCCESS);
+pub const OK_TOTP_RESET: Error = Error::new(0, MESSAGE_TOTP_RESET_SUCCESS);
+pub const OK_API_KEY_CREATE: Error = Error::new(0, MESSAGE_API_KEY_CREATE_SUCCESS);
+pub const OK_API_KEY_REVOKE: Error = Error::new(0, MESSAGE_API_KEY_REVOKE_SUCCESS);
+pub const OK_TLS_RELOAD: Error = Error::new(0, MESSAGE_TLS_RELOAD_SUCCESS);
+pub const OK_UPDATE_USER: Error = Error::new(0, MESSAGE_UPDATE_USER_SUCCESS);
+pub const OK_USER_STATE: Error = Error::new(0, MESSAGE_USER_STATE_SUCCESS);
+pub const OK_DELETE_USER: Error = Error::new(0, MESSAGE_DELETE_USER_SUCCESS);
+pub const OK_DELETE_ROLE: Error = Error::new(0, MESSAGE_DELETE_ROLE_SUCCESS);
+pub const OK_DELETE_MENU: Error = Error::new(0, MESSAGE_DELETE_MENU_SUCCESS);
+pub const OK_DELETE_DEPT: Error = Error::new(0, MESSAGE_DELETE_DEPT_SUCCESS);
+pub const OK_DELETE_DOMAIN: Error = Error::new(0, MESSAGE_DELETE_DOMAIN_SUCCESS);
+
+pub const ERR_INTERNAL: Error = Error::new(500, MESSAGE_INTERNAL_SERVER_ERROR);
+pub const ERR_SIGNIN_FAILED: Error = Error::new(1001, MESSAGE_SIGNIN_FAILED);
+pub const ERR_USER_DISABLED: Error = Error::new(1002, MESSAGE_USER_DISABLED);
+pub const ERR_ACCOUNT_EXISTS: Error = Error::new(1003, MESSAGE_ACCOUNT_EXISTS);
+pub const ERR_PROCESS_TOKEN: Error = Error::new(1004, MESSAGE_PROCESS_TOKEN_ERROR);
+pub const ERR_INVALID_PARAMS: Error = Error::new(1005, MESSAGE_INVALID_PARAMS);
+pub const ERR_INVALID_TOKEN: Error = Error::new(1006, MESSAGE_INVALID_TOKEN);
+pub const ERR_TOKEN_REUSED: Error = Error::new(1007, MESSAGE_TOKEN_REUSED);
+pub const ERR_SESSION_NOT_FOUND: Error = Error::new(1008, MESSAGE_SESSION_NOT_FOUND);
+pub const ERR_ACCOUNT_LOCKED: Error = Error::new(1009, MESSAGE_ACCOUNT_LOCKED);
+pub const ERR_PASSWORD_CHANGE_REQUIRED: Error = Error::new(1010, MESSAGE_PASSWORD_CHANGE_REQUIRED);
+pub const ERR_PASSWORD_TOO_WEAK: Error = Error::new(1011, MESSAGE_PASSWORD_TOO_WEAK);
+pub const ERR_PASSWORD_REUSED: Error = Error::new(1012, MESSAGE_PASSWORD_REUSED);
+pub const ERR_USER_NOT_FOUND: Error = Error::new(1013, MESSAGE_CAN_NOT_FIND_USER);
+pub const ERR_MFA_REQUIRED: Error = Error::new(1014, MESSAGE_MFA_REQUIRED);
+pub const ERR_TOTP_INVALID: Error = Error::new(1015, MESSAGE_TOTP_INVALID);
+pub const ERR_TOTP_NOT_ENROLLED: Error = Error::new(1016, MESSAGE_TOTP_NOT_ENROLLED);
+pub const ERR_TOTP_ALREADY_ENABLED: Error = Error::new(1017, MESSAGE_TOTP_ALREADY_ENABLED);
+pub const ERR_API_KEY_NOT_FOUND: Error = Error::new(1018, MESSAGE_API_KEY_NOT_FOUND);
+pub const ERR_API_KEY_SCOPE: Error = Error::new(1019, MESSAGE_API_KEY_SCOPE);
+pub const ERR_TLS_RELOAD_FAILED: Error = Error::new(1020, MESSAGE_TLS_RELOAD_FAILED);
+pub const ERR_TLS_DISABLED: Error = Error::new(1021, MESSAGE_TLS_DISABLED);
+pub const ERR_UPDATE_USER: Error = Error::new(1022, MESSAGE_UPDATE_USER_ERROR);
+pub const ERR_CAN_NOT_DELETE_SELF: Error = Error::new(1023, MESSAGE_CAN_NOT_DELETE_SELF);
+pub const ERR_ROLE_NOT_FOUND: Error = Error::new(1024, MESSAGE_ROLE_NOT_FOUND);
+pub const ERR_ROLE_EXISTS: Error = Error::new(1025, MESSAGE_ROLE_EXISTS);
+pub const ERR_ROLE_PARENT_INVALID: Error = Error::new(1026, MESSAGE_ROLE_PARENT_INVALID);
+pub const ERR_MENU_NOT_FOUND: Error = Error::new(1027, MESSAGE_MENU_NOT_FOUND);
+pub const ERR_MENU_PARENT_INVALID: Error = Error::new(1028, MESSAGE_MENU_PARENT_INVALID);
+pub const ERR_MENU_HAS_CHILDREN: Error = Error::new(1029, MESSAGE_MENU_HAS_CHILDREN);
+pub const ERR_DEPT_NOT_FOUND: Error = Error::new(1030, MESSAGE_DEPT_NOT_FOUND);
+pub const ERR_DEPT_PARENT_INVALID: Error = Error::new(1031, MESSAGE_DEPT_PARENT_INVALID);
+pub const ERR_DEPT_HAS_CHILDREN: Error = Error::new(1032, MESSAGE_DEPT_HAS_CHILDREN);
+pub const ERR_DOMAIN_NOT_FOUND: Error = Error::new(1033, MESSAGE_DOMAIN_NOT_FOUND);
+pub const ERR_DOMAIN_DISABLED: Error = Error::new(1034, MESSAGE_DOMAIN_DISABLED);
+pub const ERR_DOMAIN_EXISTS: Error = Error::new(1035, MESSAGE_DOMAIN_EXISTS);
+pub const ERR_DOMAIN_NOT_EMPTY: Error = Error::new(1036, MESSAGE_DOMAIN_NOT_EMPTY);
+pub const ERR_DOMAIN_PROTECTED: Error = Error::new(1037, MESSAGE_DOMAIN_PROTECTED);
+pub const ERR_NOT_SUPER_ADMIN: Error = Error::new(1038, MESSAGE_NOT_SUPER_ADMIN);
+pub const ERR_POLICY_MANAGED: Error = Error::new(1039, MESSAGE_POLICY_MANAGED);
+pub const ERR_POLICY_FILE_INVALID: Error = Error::new(1040, MESSAGE_POLICY_FILE_INVALID);
+pub const ERR_SIGNIN_REQUIRED: Error = Error::new(1041, MESSAGE_SIGNIN_REQUIRED);
+pub const ERR_API_KEY_ACCOUNT: Error = Error::new(1042, MESSAGE_API_KEY_ACCOUNT);
+
+impl From<DbErr> for Error {
+    fn from(err: DbErr) -> Self {
+        error!("database error: {}", err);
+        ERR_INTERNAL
+    }
+}
+
+impl From<casbin::Error> for Error {
+    fn from(err: casbin::Error) -> Self {
+        error!("casbin error: {}", err);
+        ERR_INTERNAL
+    }
+}
+
+impl From<JwtError> for Error {
+    fn from(err: JwtError) -> Self {
+        error!("jwt error: {}", err);
+        ERR_PROCESS_TOKEN
+    }
+}
@@ -1,28 +1,31 @@
-use axum::extract::State;
 use axum::{routing::get, Router};
-use axum_server::{tls_rustls::RustlsConfig, Handle};
+use axum_server::Handle;
 use casbin::function_map::key_match2;
-use sea_orm::*;
-use tokio::signal;
-use std::path::PathBuf;
+use casbin::{CoreApi, DefaultModel};
+use casbin_adapter::{SeaOrmAdapter, SeaOrmWatcher};
+use migration::{Migrator, MigratorTrait};
 use std::{net::SocketAddr, time::Duration};
-use casbin::{CoreApi, DefaultModel, Enforcer};
-use casbin_adapter::SeaOrmAdapter;
+use tokio::signal;
 
 #[macro_use]
 extern crate tracing;
 
 mod config;
-mod log;
-mod context;
-mod middleware;
 mod constants;
+mod context;
 pub mod error;
-use crate::middleware::casbin::CasbinAxumLayer;
+mod log;
+mod middleware;
+mod service;
+mod util;
+use crate::config::CASBIN_MODEL;
 use crate::config::CFG;
-use crate::context::AppState;
 use crate::context::db_init;
-use crate::config::CASBIN_MODEL;
+use crate::context::AppState;
+use crate::middleware::auth::AuthLayer;
+use crate::middleware::casbin::CasbinAxumLayer;
+use crate::middleware::redirect::HttpsRedirectLayer;
+use crate::util::tls::{self, ClientCertAcceptor};
 
 #[tokio::main]
 async fn main() {
@@ -30,17 +33,34 @@ async fn main() {
     info!("Starting config is {:?}", &CFG.app_name);
 
     // configure certificate and private key used by https
-    let config = RustlsConfig::from_pem_file(&CFG.server.pem_cert_path, &CFG.server.pem_key_path)
-        .await
-        .unwrap();
+    let config = tls::rustls_config(&CFG.server).unwrap();
+    if CFG.server.tls {
+        if CFG.server.reload_interval > 0 {
+            let interval = Duration::from_secs(CFG.server.reload_interval);
+            tokio::spawn(tls::watch(config.clone(), interval));
+        }
+        #[cfg(unix)]
+        tokio::spawn(tls::reload_on_sighup(config.clone()));
+    }
 
     // db
-    let conn =  db_init().await;
+    let conn = db_init().await;
+    Migrator::up(&conn, None).await.unwrap();
+
+    // revoked tokens
+    service::session::load_revoked(&conn).await.unwrap();
+    tokio::spawn(service::session::sync_revoked(conn.clone()));
 
-    // casbin load
+    // casbin load，先创建 watcher，避免漏掉加载期间其他实例的修改
+    let watcher = SeaOrmWatcher::new(conn.clone()).await.unwrap();
+    let policy_sync = watcher.sync().await.unwrap();
     let m = DefaultModel::from_str(CASBIN_MODEL).await.unwrap();
     let a = SeaOrmAdapter::new(conn.clone()).await.unwrap();
-    let casbin_middleware = CasbinAxumLayer::new(m, a).await.unwrap();
+    let a = match CFG.auth.policy_sync_interval {
+        0 => a,
+        _ => a.with_watcher(&watcher),
+    };
+    let mut casbin_middleware = CasbinAxumLayer::new(m, a).await.unwrap();
     casbin_middleware
         .write()
         .await
@@ -48,24 +68,64 @@ async fn main() {
         .write()
         .matching_fn(Some(key_match2), None);
 
-    let state = AppState { conn };
+    let enforcer = casbin_middleware.get_enforcer();
+
+    // domains
+    service::domain::bootstrap(&conn, &enforcer).await.unwrap();
+    tokio::spawn(service::domain::sync_domains(conn.clone()));
+
+    // policy sync
+    if CFG.auth.policy_sync_interval > 0 {
+        enforcer.write().await.set_watcher(Box::new(watcher));
+        let interval = Duration::from_secs(CFG.auth.policy_sync_interval);
+        tokio::spawn(policy_sync.run(enforcer.clone(), interval));
+    }
+
+    let state = AppState {
+        conn: conn.clone(),
+        enforcer: enforcer.clone(),
+        tls: CFG.server.tls.then(|| config.clone()),
+    };
 
     let app = Router::new()
-    .route("/", get(handler))
-    .with_state(state)
-    .layer(casbin_middleware);
+        .route("/", get(handler))
+        .nest("/api", service::router())
+        .with_state(state)
+        .layer(casbin_middleware)
+        .layer(AuthLayer::new(conn, enforcer));
 
     //Create a handle for our TLS server so the shutdown signal can all shutdown
     let handle = Handle::new();
     // Spawn a task to shutdown server.
     tokio::spawn(shutdown_signal(handle.clone()));
 
-
-    let addr = SocketAddr::new(CFG.server.address.parse().unwrap(), CFG.server.port);
+    let ip = CFG.server.address.parse().unwrap();
+    let addr = SocketAddr::new(ip, CFG.server.port);
     info!("listening on {addr}");
     // run https server
     match CFG.server.tls {
-        true => axum_server::bind_rustls(addr, config)
+        // 同时监听明文端口，两个服务共用同一个 handle 一起优雅退出
+        true if CFG.server.http.enabled => {
+            let http_addr = SocketAddr::new(ip, CFG.server.http.port);
+            info!("listening on {http_addr} (http)");
+            let http_app = match CFG.server.http.redirect {
+                true => app.clone().layer(HttpsRedirectLayer::new(
+                    CFG.server.port,
+                    CFG.server.http.redirect_exclude.clone(),
+                )),
+                false => app.clone(),
+            };
+            let https = axum_server::bind(addr)
+                .acceptor(ClientCertAcceptor::new(config))
+                .handle(handle.clone())
+                .serve(app.into_make_service());
+            let http = axum_server::bind(http_addr)
+                .handle(handle)
+                .serve(http_app.into_make_service());
+            tokio::try_join!(https, http).unwrap();
+        }
+        true => axum_server::bind(addr)
+            .acceptor(ClientCertAcceptor::new(config))
             .handle(handle)
             .serve(app.into_make_service())
             .await
@@ -0,0 +1,213 @@
+use axum::{body, response::Response, BoxError};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use http::{Request, StatusCode};
+use http_body::Body as HttpBody;
+use http_body_util::Full;
+use sea_orm::DatabaseConnection;
+use std::{
+    convert::Infallible,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+use crate::config::CFG;
+use crate::constants::{
+    API_KEY, AUTHORIZATION, MESSAGE_API_KEY_SCOPE, MESSAGE_DOMAIN_DISABLED, MESSAGE_INVALID_TOKEN,
+    MESSAGE_NOT_SUPER_ADMIN, X_DOMAIN,
+};
+use crate::context::SharedEnforcer;
+use crate::error::ERR_API_KEY_SCOPE;
+use crate::middleware::casbin::CasbinVals;
+use crate::middleware::ignore::is_ignored;
+use crate::service::{api_key, client_cert, domain, session};
+use crate::util::jwt::{Claims, JWT};
+use crate::util::tls::{ClientCert, TlsInfo};
+
+const BEARER: &str = "Bearer ";
+
+#[derive(Clone)]
+pub struct AuthLayer {
+    conn: DatabaseConnection,
+    enforcer: SharedEnforcer,
+}
+
+impl AuthLayer {
+    pub fn new(conn: DatabaseConnection, enforcer: SharedEnforcer) -> Self {
+        AuthLayer { conn, enforcer }
+    }
+}
+
+impl<S> Layer<S> for AuthLayer {
+    type Service = AuthMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthMiddleware {
+            inner,
+            conn: self.conn.clone(),
+            enforcer: self.enforcer.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AuthMiddleware<S> {
+    inner: S,
+    conn: DatabaseConnection,
+    enforcer: SharedEnforcer,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for AuthMiddleware<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+    ReqBody: Send + 'static,
+    ResBody: HttpBody<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<BoxError>,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let not_ready_inner = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, not_ready_inner);
+        let conn = self.conn.clone();
+        let enforcer = self.enforcer.clone();
+
+        Box::pin(async move {
+            if is_ignored(req.method().as_str(), req.uri().path()) {
+                return Ok(inner.call(req).await?.map(body::Body::new));
+            }
+
+            let mut claims = if let Some(key) = api_key_token(&req) {
+                let (method, path) = (req.method().as_str(), req.uri().path());
+                match api_key::authenticate(&conn, key, method, path).await {
+                    Ok(claims) => claims,
+                    Err(err) if err == ERR_API_KEY_SCOPE => {
+                        debug!("reject api key request to {}: out of scope", path);
+                        return Ok(forbidden(MESSAGE_API_KEY_SCOPE));
+                    }
+                    Err(err) => {
+                        debug!("reject api key request to {}: {}", path, err.msg());
+                        return Ok(unauthorized());
+                    }
+                }
+            } else if let Some(cert) = client_cert(&req) {
+                match client_cert::authenticate(&conn, cert).await {
+                    Ok(claims) => claims,
+                    Err(err) => {
+                        debug!(
+                            "reject client certificate {}: {}",
+                            cert.fingerprint,
+                            err.msg()
+                        );
+                        return Ok(unauthorized());
+                    }
+                }
+            } else {
+                let claims = match bearer_token(&req).map(|token| JWT.verify(token)) {
+                    Some(Ok(claims)) => claims,
+                    Some(Err(err)) => {
+                        debug!("reject request to {}: {}", req.uri().path(), err);
+                        return Ok(unauthorized());
+                    }
+                    None => return Ok(unauthorized()),
+                };
+                if session::is_revoked(&claims.token_id) {
+                    debug!("reject revoked token {}", claims.token_id);
+                    return Ok(unauthorized());
+                }
+                claims
+            };
+
+            // 超级管理员通过 X-Domain 头操作其他域，权限仍按其所在域的策略校验
+            if let Some(target) = target_domain(&req).filter(|d| *d != claims.domain) {
+                if !domain::is_super_admin(&enforcer, &claims).await {
+                    debug!(
+                        "reject {} acting on domain {}: not super admin",
+                        claims.sub, target
+                    );
+                    return Ok(forbidden(MESSAGE_NOT_SUPER_ADMIN));
+                }
+                let target = target.to_string();
+                claims.home_domain = Some(std::mem::replace(&mut claims.domain, target));
+            }
+            let home = claims
+                .home_domain
+                .clone()
+                .unwrap_or_else(|| claims.domain.clone());
+            if !domain::is_enabled(&home) || !domain::is_enabled(&claims.domain) {
+                debug!("reject {} in disabled domain {}", claims.sub, claims.domain);
+                return Ok(forbidden(MESSAGE_DOMAIN_DISABLED));
+            }
+
+            req.extensions_mut().insert(CasbinVals {
+                subject: claims.sub.clone(),
+                domain: Some(home),
+            });
+            req.extensions_mut().insert::<Claims>(claims);
+
+            Ok(inner.call(req).await?.map(body::Body::new))
+        })
+    }
+}
+
+// 从 Authorization 头中取出 Bearer token
+fn bearer_token<B>(req: &Request<B>) -> Option<&str> {
+    req.headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix(BEARER))
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
+// API key 可以放在 X-API-Key 头中，也可以作为 Bearer token 传递
+fn api_key_token<B>(req: &Request<B>) -> Option<&str> {
+    req.headers()
+        .get(API_KEY)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .or_else(|| bearer_token(req))
+        .filter(|v| v.starts_with(api_key::KEY_PREFIX))
+}
+
+fn target_domain<B>(req: &Request<B>) -> Option<&str> {
+    req.headers()
+        .get(X_DOMAIN)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
+// 没有携带 token 时才使用 mtls 握手得到的客户端证书
+fn client_cert<B>(req: &Request<B>) -> Option<&ClientCert> {
+    if !CFG.server.mtls.enabled || req.headers().contains_key(AUTHORIZATION) {
+        return None;
+    }
+    req.extensions()
+        .get::<TlsInfo>()
+        .and_then(|info| info.client_cert.as_ref())
+}
+
+fn unauthorized() -> Response {
+    Response::builder()
+        .status(StatusCode::UNAUTHORIZED)
+        .body(body::Body::new(Full::from(MESSAGE_INVALID_TOKEN)))
+        .unwrap()
+}
+
+fn forbidden(msg: &'static str) -> Response {
+    Response::builder()
+        .status(StatusCode::FORBIDDEN)
+        .body(body::Body::new(Full::from(msg)))
+        .unwrap()
+}
@@ -12,8 +12,10 @@ use std::{
     sync::Arc,
     task::{Context, Poll},
 };
-use tower::{Layer, Service};
 use tokio::sync::RwLock;
+use tower::{Layer, Service};
+
+use crate::middleware::ignore::{is_authenticated_only, is_ignored};
 
 #[derive(Clone, Debug)]
 pub struct CasbinVals {
@@ -102,6 +104,9 @@ where
         Box::pin(async move {
             let path = req.uri().path().to_string();
             let action = req.method().as_str().to_string();
+            if is_ignored(&action, &path) {
+                return Ok(inner.call(req).await?.map(body::Body::new));
+            }
             let option_vals = req.extensions().get::<CasbinVals>().map(|x| x.to_owned());
             let vals = match option_vals {
                 Some(value) => value,
@@ -113,6 +118,11 @@ where
                 }
             };
 
+            // 已登录即可访问的路由不需要 casbin 策略
+            if !vals.subject.is_empty() && is_authenticated_only(&action, &path) {
+                return Ok(inner.call(req).await?.map(body::Body::new));
+            }
+
             let subject = vals.subject.clone();
 
             if !vals.subject.is_empty() {
@@ -169,4 +179,51 @@ where
             }
         })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, routing::any, Router};
+    use http::{header, Request, StatusCode};
+    use tower::ServiceExt;
+
+    use super::CasbinAxumLayer;
+    use crate::context::{test_state, test_user};
+    use crate::middleware::auth::AuthLayer;
+    use crate::util::jwt::JWT;
+
+    #[tokio::test]
+    async fn authenticated_routes_need_no_policy() {
+        let state = test_state().await;
+        let user = test_user(&state.conn, "no-role").await;
+        let (_, body) = JWT.issue(&user).unwrap();
+        let app = Router::new()
+            .fallback(any(|| async { "ok" }))
+            .layer(CasbinAxumLayer::set_enforcer(state.enforcer.clone()))
+            .layer(AuthLayer::new(state.conn.clone(), state.enforcer.clone()));
+
+        let cases = [
+            // (请求方法, 请求路径, 是否携带 token, 状态码)
+            ("POST", "/api/auth/signout", true, StatusCode::OK),
+            ("POST", "/api/auth/signout", false, StatusCode::UNAUTHORIZED),
+            ("PUT", "/api/auth/password", true, StatusCode::OK),
+            ("POST", "/api/auth/2fa/enroll", true, StatusCode::OK),
+            ("POST", "/api/auth/2fa/activate", true, StatusCode::OK),
+            ("GET", "/api/menus/routes", true, StatusCode::OK),
+            ("GET", "/api/menus", true, StatusCode::FORBIDDEN),
+            ("GET", "/api/users", true, StatusCode::FORBIDDEN),
+        ];
+        for (method, path, signed_in, expected) in cases {
+            let mut req = Request::builder().method(method).uri(path);
+            if signed_in {
+                req = req.header(header::AUTHORIZATION, format!("Bearer {}", body.token));
+            }
+            let res = app
+                .clone()
+                .oneshot(req.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(res.status(), expected, "{} {}", method, path);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use casbin::function_map::key_match2;
+
+use crate::config::{IgnoreRoute, MatchType, CFG};
+
+// 判断请求是否命中配置文件中的匿名路由白名单
+pub fn is_ignored(method: &str, path: &str) -> bool {
+    matches_any(&CFG.auth.ignore_routes, method, path)
+}
+
+// 判断请求是否只需要登录，不做 casbin 鉴权
+pub fn is_authenticated_only(method: &str, path: &str) -> bool {
+    matches_any(&CFG.auth.authenticated_routes, method, path)
+}
+
+pub fn matches_any(routes: &[IgnoreRoute], method: &str, path: &str) -> bool {
+    routes
+        .iter()
+        .any(|route| route_matches(route, method, path))
+}
+
+fn route_matches(route: &IgnoreRoute, method: &str, path: &str) -> bool {
+    if !route.methods.is_empty() && !route.methods.iter().any(|m| m.eq_ignore_ascii_case(method)) {
+        return false;
+    }
+
+    // 配置中的路由可以省略开头的 '/'
+    let pattern = route.path.trim_start_matches('/');
+    let path = path.trim_start_matches('/');
+    match route.match_type {
+        MatchType::Exact => path == pattern,
+        MatchType::Prefix => path.starts_with(pattern),
+        MatchType::KeyMatch2 => key_match2(path, pattern),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(path: &str, match_type: MatchType, methods: &[&str]) -> IgnoreRoute {
+        IgnoreRoute {
+            path: path.to_string(),
+            match_type,
+            methods: methods.iter().map(|m| m.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn route_matching() {
+        use MatchType::*;
+        let cases = [
+            // (路由, 请求方法, 请求路径, 是否命中)
+            (
+                route("/api/auth/signin", Exact, &[]),
+                "POST",
+                "/api/auth/signin",
+                true,
+            ),
+            (
+                route("api/auth/signin", Exact, &[]),
+                "GET",
+                "/api/auth/signin",
+                true,
+            ),
+            (
+                route("/api/auth/signin", Exact, &[]),
+                "POST",
+                "/api/auth/signin/x",
+                false,
+            ),
+            (
+                route("/api/auth/signin", Exact, &["POST"]),
+                "post",
+                "/api/auth/signin",
+                true,
+            ),
+            (
+                route("/api/auth/signin", Exact, &["POST"]),
+                "GET",
+                "/api/auth/signin",
+                false,
+            ),
+            (
+                route("/api/public", Prefix, &[]),
+                "GET",
+                "/api/public/docs/1",
+                true,
+            ),
+            (
+                route("/api/public", Prefix, &[]),
+                "GET",
+                "/api/private",
+                false,
+            ),
+            (
+                route("/api/users/:id", KeyMatch2, &[]),
+                "GET",
+                "/api/users/1",
+                true,
+            ),
+            (
+                route("/api/users/:id", KeyMatch2, &[]),
+                "GET",
+                "/api/users/1/roles",
+                false,
+            ),
+            (
+                route("/api/files/*", KeyMatch2, &["GET"]),
+                "GET",
+                "/api/files/a/b",
+                true,
+            ),
+            (
+                route("/api/files/*", KeyMatch2, &["GET"]),
+                "PUT",
+                "/api/files/a/b",
+                false,
+            ),
+            (
+                route("/.well-known/*", KeyMatch2, &[]),
+                "GET",
+                "/.well-known/acme/x",
+                true,
+            ),
+        ];
+        for (route, method, path, expected) in cases {
+            let matched = route_matches(&route, method, path);
+            assert_eq!(matched, expected, "{} {} {:?}", method, path, route);
+        }
+    }
+
+    #[test]
+    fn matches_any_route() {
+        use MatchType::*;
+        let routes = [
+            route("/api/auth/signin", Exact, &["POST"]),
+            route("/health", Prefix, &[]),
+        ];
+        assert!(matches_any(&routes, "POST", "/api/auth/signin"));
+        assert!(matches_any(&routes, "GET", "/healthz"));
+        assert!(!matches_any(&routes, "GET", "/api/auth/signin"));
+        assert!(!matches_any(&[], "GET", "/health"));
+    }
+}
@@ -1,2 +1,4 @@
+pub mod auth;
 pub mod casbin;
-pub mod auth;
\ No newline at end of file
+pub mod ignore;
+pub mod redirect;
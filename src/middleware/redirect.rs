@@ -0,0 +1,205 @@
+use axum::{body, response::Response, BoxError};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use http::{header, uri::Authority, Request, StatusCode};
+use http_body::Body as HttpBody;
+use std::{
+    convert::Infallible,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+use crate::config::IgnoreRoute;
+use crate::middleware::ignore::matches_any;
+
+// 明文端口上把请求 308 跳转到 https，配置中排除的路由照常处理
+#[derive(Clone)]
+pub struct HttpsRedirectLayer {
+    https_port: u16,
+    exclude: Arc<[IgnoreRoute]>,
+}
+
+impl HttpsRedirectLayer {
+    pub fn new(https_port: u16, exclude: Vec<IgnoreRoute>) -> Self {
+        HttpsRedirectLayer {
+            https_port,
+            exclude: exclude.into(),
+        }
+    }
+}
+
+impl<S> Layer<S> for HttpsRedirectLayer {
+    type Service = HttpsRedirectMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HttpsRedirectMiddleware {
+            inner,
+            https_port: self.https_port,
+            exclude: self.exclude.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct HttpsRedirectMiddleware<S> {
+    inner: S,
+    https_port: u16,
+    exclude: Arc<[IgnoreRoute]>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for HttpsRedirectMiddleware<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+    ReqBody: Send + 'static,
+    ResBody: HttpBody<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<BoxError>,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let not_ready_inner = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, not_ready_inner);
+        let https_port = self.https_port;
+        let exclude = self.exclude.clone();
+
+        Box::pin(async move {
+            let (method, path) = (req.method().as_str(), req.uri().path());
+            if matches_any(&exclude, method, path) {
+                return Ok(inner.call(req).await?.map(body::Body::new));
+            }
+
+            let response = match https_location(&req, https_port) {
+                Some(location) => Response::builder()
+                    .status(StatusCode::PERMANENT_REDIRECT)
+                    .header(header::LOCATION, location)
+                    .body(body::Body::empty()),
+                None => Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(body::Body::empty()),
+            };
+            Ok(response.unwrap())
+        })
+    }
+}
+
+// 保留原请求的主机名、路径和查询参数，只替换协议和端口
+fn https_location<B>(req: &Request<B>, https_port: u16) -> Option<String> {
+    let authority: Authority = match req.headers().get(header::HOST) {
+        Some(host) => host.to_str().ok()?.parse().ok()?,
+        None => req.uri().authority()?.clone(),
+    };
+    let path = req.uri().path_and_query().map_or("/", |p| p.as_str());
+    match https_port {
+        443 => Some(format!("https://{}{}", authority.host(), path)),
+        port => Some(format!("https://{}:{}{}", authority.host(), port, path)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, routing::any, Router};
+    use http::{header, Request, StatusCode};
+    use tower::ServiceExt;
+
+    use super::{https_location, HttpsRedirectLayer};
+    use crate::config::{IgnoreRoute, MatchType};
+
+    #[test]
+    fn rewrite_location() {
+        let cases = [
+            // (Host 请求头, 请求地址, https 端口, 跳转地址)
+            (
+                Some("example.com:3080"),
+                "/api/users?page=2&size=10",
+                3443,
+                Some("https://example.com:3443/api/users?page=2&size=10"),
+            ),
+            (Some("example.com"), "/", 443, Some("https://example.com/")),
+            (
+                Some("[::1]:3080"),
+                "/health",
+                443,
+                Some("https://[::1]/health"),
+            ),
+            (
+                None,
+                "http://example.com:3080/signin?next=%2F",
+                443,
+                Some("https://example.com/signin?next=%2F"),
+            ),
+            (None, "/api/users", 443, None),
+            (Some("bad host"), "/api/users", 443, None),
+        ];
+        for (host, uri, port, expected) in cases {
+            let mut req = Request::builder().uri(uri);
+            if let Some(host) = host {
+                req = req.header(header::HOST, host);
+            }
+            let req = req.body(()).unwrap();
+            assert_eq!(https_location(&req, port).as_deref(), expected, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn redirect_except_excluded_routes() {
+        let exclude = vec![IgnoreRoute {
+            path: "/health".to_string(),
+            match_type: MatchType::Exact,
+            methods: vec!["GET".to_string()],
+        }];
+        let app = Router::new()
+            .fallback(any(|| async { "ok" }))
+            .layer(HttpsRedirectLayer::new(3443, exclude));
+
+        let cases = [
+            // (请求方法, 请求路径, 状态码, 跳转地址)
+            ("GET", "/health", StatusCode::OK, None),
+            (
+                "HEAD",
+                "/health",
+                StatusCode::PERMANENT_REDIRECT,
+                Some("https://example.com:3443/health"),
+            ),
+            (
+                "POST",
+                "/api/auth/signin?remember=1",
+                StatusCode::PERMANENT_REDIRECT,
+                Some("https://example.com:3443/api/auth/signin?remember=1"),
+            ),
+        ];
+        for (method, path, status, location) in cases {
+            let req = Request::builder()
+                .method(method)
+                .uri(path)
+                .header(header::HOST, "example.com:3080")
+                .body(Body::empty())
+                .unwrap();
+            let res = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), status, "{} {}", method, path);
+            let actual = res
+                .headers()
+                .get(header::LOCATION)
+                .map(|v| v.to_str().unwrap());
+            assert_eq!(actual, location, "{} {}", method, path);
+        }
+
+        // 没有 Host 请求头时无法拼出跳转地址
+        let req = Request::builder()
+            .uri("/api/users")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,400 @@
+use axum::{
+    extract::{Path, State},
+    routing::{delete, get},
+    Extension, Json, Router,
+};
+use casbin::function_map::key_match2;
+use entity::sys_api_key;
+use jsonwebtoken::get_current_timestamp;
+use rand::RngCore;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, ConnectionTrait,
+    DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::config::CFG;
+use crate::context::{AppState, SharedEnforcer};
+use crate::error::{
+    Result, ERR_API_KEY_ACCOUNT, ERR_API_KEY_NOT_FOUND, ERR_API_KEY_SCOPE, ERR_INVALID_PARAMS,
+    ERR_INVALID_TOKEN, ERR_USER_DISABLED, ERR_USER_NOT_FOUND, OK_API_KEY_CREATE, OK_API_KEY_REVOKE,
+};
+use crate::service::{auth, domain, token};
+use crate::util::jwt::Claims;
+use crate::util::res::Res;
+
+// 所有 API key 都以此开头，便于和 JWT 区分以及在日志、代码仓库中被扫描出来
+pub const KEY_PREFIX: &str = "ak_";
+
+// 域级 key 的 subject 都在此命名空间下，角色和用户不能使用
+pub const SUBJECT_PREFIX: &str = "apikey:";
+
+// last_used_at 的最小更新间隔，避免每个请求都写库
+const TOUCH_INTERVAL: i64 = 60;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list).post(create))
+        .route("/:id", delete(revoke))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateApiKeyReq {
+    pub name: String,
+    // 指定账号时为用户级 key，以该用户的身份访问，只有超级管理员可以为其他用户创建
+    pub account: Option<String>,
+    // 域级 key 在 casbin 中使用的 subject，总是带有 "apikey:" 前缀，默认为 "apikey:<name>"
+    pub subject: Option<String>,
+    // 形如 "GET /api/users/:account" 或 "/api/sessions/*"，为空时只受 casbin 策略限制
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    // 有效期（秒），为空时永不过期
+    pub expires_in: Option<u64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CreateApiKeyRes {
+    // 完整的 key 只在创建时返回一次
+    pub key: String,
+    #[serde(flatten)]
+    pub info: sys_api_key::Model,
+}
+
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Res<Vec<sys_api_key::Model>> {
+    list_keys(&state.conn, &claims.domain).await.into()
+}
+
+pub async fn create(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<CreateApiKeyReq>,
+) -> Res<CreateApiKeyRes> {
+    match do_create(&state.conn, &state.enforcer, &claims, req).await {
+        Ok(res) => Res::with_data_msg(res, &OK_API_KEY_CREATE),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+pub async fn revoke(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+) -> Res<()> {
+    match do_revoke(&state.conn, &claims, id).await {
+        Ok(()) => Res::with_msg(&OK_API_KEY_REVOKE),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+pub async fn list_keys(conn: &DatabaseConnection, domain: &str) -> Result<Vec<sys_api_key::Model>> {
+    let keys = sys_api_key::Entity::find()
+        .filter(sys_api_key::Column::Domain.eq(domain))
+        .order_by_desc(sys_api_key::Column::CreatedAt)
+        .all(conn)
+        .await?;
+    Ok(keys)
+}
+
+// 认证中间件调用，校验 key 并返回代表该 key 的 Claims；scope 不允许时返回 ERR_API_KEY_SCOPE
+pub async fn authenticate(
+    conn: &DatabaseConnection,
+    key: &str,
+    method: &str,
+    path: &str,
+) -> Result<Claims> {
+    let (prefix, secret) = key.split_once('.').ok_or(ERR_INVALID_TOKEN)?;
+    let row = sys_api_key::Entity::find()
+        .filter(sys_api_key::Column::Prefix.eq(prefix))
+        .one(conn)
+        .await?
+        .ok_or(ERR_INVALID_TOKEN)?;
+
+    let now = get_current_timestamp() as i64;
+    if row.key_hash != token::sha256(secret) || row.revoked {
+        return Err(ERR_INVALID_TOKEN);
+    }
+    if row.expires_at != 0 && row.expires_at <= now {
+        return Err(ERR_INVALID_TOKEN);
+    }
+    if !row.account.is_empty() {
+        let user = auth::find_user(conn, &row.domain, &row.account)
+            .await?
+            .ok_or(ERR_INVALID_TOKEN)?;
+        if !user.state {
+            return Err(ERR_USER_DISABLED);
+        }
+    }
+    if !scope_allows(&row.scopes, method, path) {
+        return Err(ERR_API_KEY_SCOPE);
+    }
+
+    if row.last_used_at + TOUCH_INTERVAL <= now {
+        touch(conn.clone(), row.id, now);
+    }
+
+    Ok(Claims {
+        token_id: row.prefix,
+        sub: row.subject,
+        domain: row.domain,
+        name: row.name,
+        iss: CFG.jwt.issuer.clone(),
+        aud: CFG.jwt.audience.clone(),
+        iat: row.created_at as u64,
+        // 0 表示永不过期
+        exp: row.expires_at as u64,
+        home_domain: None,
+    })
+}
+
+async fn do_create(
+    conn: &DatabaseConnection,
+    enforcer: &SharedEnforcer,
+    claims: &Claims,
+    req: CreateApiKeyReq,
+) -> Result<CreateApiKeyRes> {
+    let name = req.name.trim().to_string();
+    if name.is_empty() || !req.scopes.iter().all(|scope| parse_scope(scope).is_some()) {
+        return Err(ERR_INVALID_PARAMS);
+    }
+
+    let (account, subject) = match req.account {
+        Some(account) => {
+            if account != claims.sub && !domain::is_super_admin(enforcer, claims).await {
+                return Err(ERR_API_KEY_ACCOUNT);
+            }
+            let user = auth::find_user(conn, &claims.domain, &account)
+                .await?
+                .ok_or(ERR_USER_NOT_FOUND)?;
+            (user.account.clone(), user.account)
+        }
+        None => {
+            let subject = req.subject.unwrap_or_else(|| name.clone());
+            let subject = subject.trim();
+            let subject = subject.strip_prefix(SUBJECT_PREFIX).unwrap_or(subject);
+            if subject.is_empty() {
+                return Err(ERR_INVALID_PARAMS);
+            }
+            (String::new(), format!("{}{}", SUBJECT_PREFIX, subject))
+        }
+    };
+
+    let mut prefix = [0u8; 6];
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut prefix);
+    rand::thread_rng().fill_bytes(&mut secret);
+    let prefix = format!("{}{}", KEY_PREFIX, hex::encode(prefix));
+    let secret = hex::encode(secret);
+
+    let now = get_current_timestamp() as i64;
+    let info = sys_api_key::ActiveModel {
+        id: NotSet,
+        name: Set(name),
+        prefix: Set(prefix.clone()),
+        key_hash: Set(token::sha256(&secret)),
+        account: Set(account),
+        domain: Set(claims.domain.clone()),
+        subject: Set(subject),
+        scopes: Set(req.scopes.join(",")),
+        expires_at: Set(req.expires_in.map_or(0, |secs| now + secs as i64)),
+        revoked: Set(false),
+        last_used_at: Set(0),
+        created_at: Set(now),
+    }
+    .insert(conn)
+    .await?;
+
+    info!(
+        "user {} created api key {} for subject {} in domain {}",
+        claims.sub, info.prefix, info.subject, info.domain
+    );
+    Ok(CreateApiKeyRes {
+        key: format!("{}.{}", prefix, secret),
+        info,
+    })
+}
+
+async fn do_revoke(conn: &DatabaseConnection, claims: &Claims, id: i32) -> Result<()> {
+    let updated = sys_api_key::Entity::update_many()
+        .col_expr(sys_api_key::Column::Revoked, Expr::value(true))
+        .filter(sys_api_key::Column::Id.eq(id))
+        .filter(sys_api_key::Column::Domain.eq(&claims.domain))
+        .exec(conn)
+        .await?;
+    if updated.rows_affected == 0 {
+        return Err(ERR_API_KEY_NOT_FOUND);
+    }
+    info!(
+        "user {} revoked api key {} in domain {}",
+        claims.sub, id, claims.domain
+    );
+    Ok(())
+}
+
+// 删除用户时吊销其名下的用户级 key
+pub async fn revoke_account<C: ConnectionTrait>(
+    conn: &C,
+    domain: &str,
+    account: &str,
+) -> Result<u64> {
+    let updated = sys_api_key::Entity::update_many()
+        .col_expr(sys_api_key::Column::Revoked, Expr::value(true))
+        .filter(sys_api_key::Column::Domain.eq(domain))
+        .filter(sys_api_key::Column::Account.eq(account))
+        .exec(conn)
+        .await?;
+    Ok(updated.rows_affected)
+}
+
+fn touch(conn: DatabaseConnection, id: i32, now: i64) {
+    tokio::spawn(async move {
+        let result = sys_api_key::Entity::update_many()
+            .col_expr(sys_api_key::Column::LastUsedAt, Expr::value(now))
+            .filter(sys_api_key::Column::Id.eq(id))
+            .exec(&conn)
+            .await;
+        if let Err(err) = result {
+            warn!("failed to update last use of api key {}: {}", id, err);
+        }
+    });
+}
+
+// 解析 "METHOD /path" 或 "/path"，方法为空表示任意方法
+fn parse_scope(scope: &str) -> Option<(Option<&str>, &str)> {
+    let scope = scope.trim();
+    let (method, path) = match scope.split_once(' ') {
+        Some((method, path)) => (Some(method), path.trim()),
+        None => (None, scope),
+    };
+    path.starts_with('/').then_some((method, path))
+}
+
+fn scope_allows(scopes: &str, method: &str, path: &str) -> bool {
+    let mut scopes = scopes
+        .split(',')
+        .filter(|s| !s.trim().is_empty())
+        .peekable();
+    if scopes.peek().is_none() {
+        return true;
+    }
+    scopes.filter_map(parse_scope).any(|(m, pattern)| {
+        m.is_none_or(|m| m == "*" || m.eq_ignore_ascii_case(method)) && key_match2(path, pattern)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{test_state, test_super_admin, test_user};
+    use crate::util::jwt::JWT;
+
+    fn req(name: &str, account: Option<&str>, subject: Option<&str>) -> CreateApiKeyReq {
+        CreateApiKeyReq {
+            name: name.to_string(),
+            account: account.map(str::to_string),
+            subject: subject.map(str::to_string),
+            scopes: vec![],
+            expires_in: None,
+        }
+    }
+
+    #[test]
+    fn scope_parsing() {
+        assert_eq!(parse_scope("/api/users"), Some((None, "/api/users")));
+        assert_eq!(
+            parse_scope(" GET  /api/users/:account "),
+            Some((Some("GET"), "/api/users/:account"))
+        );
+        assert_eq!(parse_scope("GET api/users"), None);
+        assert_eq!(parse_scope("api/users"), None);
+        assert_eq!(parse_scope(""), None);
+    }
+
+    #[test]
+    fn scope_matching() {
+        let cases = [
+            // (scopes, 请求方法, 请求路径, 是否允许)
+            ("", "DELETE", "/api/users/1", true),
+            (" , ", "DELETE", "/api/users/1", true),
+            ("GET /api/users/:account", "GET", "/api/users/alice", true),
+            ("GET /api/users/:account", "get", "/api/users/alice", true),
+            ("GET /api/users/:account", "PUT", "/api/users/alice", false),
+            ("GET /api/users/:account", "GET", "/api/users", false),
+            ("/api/sessions/*", "DELETE", "/api/sessions/a/b", true),
+            ("* /api/sessions/*", "POST", "/api/sessions/a", true),
+            (
+                "GET /api/users,/api/sessions/*",
+                "GET",
+                "/api/sessions/a",
+                true,
+            ),
+            ("GET /api/users,/api/sessions/*", "GET", "/api/roles", false),
+            // 无法解析的 scope 不放行任何请求
+            ("api/users", "GET", "/api/users", false),
+        ];
+        for (scopes, method, path, expected) in cases {
+            let allowed = scope_allows(scopes, method, path);
+            assert_eq!(allowed, expected, "{:?} {} {}", scopes, method, path);
+        }
+    }
+
+    #[tokio::test]
+    async fn only_super_admins_create_keys_for_others() {
+        let state = test_state().await;
+        let (conn, enforcer) = (&state.conn, &state.enforcer);
+        let alice = JWT.claims_for(&test_user(conn, "alice").await);
+        test_user(conn, "bob").await;
+
+        let err = do_create(conn, enforcer, &alice, req("ci", Some("bob"), None))
+            .await
+            .unwrap_err();
+        assert_eq!(err, ERR_API_KEY_ACCOUNT);
+
+        let own = do_create(conn, enforcer, &alice, req("ci", Some("alice"), None))
+            .await
+            .unwrap();
+        assert_eq!(own.info.account, "alice");
+        assert_eq!(own.info.subject, "alice");
+
+        let root = test_super_admin(&state, "root").await;
+        let res = do_create(conn, enforcer, &root, req("ci", Some("bob"), None))
+            .await
+            .unwrap();
+        assert_eq!(res.info.subject, "bob");
+        let claims = authenticate(conn, &res.key, "GET", "/api/users")
+            .await
+            .unwrap();
+        assert_eq!(claims.sub, "bob");
+    }
+
+    #[tokio::test]
+    async fn domain_keys_use_their_own_subjects() {
+        let state = test_state().await;
+        let (conn, enforcer) = (&state.conn, &state.enforcer);
+        let alice = JWT.claims_for(&test_user(conn, "alice").await);
+
+        let cases = [
+            // (名称, 请求的 subject, 实际的 subject)
+            ("ci", None, "apikey:ci"),
+            ("deploy", Some("alice"), "apikey:alice"),
+            ("admin", Some("super_admin"), "apikey:super_admin"),
+            ("bot", Some("apikey:bot"), "apikey:bot"),
+        ];
+        for (name, subject, expected) in cases {
+            let res = do_create(conn, enforcer, &alice, req(name, None, subject))
+                .await
+                .unwrap();
+            assert_eq!(res.info.subject, expected);
+            assert_eq!(res.info.account, "");
+        }
+
+        for subject in [" ", "apikey:"] {
+            let err = do_create(conn, enforcer, &alice, req("ci", None, Some(subject)))
+                .await
+                .unwrap_err();
+            assert_eq!(err, ERR_INVALID_PARAMS);
+        }
+    }
+}
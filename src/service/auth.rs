@@ -0,0 +1,663 @@
+use axum::{extract::State, routing::post, Extension, Json, Router};
+use entity::{sys_password_history, sys_user};
+use jsonwebtoken::get_current_timestamp;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, DatabaseConnection,
+    EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::config::CFG;
+use crate::context::AppState;
+use crate::error::{
+    Result, ERR_ACCOUNT_EXISTS, ERR_ACCOUNT_LOCKED, ERR_INVALID_PARAMS, ERR_MFA_REQUIRED,
+    ERR_PASSWORD_CHANGE_REQUIRED, ERR_PASSWORD_REUSED, ERR_PASSWORD_TOO_WEAK, ERR_SIGNIN_FAILED,
+    ERR_SIGNIN_REQUIRED, ERR_USER_DISABLED, OK_PASSWORD_CHANGE, OK_SIGNIN, OK_SIGNOUT, OK_SIGNUP,
+};
+use crate::service::{domain, role, session, token, totp};
+use crate::util::jwt::{AuthBody, Claims, JWT};
+use crate::util::password::{self, Verified};
+use crate::util::res::Res;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/signin", post(signin))
+        .route("/register", post(signup))
+        .route("/signout", post(signout))
+        .route("/refresh", post(refresh))
+        .route("/password", post(change_password).put(update_password))
+        .nest("/2fa", totp::router())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SigninReq {
+    pub account: String,
+    pub password: String,
+    pub domain: String,
+}
+
+// 开启两步验证的用户登录时先返回中间 token
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+pub enum SigninBody {
+    Token(AuthBody),
+    Mfa { mfa_token: String, expires_in: u64 },
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RefreshReq {
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ChangePasswordReq {
+    pub account: String,
+    pub domain: String,
+    pub old_password: String,
+    pub new_password: String,
+    // 开启两步验证时需要验证码或恢复码
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UpdatePasswordReq {
+    pub old_password: String,
+    pub new_password: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SignupReq {
+    pub account: String,
+    pub password: String,
+    pub name: String,
+    pub domain: String,
+    #[serde(default)]
+    pub email: String,
+    #[serde(default)]
+    pub phone: String,
+}
+
+pub async fn signin(State(state): State<AppState>, Json(req): Json<SigninReq>) -> Res<SigninBody> {
+    match do_signin(&state.conn, req).await {
+        Ok(body @ SigninBody::Token(_)) => Res::with_data_msg(body, &OK_SIGNIN),
+        Ok(body @ SigninBody::Mfa { .. }) => Res::with_data_msg(body, &ERR_MFA_REQUIRED),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+pub async fn signup(State(state): State<AppState>, Json(req): Json<SignupReq>) -> Res<()> {
+    match create_user(&state.conn, req).await {
+        Ok(_) => Res::with_msg(&OK_SIGNUP),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+pub async fn signout(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Res<()> {
+    if let Err(err) = session::revoke_session(&state.conn, &claims.domain, &claims.token_id).await {
+        return Res::with_err(&err);
+    }
+    info!("user {} signed out of domain {}", claims.sub, claims.domain);
+    Res::with_msg(&OK_SIGNOUT)
+}
+
+pub async fn refresh(State(state): State<AppState>, Json(req): Json<RefreshReq>) -> Res<AuthBody> {
+    token::rotate(&state.conn, &req.refresh_token).await.into()
+}
+
+// 必须修改密码的用户无法登录，只有这种情况下允许不带 token 使用旧密码修改密码
+pub async fn change_password(
+    State(state): State<AppState>,
+    Json(req): Json<ChangePasswordReq>,
+) -> Res<()> {
+    match do_change_password(&state.conn, req).await {
+        Ok(()) => Res::with_msg(&OK_PASSWORD_CHANGE),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+// 已登录用户修改自己的密码
+pub async fn update_password(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<UpdatePasswordReq>,
+) -> Res<()> {
+    match do_update_password(&state.conn, &claims, req).await {
+        Ok(()) => Res::with_msg(&OK_PASSWORD_CHANGE),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+pub async fn find_user(
+    conn: &DatabaseConnection,
+    domain: &str,
+    account: &str,
+) -> Result<Option<sys_user::Model>> {
+    let user = sys_user::Entity::find()
+        .filter(sys_user::Column::Domain.eq(domain))
+        .filter(sys_user::Column::Account.eq(account))
+        .one(conn)
+        .await?;
+    Ok(user)
+}
+
+async fn do_signin(conn: &DatabaseConnection, req: SigninReq) -> Result<SigninBody> {
+    let user = authenticate(conn, &req.domain, &req.account, req.password).await?;
+    if user.must_change_password {
+        return Err(ERR_PASSWORD_CHANGE_REQUIRED);
+    }
+    if totp::is_enabled(conn, user.id).await? {
+        let (mfa_token, expires_in) = JWT.issue_mfa(&user)?;
+        return Ok(SigninBody::Mfa {
+            mfa_token,
+            expires_in,
+        });
+    }
+
+    let body = token::issue_pair(conn, &user, None).await?;
+    info!("user {} signed in to domain {}", user.account, user.domain);
+    Ok(SigninBody::Token(body))
+}
+
+// 校验账号密码，处理登录失败计数和临时锁定
+async fn authenticate(
+    conn: &DatabaseConnection,
+    domain: &str,
+    account: &str,
+    plain: String,
+) -> Result<sys_user::Model> {
+    let (user, verified) = verify_credentials(conn, domain, account, plain.clone()).await?;
+    if user.login_fail_count != 0 || user.locked_until != 0 {
+        sys_user::Entity::update_many()
+            .col_expr(sys_user::Column::LoginFailCount, Expr::value(0))
+            .col_expr(sys_user::Column::LockedUntil, Expr::value(0))
+            .filter(sys_user::Column::Id.eq(user.id))
+            .exec(conn)
+            .await?;
+    }
+    if verified == Verified::NeedsRehash {
+        rehash_password(conn, &user, plain).await;
+    }
+    Ok(user)
+}
+
+// 只校验密码，不清除失败计数，调用方还需要校验其他因素时使用
+async fn verify_credentials(
+    conn: &DatabaseConnection,
+    domain: &str,
+    account: &str,
+    plain: String,
+) -> Result<(sys_user::Model, Verified)> {
+    domain::ensure_enabled(domain)?;
+    let now = get_current_timestamp() as i64;
+    let user = find_user(conn, domain, account).await?;
+    if user.as_ref().is_some_and(|u| u.locked_until > now) {
+        return Err(ERR_ACCOUNT_LOCKED);
+    }
+
+    // 用户不存在和密码错误返回同样的提示，避免暴露账号是否存在
+    let hash = user.as_ref().map(|u| u.password.clone());
+    let verified = password::verify_blocking(plain, hash).await?;
+    let Some(user) = user else {
+        return Err(ERR_SIGNIN_FAILED);
+    };
+    if !verified.is_valid() {
+        record_failure(conn, &user, now).await?;
+        return Err(ERR_SIGNIN_FAILED);
+    }
+    if !user.state {
+        return Err(ERR_USER_DISABLED);
+    }
+    Ok((user, verified))
+}
+
+pub async fn record_failure(
+    conn: &DatabaseConnection,
+    user: &sys_user::Model,
+    now: i64,
+) -> Result<()> {
+    let lockout = &CFG.password.lockout;
+    let failures = user.login_fail_count + 1;
+    let stmt = sys_user::Entity::update_many().filter(sys_user::Column::Id.eq(user.id));
+    if lockout.max_failures > 0 && failures >= lockout.max_failures {
+        warn!(
+            "user {} in domain {} locked after {} failed sign-in attempts",
+            user.account, user.domain, failures
+        );
+        stmt.col_expr(sys_user::Column::LoginFailCount, Expr::value(0))
+            .col_expr(
+                sys_user::Column::LockedUntil,
+                Expr::value(now + lockout.duration),
+            )
+            .exec(conn)
+            .await?;
+    } else {
+        stmt.col_expr(
+            sys_user::Column::LoginFailCount,
+            Expr::col(sys_user::Column::LoginFailCount).add(1),
+        )
+        .exec(conn)
+        .await?;
+    }
+    Ok(())
+}
+
+// 旧算法或旧参数的 hash 在登录成功后升级为当前配置的 Argon2id，失败不影响登录
+async fn rehash_password(conn: &DatabaseConnection, user: &sys_user::Model, plain: String) {
+    let hash = match password::hash_blocking(plain).await {
+        Ok(hash) => hash,
+        Err(_) => return,
+    };
+    let mut model: sys_user::ActiveModel = user.clone().into();
+    model.password = Set(hash);
+    match model.update(conn).await {
+        Ok(_) => info!("upgraded password hash of user {}", user.account),
+        Err(err) => warn!(
+            "failed to upgrade password hash of user {}: {}",
+            user.account, err
+        ),
+    }
+}
+
+// 注册、管理员新建用户和新建域共用，校验密码策略和账号唯一性
+pub async fn create_user(conn: &DatabaseConnection, req: SignupReq) -> Result<sys_user::Model> {
+    if req.account.trim().is_empty() || req.password.is_empty() || req.domain.trim().is_empty() {
+        return Err(ERR_INVALID_PARAMS);
+    }
+    domain::ensure_enabled(&req.domain)?;
+    if !password::check_policy(&req.password) {
+        return Err(ERR_PASSWORD_TOO_WEAK);
+    }
+    // 与角色重名的账号会直接拥有该角色的权限
+    if role::subject_taken(conn, &req.domain, &req.account).await? {
+        return Err(ERR_ACCOUNT_EXISTS);
+    }
+
+    let hash = password::hash_blocking(req.password).await?;
+    let user = sys_user::ActiveModel {
+        id: NotSet,
+        account: Set(req.account),
+        password: Set(hash),
+        name: Set(req.name),
+        domain: Set(req.domain),
+        avatar: Set(String::new()),
+        email: Set(req.email),
+        phone: Set(req.phone),
+        pw_reset_count: Set(0),
+        state: Set(true),
+        login_fail_count: Set(0),
+        locked_until: Set(0),
+        must_change_password: Set(false),
+    };
+    let user = user.insert(conn).await?;
+    record_history(conn, user.id, user.password.clone()).await?;
+    Ok(user)
+}
+
+async fn do_change_password(conn: &DatabaseConnection, req: ChangePasswordReq) -> Result<()> {
+    // 两步验证通过前不清除失败计数，避免用正确的密码反复尝试验证码
+    let (user, _) = verify_credentials(conn, &req.domain, &req.account, req.old_password).await?;
+    if !user.must_change_password {
+        return Err(ERR_SIGNIN_REQUIRED);
+    }
+    totp::ensure_second_factor(conn, &user, req.code, req.recovery_code).await?;
+    replace_password(conn, &user, req.new_password).await
+}
+
+async fn do_update_password(
+    conn: &DatabaseConnection,
+    claims: &Claims,
+    req: UpdatePasswordReq,
+) -> Result<()> {
+    // 超级管理员跨域操作时 claims.domain 为目标域，账号在其所在的平台域
+    let domain = claims.home_domain.as_deref().unwrap_or(&claims.domain);
+    let user = authenticate(conn, domain, &claims.sub, req.old_password).await?;
+    replace_password(conn, &user, req.new_password).await
+}
+
+async fn replace_password(
+    conn: &DatabaseConnection,
+    user: &sys_user::Model,
+    new_password: String,
+) -> Result<()> {
+    if !password::check_policy(&new_password) {
+        return Err(ERR_PASSWORD_TOO_WEAK);
+    }
+    ensure_not_reused(conn, user, &new_password).await?;
+    set_password(conn, user, new_password, false).await?;
+
+    // 修改密码后让该用户所有已登录的会话失效
+    session::revoke_user(conn, &user.domain, &user.account).await?;
+    info!(
+        "user {} in domain {} changed password",
+        user.account, user.domain
+    );
+    Ok(())
+}
+
+// 新密码不能与当前密码及最近使用过的密码相同
+async fn ensure_not_reused(
+    conn: &DatabaseConnection,
+    user: &sys_user::Model,
+    plain: &str,
+) -> Result<()> {
+    let history = CFG.password.policy.history;
+    if history == 0 {
+        return Ok(());
+    }
+    let mut hashes: Vec<String> = sys_password_history::Entity::find()
+        .filter(sys_password_history::Column::UserId.eq(user.id))
+        .order_by_desc(sys_password_history::Column::Id)
+        .limit(history)
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|h| h.password)
+        .collect();
+    hashes.push(user.password.clone());
+    if password::matches_any_blocking(plain.to_string(), hashes).await? {
+        return Err(ERR_PASSWORD_REUSED);
+    }
+    Ok(())
+}
+
+// 设置新密码并解除锁定；由管理员重置时累加 pw_reset_count 并要求用户下次登录前修改密码
+pub async fn set_password(
+    conn: &DatabaseConnection,
+    user: &sys_user::Model,
+    plain: String,
+    reset_by_admin: bool,
+) -> Result<()> {
+    let hash = password::hash_blocking(plain).await?;
+    let mut model: sys_user::ActiveModel = user.clone().into();
+    model.password = Set(hash.clone());
+    model.must_change_password = Set(reset_by_admin);
+    model.login_fail_count = Set(0);
+    model.locked_until = Set(0);
+    if reset_by_admin {
+        model.pw_reset_count = Set(user.pw_reset_count + 1);
+    }
+    model.update(conn).await?;
+    record_history(conn, user.id, hash).await
+}
+
+async fn record_history(conn: &DatabaseConnection, user_id: i32, hash: String) -> Result<()> {
+    let history = CFG.password.policy.history;
+    if history == 0 {
+        return Ok(());
+    }
+    sys_password_history::ActiveModel {
+        id: NotSet,
+        user_id: Set(user_id),
+        password: Set(hash),
+        created_at: Set(get_current_timestamp() as i64),
+    }
+    .insert(conn)
+    .await?;
+
+    // 只保留最近 history 条记录
+    let stale: Vec<i32> = sys_password_history::Entity::find()
+        .filter(sys_password_history::Column::UserId.eq(user_id))
+        .order_by_desc(sys_password_history::Column::Id)
+        .all(conn)
+        .await?
+        .into_iter()
+        .skip(history as usize)
+        .map(|h| h.id)
+        .collect();
+    if !stale.is_empty() {
+        sys_password_history::Entity::delete_many()
+            .filter(sys_password_history::Column::Id.is_in(stale))
+            .exec(conn)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TENANT_ADMIN_ROLE;
+    use crate::context::{test_db, test_user};
+    use crate::error::ERR_TOTP_INVALID;
+    use crate::service::token::{issue_pair, sha256};
+    use entity::{sys_recovery_code, sys_user_totp};
+
+    const PASSWORD: &str = "Secret-123";
+
+    fn change_req(user: &sys_user::Model, new_password: &str) -> ChangePasswordReq {
+        ChangePasswordReq {
+            account: user.account.clone(),
+            domain: user.domain.clone(),
+            old_password: PASSWORD.to_string(),
+            new_password: new_password.to_string(),
+            code: None,
+            recovery_code: None,
+        }
+    }
+
+    async fn reload(conn: &DatabaseConnection, user: &sys_user::Model) -> sys_user::Model {
+        find_user(conn, &user.domain, &user.account)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    // 模拟管理员重置密码后要求用户修改密码
+    async fn reset_by_admin(conn: &DatabaseConnection, user: &sys_user::Model) -> sys_user::Model {
+        set_password(conn, user, PASSWORD.to_string(), true)
+            .await
+            .unwrap();
+        reload(conn, user).await
+    }
+
+    async fn enable_totp(conn: &DatabaseConnection, user: &sys_user::Model, recovery_code: &str) {
+        sys_user_totp::ActiveModel {
+            id: NotSet,
+            user_id: Set(user.id),
+            secret: Set("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_string()),
+            enabled: Set(true),
+            last_used_step: Set(0),
+            created_at: Set(0),
+        }
+        .insert(conn)
+        .await
+        .unwrap();
+        sys_recovery_code::ActiveModel {
+            id: NotSet,
+            user_id: Set(user.id),
+            code_hash: Set(sha256(recovery_code)),
+            used: Set(false),
+            created_at: Set(0),
+        }
+        .insert(conn)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn tokenless_change_requires_pending_reset() {
+        let conn = test_db().await;
+        let user = test_user(&conn, "kim").await;
+        let session = issue_pair(&conn, &user, None).await.unwrap();
+
+        let err = do_change_password(&conn, change_req(&user, "Changed-456")).await;
+        assert_eq!(err.unwrap_err(), ERR_SIGNIN_REQUIRED);
+        assert!(!session::is_revoked(
+            &JWT.verify(&session.token).unwrap().token_id
+        ));
+
+        let user = reset_by_admin(&conn, &user).await;
+        do_change_password(&conn, change_req(&user, "Changed-456"))
+            .await
+            .unwrap();
+        let user = reload(&conn, &user).await;
+        assert!(!user.must_change_password);
+        assert!(password::verify("Changed-456", &user.password).is_valid());
+    }
+
+    #[tokio::test]
+    async fn tokenless_change_requires_second_factor() {
+        let conn = test_db().await;
+        let user = test_user(&conn, "liam").await;
+        enable_totp(&conn, &user, "ABCDE23456").await;
+        let user = reset_by_admin(&conn, &user).await;
+
+        let err = do_change_password(&conn, change_req(&user, "Changed-456")).await;
+        assert_eq!(err.unwrap_err(), ERR_TOTP_INVALID);
+        let mut req = change_req(&user, "Changed-456");
+        req.code = Some("000000".to_string());
+        assert_eq!(
+            do_change_password(&conn, req).await.unwrap_err(),
+            ERR_TOTP_INVALID
+        );
+        assert_eq!(reload(&conn, &user).await.login_fail_count, 2);
+
+        let mut req = change_req(&user, "Changed-456");
+        req.recovery_code = Some("abcde-23456".to_string());
+        do_change_password(&conn, req).await.unwrap();
+        assert!(!reload(&conn, &user).await.must_change_password);
+    }
+
+    #[tokio::test]
+    async fn signed_in_user_changes_password() {
+        let conn = test_db().await;
+        let user = test_user(&conn, "mia").await;
+        let session = issue_pair(&conn, &user, None).await.unwrap();
+        let claims = JWT.verify(&session.token).unwrap();
+
+        let req = UpdatePasswordReq {
+            old_password: "wrong".to_string(),
+            new_password: "Changed-456".to_string(),
+        };
+        let err = do_update_password(&conn, &claims, req).await.unwrap_err();
+        assert_eq!(err, ERR_SIGNIN_FAILED);
+
+        let req = UpdatePasswordReq {
+            old_password: PASSWORD.to_string(),
+            new_password: "Changed-456".to_string(),
+        };
+        do_update_password(&conn, &claims, req).await.unwrap();
+        assert!(password::verify("Changed-456", &reload(&conn, &user).await.password).is_valid());
+        assert!(session::is_revoked(&claims.token_id));
+    }
+
+    #[tokio::test]
+    async fn lockout_after_repeated_failures() {
+        let conn = test_db().await;
+        let user = test_user(&conn, "noah").await;
+        let lockout = &CFG.password.lockout;
+        let signin =
+            |password: &str| authenticate(&conn, &user.domain, &user.account, password.to_string());
+
+        for _ in 1..lockout.max_failures {
+            assert_eq!(signin("wrong").await.unwrap_err(), ERR_SIGNIN_FAILED);
+        }
+        assert_eq!(
+            reload(&conn, &user).await.login_fail_count,
+            lockout.max_failures - 1
+        );
+        // 成功登录后清零
+        signin(PASSWORD).await.unwrap();
+        assert_eq!(reload(&conn, &user).await.login_fail_count, 0);
+
+        for _ in 0..lockout.max_failures {
+            assert_eq!(signin("wrong").await.unwrap_err(), ERR_SIGNIN_FAILED);
+        }
+        let locked = reload(&conn, &user).await;
+        assert!(locked.locked_until > get_current_timestamp() as i64);
+        assert_eq!(signin(PASSWORD).await.unwrap_err(), ERR_ACCOUNT_LOCKED);
+
+        // 锁定到期后可以正常登录
+        sys_user::Entity::update_many()
+            .col_expr(sys_user::Column::LockedUntil, Expr::value(1))
+            .filter(sys_user::Column::Id.eq(user.id))
+            .exec(&conn)
+            .await
+            .unwrap();
+        signin(PASSWORD).await.unwrap();
+        assert_eq!(reload(&conn, &user).await.locked_until, 0);
+    }
+
+    #[tokio::test]
+    async fn password_policy_and_history() {
+        let conn = test_db().await;
+        let user = test_user(&conn, "olivia").await;
+        let history = CFG.password.policy.history as usize;
+        assert!(history > 1);
+
+        let err = replace_password(&conn, &user, "weak".to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(err, ERR_PASSWORD_TOO_WEAK);
+        let err = replace_password(&conn, &user, PASSWORD.to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(err, ERR_PASSWORD_REUSED);
+
+        let passwords: Vec<String> = (0..history).map(|i| format!("Changed-{}", i)).collect();
+        for password in &passwords {
+            let user = reload(&conn, &user).await;
+            replace_password(&conn, &user, password.clone())
+                .await
+                .unwrap();
+        }
+        let user = reload(&conn, &user).await;
+        for password in &passwords {
+            let err = ensure_not_reused(&conn, &user, password).await.unwrap_err();
+            assert_eq!(err, ERR_PASSWORD_REUSED);
+        }
+        // 只保留最近 history 条记录，更早的密码可以再次使用
+        ensure_not_reused(&conn, &user, PASSWORD).await.unwrap();
+        let rows = sys_password_history::Entity::find()
+            .filter(sys_password_history::Column::UserId.eq(user.id))
+            .all(&conn)
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), history);
+    }
+
+    #[tokio::test]
+    async fn accounts_can_not_shadow_roles() {
+        let conn = test_db().await;
+        let domain = CFG.auth.platform_domain.clone();
+        entity::sys_role::ActiveModel {
+            id: NotSet,
+            name: Set("auditor".to_string()),
+            state: Set(true),
+            parent_id: Set(0),
+            domain: Set(domain.clone()),
+            data_scope: Set(Default::default()),
+        }
+        .insert(&conn)
+        .await
+        .unwrap();
+
+        let reserved = [
+            CFG.auth.super_admin_role.as_str(),
+            TENANT_ADMIN_ROLE,
+            "auditor",
+        ];
+        for account in reserved {
+            let req = SignupReq {
+                account: account.to_string(),
+                password: PASSWORD.to_string(),
+                name: account.to_string(),
+                domain: domain.clone(),
+                email: String::new(),
+                phone: String::new(),
+            };
+            assert_eq!(
+                create_user(&conn, req).await.unwrap_err(),
+                ERR_ACCOUNT_EXISTS
+            );
+        }
+        assert!(find_user(&conn, &domain, "auditor")
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
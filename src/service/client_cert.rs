@@ -0,0 +1,129 @@
+use sea_orm::DatabaseConnection;
+
+use crate::config::{CertMapping, Mtls, CFG};
+use crate::error::{Result, ERR_INVALID_TOKEN, ERR_USER_DISABLED};
+use crate::service::auth;
+use crate::util::jwt::Claims;
+use crate::util::tls::ClientCert;
+
+// 认证中间件调用，把客户端证书映射为 sys_user 账号或 casbin subject
+pub async fn authenticate(conn: &DatabaseConnection, cert: &ClientCert) -> Result<Claims> {
+    map_cert(conn, &CFG.server.mtls, cert).await
+}
+
+async fn map_cert(conn: &DatabaseConnection, mtls: &Mtls, cert: &ClientCert) -> Result<Claims> {
+    let identity = cert.identity(mtls.identity).ok_or(ERR_INVALID_TOKEN)?;
+
+    let (sub, name) = match mtls.mapping {
+        CertMapping::User => {
+            let user = auth::find_user(conn, &mtls.domain, identity)
+                .await?
+                .ok_or(ERR_INVALID_TOKEN)?;
+            if !user.state {
+                return Err(ERR_USER_DISABLED);
+            }
+            (user.account, user.name)
+        }
+        CertMapping::Subject => (identity.to_string(), identity.to_string()),
+    };
+
+    Ok(Claims {
+        token_id: cert.fingerprint.clone(),
+        sub,
+        domain: mtls.domain.clone(),
+        name,
+        iss: CFG.jwt.issuer.clone(),
+        aud: CFG.jwt.audience.clone(),
+        iat: 0,
+        exp: 0,
+        home_domain: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CertIdentity;
+    use crate::context::{test_db, test_user};
+    use entity::sys_user;
+    use sea_orm::{ActiveModelTrait, Set};
+
+    // CN=device-01，SAN 中各有一个 dns、email 和 uri
+    fn client_cert() -> ClientCert {
+        let pem = std::fs::read("resource/cert/test/client.pem").unwrap();
+        let der = rustls_pemfile::certs(&mut pem.as_slice())
+            .next()
+            .unwrap()
+            .unwrap();
+        ClientCert::parse(&der).unwrap()
+    }
+
+    fn mtls(identity: CertIdentity, mapping: CertMapping) -> Mtls {
+        Mtls {
+            identity,
+            mapping,
+            domain: CFG.auth.platform_domain.clone(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_identities() {
+        let cert = client_cert();
+        assert_eq!(cert.fingerprint.len(), 64);
+        // (身份位置, 取到的身份)
+        let cases = [
+            (CertIdentity::CommonName, "device-01"),
+            (CertIdentity::Dns, "device-01.local"),
+            (CertIdentity::Email, "device-01@example.com"),
+            (CertIdentity::Uri, "spiffe://rust-admin/device-01"),
+        ];
+        for (kind, identity) in cases {
+            assert_eq!(cert.identity(kind), Some(identity), "{:?}", kind);
+        }
+        assert_eq!(
+            ClientCert::default().identity(CertIdentity::CommonName),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn map_to_user() {
+        let conn = test_db().await;
+        let cert = client_cert();
+        let mtls = mtls(CertIdentity::CommonName, CertMapping::User);
+        assert_eq!(
+            map_cert(&conn, &mtls, &cert).await.unwrap_err(),
+            ERR_INVALID_TOKEN
+        );
+
+        let user = test_user(&conn, "device-01").await;
+        let claims = map_cert(&conn, &mtls, &cert).await.unwrap();
+        assert_eq!(claims.sub, user.account);
+        assert_eq!(claims.domain, user.domain);
+        assert_eq!(claims.token_id, cert.fingerprint);
+
+        let mut model: sys_user::ActiveModel = user.into();
+        model.state = Set(false);
+        model.update(&conn).await.unwrap();
+        assert_eq!(
+            map_cert(&conn, &mtls, &cert).await.unwrap_err(),
+            ERR_USER_DISABLED
+        );
+    }
+
+    #[tokio::test]
+    async fn map_to_subject() {
+        let conn = test_db().await;
+        let cert = client_cert();
+
+        // 映射为 casbin subject 时不要求存在同名用户
+        let mtls = mtls(CertIdentity::Uri, CertMapping::Subject);
+        let claims = map_cert(&conn, &mtls, &cert).await.unwrap();
+        assert_eq!(claims.sub, "spiffe://rust-admin/device-01");
+        assert_eq!(claims.domain, CFG.auth.platform_domain);
+
+        let claims = map_cert(&conn, &mtls, &ClientCert::default()).await;
+        assert_eq!(claims.unwrap_err(), ERR_INVALID_TOKEN);
+    }
+}
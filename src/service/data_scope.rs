@@ -0,0 +1,257 @@
+use std::collections::HashSet;
+
+use entity::{
+    sys_dept,
+    sys_role::{self, DataScope},
+    sys_role_dept, sys_user_dept, sys_user_role,
+};
+use sea_orm::{
+    sea_query::Query, ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter,
+};
+
+use crate::error::Result;
+use crate::service::{auth, dept};
+use crate::util::jwt::Claims;
+use crate::util::tree;
+
+// 当前请求可以访问的数据范围，由用户直接拥有的所有启用角色的数据范围合并而来
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scope {
+    All,
+    Limited {
+        dept_ids: HashSet<i32>,
+        // 仅本人时为当前用户的 id
+        user_id: Option<i32>,
+    },
+}
+
+impl Scope {
+    // 生成过滤行的条件，user_col 为表中记录所属用户 id 的列
+    pub fn condition<C: ColumnTrait>(&self, user_col: C) -> Condition {
+        let (dept_ids, user_id) = match self {
+            Scope::All => return Condition::all(),
+            Scope::Limited { dept_ids, user_id } => (dept_ids, user_id),
+        };
+        let mut condition = Condition::any();
+        if !dept_ids.is_empty() {
+            let members = Query::select()
+                .column(sys_user_dept::Column::UserId)
+                .from(sys_user_dept::Entity)
+                .and_where(sys_user_dept::Column::DeptId.is_in(dept_ids.iter().copied()))
+                .to_owned();
+            condition = condition.add(user_col.in_subquery(members));
+        }
+        if let Some(user_id) = user_id {
+            condition = condition.add(user_col.eq(*user_id));
+        }
+        if dept_ids.is_empty() && user_id.is_none() {
+            // 没有任何可访问的数据
+            condition = condition.add(user_col.is_in(Vec::<i32>::new()));
+        }
+        condition
+    }
+}
+
+// 不是用户的主体（域级 API key、映射为 subject 的客户端证书）和跨域操作的超级管理员
+// 不做数据范围限制，只受 casbin 策略约束；没有启用角色的用户只能访问自己的数据
+pub async fn resolve(conn: &DatabaseConnection, claims: &Claims) -> Result<Scope> {
+    if claims.home_domain.is_some() {
+        return Ok(Scope::All);
+    }
+    let Some(user) = auth::find_user(conn, &claims.domain, &claims.sub).await? else {
+        return Ok(Scope::All);
+    };
+    let role_ids: Vec<i32> = sys_user_role::Entity::find()
+        .filter(sys_user_role::Column::UserId.eq(user.id))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|m| m.role_id)
+        .collect();
+    let roles = sys_role::Entity::find()
+        .filter(sys_role::Column::Id.is_in(role_ids))
+        .filter(sys_role::Column::State.eq(true))
+        .all(conn)
+        .await?;
+    if roles.is_empty() {
+        return Ok(Scope::Limited {
+            dept_ids: HashSet::new(),
+            user_id: Some(user.id),
+        });
+    }
+
+    let user_depts: Vec<i32> = sys_user_dept::Entity::find()
+        .filter(sys_user_dept::Column::UserId.eq(user.id))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|m| m.dept_id)
+        .collect();
+    let mut domain_depts: Option<Vec<sys_dept::Model>> = None;
+    let mut dept_ids = HashSet::new();
+    let mut user_id = None;
+    for role in roles {
+        match role.data_scope {
+            DataScope::All => return Ok(Scope::All),
+            DataScope::Dept => dept_ids.extend(user_depts.iter().copied()),
+            DataScope::DeptAndChildren => {
+                if domain_depts.is_none() {
+                    domain_depts = Some(dept::domain_depts(conn, &claims.domain).await?);
+                }
+                let depts = domain_depts.as_deref().unwrap_or_default();
+                for &id in &user_depts {
+                    dept_ids.extend(tree::subtree_ids(depts, id));
+                }
+            }
+            DataScope::OnlySelf => user_id = Some(user.id),
+            DataScope::Custom => dept_ids.extend(role_depts(conn, role.id).await?),
+        }
+    }
+    Ok(Scope::Limited { dept_ids, user_id })
+}
+
+// 自定义数据范围的角色可以访问的部门
+pub async fn role_depts(conn: &DatabaseConnection, role_id: i32) -> Result<Vec<i32>> {
+    let dept_ids = sys_role_dept::Entity::find()
+        .filter(sys_role_dept::Column::RoleId.eq(role_id))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|m| m.dept_id)
+        .collect();
+    Ok(dept_ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{test_db, test_user};
+    use sea_orm::{ActiveModelTrait, ActiveValue::NotSet, Set};
+
+    async fn insert_role(
+        conn: &DatabaseConnection,
+        name: &str,
+        data_scope: DataScope,
+        state: bool,
+    ) -> i32 {
+        let role = sys_role::ActiveModel {
+            id: NotSet,
+            name: Set(name.to_string()),
+            state: Set(state),
+            parent_id: Set(0),
+            domain: Set(crate::config::CFG.auth.platform_domain.clone()),
+            data_scope: Set(data_scope),
+        }
+        .insert(conn)
+        .await
+        .unwrap();
+        role.id
+    }
+
+    async fn insert_dept(conn: &DatabaseConnection, name: &str, parent_id: i32) -> i32 {
+        let dept = sys_dept::ActiveModel {
+            id: NotSet,
+            name: Set(name.to_string()),
+            parent_id: Set(parent_id),
+            leader: Set(String::new()),
+            sort: Set(0),
+            state: Set(true),
+            domain: Set(crate::config::CFG.auth.platform_domain.clone()),
+        }
+        .insert(conn)
+        .await
+        .unwrap();
+        dept.id
+    }
+
+    async fn assign(conn: &DatabaseConnection, user_id: i32, role_id: i32) {
+        sys_user_role::ActiveModel {
+            id: NotSet,
+            user_id: Set(user_id),
+            role_id: Set(role_id),
+            created_at: Set(0),
+        }
+        .insert(conn)
+        .await
+        .unwrap();
+    }
+
+    fn limited(dept_ids: &[i32], user_id: Option<i32>) -> Scope {
+        Scope::Limited {
+            dept_ids: dept_ids.iter().copied().collect(),
+            user_id,
+        }
+    }
+
+    #[tokio::test]
+    async fn users_without_enabled_roles_only_see_themselves() {
+        let conn = test_db().await;
+        let user = test_user(&conn, "paul").await;
+        let claims = crate::util::jwt::JWT.claims_for(&user);
+        assert_eq!(
+            resolve(&conn, &claims).await.unwrap(),
+            limited(&[], Some(user.id))
+        );
+
+        let role = insert_role(&conn, "auditor", DataScope::All, false).await;
+        assign(&conn, user.id, role).await;
+        assert_eq!(
+            resolve(&conn, &claims).await.unwrap(),
+            limited(&[], Some(user.id))
+        );
+    }
+
+    #[tokio::test]
+    async fn role_scopes_are_merged() {
+        let conn = test_db().await;
+        let user = test_user(&conn, "paul").await;
+        let claims = crate::util::jwt::JWT.claims_for(&user);
+        let root = insert_dept(&conn, "root", 0).await;
+        let child = insert_dept(&conn, "child", root).await;
+        let other = insert_dept(&conn, "other", 0).await;
+        sys_user_dept::ActiveModel {
+            id: NotSet,
+            user_id: Set(user.id),
+            dept_id: Set(root),
+            created_at: Set(0),
+        }
+        .insert(&conn)
+        .await
+        .unwrap();
+
+        let dept = insert_role(&conn, "dept", DataScope::Dept, true).await;
+        assign(&conn, user.id, dept).await;
+        assert_eq!(
+            resolve(&conn, &claims).await.unwrap(),
+            limited(&[root], None)
+        );
+
+        let custom = insert_role(&conn, "custom", DataScope::Custom, true).await;
+        sys_role_dept::ActiveModel {
+            id: NotSet,
+            role_id: Set(custom),
+            dept_id: Set(other),
+        }
+        .insert(&conn)
+        .await
+        .unwrap();
+        assign(&conn, user.id, custom).await;
+        let own = insert_role(&conn, "own", DataScope::OnlySelf, true).await;
+        assign(&conn, user.id, own).await;
+        let expected = limited(&[root, other], Some(user.id));
+        assert_eq!(resolve(&conn, &claims).await.unwrap(), expected);
+
+        let children = insert_role(&conn, "children", DataScope::DeptAndChildren, true).await;
+        assign(&conn, user.id, children).await;
+        let expected = limited(&[root, child, other], Some(user.id));
+        assert_eq!(resolve(&conn, &claims).await.unwrap(), expected);
+
+        // 禁用的角色不参与合并，任一角色为全部数据时不再限制
+        let disabled = insert_role(&conn, "disabled", DataScope::All, false).await;
+        assign(&conn, user.id, disabled).await;
+        assert_eq!(resolve(&conn, &claims).await.unwrap(), expected);
+        let all = insert_role(&conn, "all", DataScope::All, true).await;
+        assign(&conn, user.id, all).await;
+        assert_eq!(resolve(&conn, &claims).await.unwrap(), Scope::All);
+    }
+}
@@ -0,0 +1,570 @@
+use axum::{
+    extract::{Path, Query, State},
+    routing::{delete, get, put},
+    Extension, Json, Router,
+};
+use entity::{sys_dept, sys_role_dept, sys_user, sys_user_dept};
+use jsonwebtoken::get_current_timestamp;
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ActiveModelTrait,
+    ActiveValue::NotSet,
+    ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, Set, TransactionTrait,
+};
+use serde::Deserialize;
+
+use crate::context::AppState;
+use crate::error::{
+    Result, ERR_DEPT_HAS_CHILDREN, ERR_DEPT_NOT_FOUND, ERR_INVALID_PARAMS, ERR_USER_NOT_FOUND,
+    OK_DELETE_DEPT,
+};
+use crate::service::auth;
+use crate::service::data_scope::{self, Scope};
+use crate::util::jwt::Claims;
+use crate::util::res::Res;
+use crate::util::tree::{self, Tree};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list).post(create))
+        .route("/:id", get(detail).put(update).delete(remove))
+        .route("/:id/move", put(move_to))
+        .route("/:id/users", get(members).post(add_members))
+        .route("/:id/users/:account", delete(remove_member))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateDeptReq {
+    pub name: String,
+    // 0 表示顶级部门
+    #[serde(default)]
+    pub parent_id: i32,
+    // 负责人账号
+    #[serde(default)]
+    pub leader: String,
+    // 为空时排在同级部门的最后
+    pub sort: Option<i32>,
+    #[serde(default = "default_state")]
+    pub state: bool,
+}
+
+// 只修改传入的字段，层级和顺序通过 move 修改；leader 传空字符串表示清除负责人
+#[derive(Deserialize, Debug, Default)]
+pub struct UpdateDeptReq {
+    pub name: Option<String>,
+    pub leader: Option<String>,
+    pub state: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MoveDeptReq {
+    pub parent_id: i32,
+    pub sort: Option<i32>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct MembersQuery {
+    // 为 true 时包含所有子部门的成员
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AddMembersReq {
+    pub accounts: Vec<String>,
+}
+
+fn default_state() -> bool {
+    true
+}
+
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Res<Vec<Tree<sys_dept::Model>>> {
+    dept_tree(&state.conn, &claims.domain).await.into()
+}
+
+pub async fn detail(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+) -> Res<sys_dept::Model> {
+    find_dept(&state.conn, &claims.domain, id).await.into()
+}
+
+pub async fn create(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<CreateDeptReq>,
+) -> Res<sys_dept::Model> {
+    do_create(&state.conn, &claims, req).await.into()
+}
+
+pub async fn update(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+    Json(req): Json<UpdateDeptReq>,
+) -> Res<sys_dept::Model> {
+    do_update(&state.conn, &claims, id, req).await.into()
+}
+
+// 有子部门时拒绝删除，部门的成员关系和角色的自定义数据范围一并删除
+pub async fn remove(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+) -> Res<()> {
+    match do_remove(&state.conn, &claims, id).await {
+        Ok(()) => Res::with_msg(&OK_DELETE_DEPT),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+pub async fn move_to(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+    Json(req): Json<MoveDeptReq>,
+) -> Res<sys_dept::Model> {
+    do_move(&state.conn, &claims, id, req).await.into()
+}
+
+pub async fn members(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+    Query(query): Query<MembersQuery>,
+) -> Res<Vec<sys_user::Model>> {
+    let conn = &state.conn;
+    match data_scope::resolve(conn, &claims).await {
+        Ok(scope) => dept_members(conn, &claims.domain, &scope, id, query.recursive)
+            .await
+            .into(),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+pub async fn add_members(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+    Json(req): Json<AddMembersReq>,
+) -> Res<()> {
+    do_add_members(&state.conn, &claims, id, req.accounts)
+        .await
+        .into()
+}
+
+pub async fn remove_member(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((id, account)): Path<(i32, String)>,
+) -> Res<()> {
+    do_remove_member(&state.conn, &claims, id, &account)
+        .await
+        .into()
+}
+
+pub async fn dept_tree(
+    conn: &DatabaseConnection,
+    domain: &str,
+) -> Result<Vec<Tree<sys_dept::Model>>> {
+    Ok(tree::build(domain_depts(conn, domain).await?))
+}
+
+pub async fn find_dept(
+    conn: &DatabaseConnection,
+    domain: &str,
+    id: i32,
+) -> Result<sys_dept::Model> {
+    sys_dept::Entity::find_by_id(id)
+        .filter(sys_dept::Column::Domain.eq(domain))
+        .one(conn)
+        .await?
+        .ok_or(ERR_DEPT_NOT_FOUND)
+}
+
+pub async fn domain_depts(conn: &DatabaseConnection, domain: &str) -> Result<Vec<sys_dept::Model>> {
+    let depts = sys_dept::Entity::find()
+        .filter(sys_dept::Column::Domain.eq(domain))
+        .order_by_asc(sys_dept::Column::Sort)
+        .order_by_asc(sys_dept::Column::Id)
+        .all(conn)
+        .await?;
+    Ok(depts)
+}
+
+pub async fn dept_members(
+    conn: &DatabaseConnection,
+    domain: &str,
+    scope: &Scope,
+    id: i32,
+    recursive: bool,
+) -> Result<Vec<sys_user::Model>> {
+    let dept = find_dept(conn, domain, id).await?;
+    let dept_ids = match recursive {
+        true => tree::subtree_ids(&domain_depts(conn, domain).await?, dept.id),
+        false => vec![dept.id],
+    };
+    let user_ids: Vec<i32> = sys_user_dept::Entity::find()
+        .filter(sys_user_dept::Column::DeptId.is_in(dept_ids))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|m| m.user_id)
+        .collect();
+    let users = sys_user::Entity::find()
+        .filter(sys_user::Column::Id.is_in(user_ids))
+        .filter(scope.condition(sys_user::Column::Id))
+        .order_by_asc(sys_user::Column::Id)
+        .all(conn)
+        .await?;
+    Ok(users)
+}
+
+// 删除用户时清理其部门关系和负责人设置
+pub async fn remove_user<C: ConnectionTrait>(conn: &C, user: &sys_user::Model) -> Result<()> {
+    sys_user_dept::Entity::delete_many()
+        .filter(sys_user_dept::Column::UserId.eq(user.id))
+        .exec(conn)
+        .await?;
+    sys_dept::Entity::update_many()
+        .col_expr(sys_dept::Column::Leader, Expr::value(""))
+        .filter(sys_dept::Column::Domain.eq(&user.domain))
+        .filter(sys_dept::Column::Leader.eq(&user.account))
+        .exec(conn)
+        .await?;
+    Ok(())
+}
+
+// 负责人必须是同一个域中的用户，空字符串表示不设置
+async fn check_leader(conn: &DatabaseConnection, domain: &str, leader: &str) -> Result<String> {
+    let leader = leader.trim();
+    if leader.is_empty() {
+        return Ok(String::new());
+    }
+    let user = auth::find_user(conn, domain, leader)
+        .await?
+        .ok_or(ERR_USER_NOT_FOUND)?;
+    Ok(user.account)
+}
+
+async fn do_create(
+    conn: &DatabaseConnection,
+    claims: &Claims,
+    req: CreateDeptReq,
+) -> Result<sys_dept::Model> {
+    let name = req.name.trim().to_string();
+    if name.is_empty() {
+        return Err(ERR_INVALID_PARAMS);
+    }
+    tree::ensure_parent_valid::<sys_dept::Entity>(conn, &claims.domain, None, req.parent_id)
+        .await?;
+    let leader = check_leader(conn, &claims.domain, &req.leader).await?;
+    let sort = match req.sort {
+        Some(sort) => sort,
+        None => tree::next_sort::<sys_dept::Entity>(conn, &claims.domain, req.parent_id).await?,
+    };
+
+    let dept = sys_dept::ActiveModel {
+        id: NotSet,
+        name: Set(name),
+        parent_id: Set(req.parent_id),
+        leader: Set(leader),
+        sort: Set(sort),
+        state: Set(req.state),
+        domain: Set(claims.domain.clone()),
+    }
+    .insert(conn)
+    .await?;
+    info!(
+        "user {} created dept {} in domain {}",
+        claims.sub, dept.name, dept.domain
+    );
+    Ok(dept)
+}
+
+async fn do_update(
+    conn: &DatabaseConnection,
+    claims: &Claims,
+    id: i32,
+    req: UpdateDeptReq,
+) -> Result<sys_dept::Model> {
+    let mut model: sys_dept::ActiveModel = find_dept(conn, &claims.domain, id).await?.into();
+    if let Some(name) = req.name {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Err(ERR_INVALID_PARAMS);
+        }
+        model.name = Set(name);
+    }
+    if let Some(leader) = req.leader {
+        model.leader = Set(check_leader(conn, &claims.domain, &leader).await?);
+    }
+    if let Some(state) = req.state {
+        model.state = Set(state);
+    }
+    let dept = model.update(conn).await?;
+    info!(
+        "user {} updated dept {} in domain {}",
+        claims.sub, dept.id, dept.domain
+    );
+    Ok(dept)
+}
+
+async fn do_remove(conn: &DatabaseConnection, claims: &Claims, id: i32) -> Result<()> {
+    let dept = find_dept(conn, &claims.domain, id).await?;
+    let children = sys_dept::Entity::find()
+        .filter(sys_dept::Column::Domain.eq(&claims.domain))
+        .filter(sys_dept::Column::ParentId.eq(dept.id))
+        .count(conn)
+        .await?;
+    if children > 0 {
+        return Err(ERR_DEPT_HAS_CHILDREN);
+    }
+    let txn = conn.begin().await?;
+    sys_user_dept::Entity::delete_many()
+        .filter(sys_user_dept::Column::DeptId.eq(dept.id))
+        .exec(&txn)
+        .await?;
+    sys_role_dept::Entity::delete_many()
+        .filter(sys_role_dept::Column::DeptId.eq(dept.id))
+        .exec(&txn)
+        .await?;
+    sys_dept::Entity::delete_by_id(dept.id).exec(&txn).await?;
+    txn.commit().await?;
+    info!(
+        "user {} deleted dept {} in domain {}",
+        claims.sub, dept.name, dept.domain
+    );
+    Ok(())
+}
+
+async fn do_move(
+    conn: &DatabaseConnection,
+    claims: &Claims,
+    id: i32,
+    req: MoveDeptReq,
+) -> Result<sys_dept::Model> {
+    let dept = find_dept(conn, &claims.domain, id).await?;
+    tree::ensure_parent_valid::<sys_dept::Entity>(conn, &claims.domain, Some(id), req.parent_id)
+        .await?;
+    let sort = match req.sort {
+        Some(sort) => sort,
+        None if dept.parent_id == req.parent_id => dept.sort,
+        None => tree::next_sort::<sys_dept::Entity>(conn, &claims.domain, req.parent_id).await?,
+    };
+
+    let mut model: sys_dept::ActiveModel = dept.into();
+    model.parent_id = Set(req.parent_id);
+    model.sort = Set(sort);
+    let dept = model.update(conn).await?;
+    info!(
+        "user {} moved dept {} under {} at {}",
+        claims.sub, dept.id, dept.parent_id, dept.sort
+    );
+    Ok(dept)
+}
+
+async fn do_add_members(
+    conn: &DatabaseConnection,
+    claims: &Claims,
+    id: i32,
+    accounts: Vec<String>,
+) -> Result<()> {
+    let dept = find_dept(conn, &claims.domain, id).await?;
+    let mut users = Vec::with_capacity(accounts.len());
+    for account in &accounts {
+        let user = auth::find_user(conn, &claims.domain, account)
+            .await?
+            .ok_or(ERR_USER_NOT_FOUND)?;
+        users.push(user);
+    }
+    if users.is_empty() {
+        return Ok(());
+    }
+
+    let now = get_current_timestamp() as i64;
+    let models = users.iter().map(|user| sys_user_dept::ActiveModel {
+        id: NotSet,
+        user_id: Set(user.id),
+        dept_id: Set(dept.id),
+        created_at: Set(now),
+    });
+    sys_user_dept::Entity::insert_many(models)
+        .on_conflict(
+            OnConflict::columns([sys_user_dept::Column::UserId, sys_user_dept::Column::DeptId])
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(conn)
+        .await?;
+    info!(
+        "user {} added {:?} to dept {} in domain {}",
+        claims.sub, accounts, dept.name, dept.domain
+    );
+    Ok(())
+}
+
+async fn do_remove_member(
+    conn: &DatabaseConnection,
+    claims: &Claims,
+    id: i32,
+    account: &str,
+) -> Result<()> {
+    let dept = find_dept(conn, &claims.domain, id).await?;
+    let user = auth::find_user(conn, &claims.domain, account)
+        .await?
+        .ok_or(ERR_USER_NOT_FOUND)?;
+    sys_user_dept::Entity::delete_many()
+        .filter(sys_user_dept::Column::DeptId.eq(dept.id))
+        .filter(sys_user_dept::Column::UserId.eq(user.id))
+        .exec(conn)
+        .await?;
+    info!(
+        "user {} removed {} from dept {} in domain {}",
+        claims.sub, account, dept.name, dept.domain
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{test_db, test_user};
+    use crate::error::{ERR_DEPT_PARENT_INVALID, ERR_INTERNAL};
+    use crate::util::jwt::JWT;
+
+    async fn create(
+        conn: &DatabaseConnection,
+        claims: &Claims,
+        name: &str,
+        parent_id: i32,
+    ) -> Result<sys_dept::Model> {
+        let req = CreateDeptReq {
+            name: name.to_string(),
+            parent_id,
+            leader: String::new(),
+            sort: None,
+            state: true,
+        };
+        do_create(conn, claims, req).await
+    }
+
+    fn move_req(parent_id: i32) -> MoveDeptReq {
+        MoveDeptReq {
+            parent_id,
+            sort: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn tree_and_move() {
+        let conn = &test_db().await;
+        let claims = JWT.claims_for(&test_user(conn, "root").await);
+        let hq = create(conn, &claims, "hq", 0).await.unwrap();
+        let sales = create(conn, &claims, "sales", hq.id).await.unwrap();
+        let dev = create(conn, &claims, "dev", hq.id).await.unwrap();
+        let team = create(conn, &claims, "team", dev.id).await.unwrap();
+        assert_eq!((sales.sort, dev.sort, team.sort), (0, 1, 0));
+
+        let trees = dept_tree(conn, &claims.domain).await.unwrap();
+        assert_eq!(trees.len(), 1);
+        let names: Vec<&str> = trees[0]
+            .children
+            .iter()
+            .map(|t| t.node.name.as_str())
+            .collect();
+        assert_eq!(names, ["sales", "dev"]);
+        assert_eq!(trees[0].children[1].children[0].node.id, team.id);
+
+        // 父部门不存在、在其他域或者是自己的子孙时拒绝
+        let mut other = claims.clone();
+        other.domain = "other".to_string();
+        let foreign = create(conn, &other, "foreign", 0).await.unwrap();
+        let err = create(conn, &claims, "x", 9999).await.unwrap_err();
+        assert_eq!(err, ERR_DEPT_PARENT_INVALID);
+        for parent_id in [foreign.id, dev.id, team.id] {
+            let err = do_move(conn, &claims, dev.id, move_req(parent_id))
+                .await
+                .unwrap_err();
+            assert_eq!(err, ERR_DEPT_PARENT_INVALID);
+        }
+
+        // 移动到其他父部门时排在最后，同级移动时保持原来的顺序
+        let moved = do_move(conn, &claims, dev.id, move_req(sales.id))
+            .await
+            .unwrap();
+        assert_eq!((moved.parent_id, moved.sort), (sales.id, 0));
+        let moved = do_move(conn, &claims, team.id, move_req(sales.id))
+            .await
+            .unwrap();
+        assert_eq!((moved.parent_id, moved.sort), (sales.id, 1));
+        let moved = do_move(conn, &claims, team.id, move_req(sales.id))
+            .await
+            .unwrap();
+        assert_eq!(moved.sort, 1);
+        let ids = tree::subtree_ids(&domain_depts(conn, &claims.domain).await.unwrap(), hq.id);
+        assert_eq!(ids, [hq.id, sales.id, dev.id, team.id]);
+    }
+
+    #[tokio::test]
+    async fn remove_dept() {
+        let conn = &test_db().await;
+        let claims = JWT.claims_for(&test_user(conn, "root").await);
+        test_user(conn, "ann").await;
+        let hq = create(conn, &claims, "hq", 0).await.unwrap();
+        let sales = create(conn, &claims, "sales", hq.id).await.unwrap();
+        do_add_members(conn, &claims, sales.id, vec!["ann".to_string()])
+            .await
+            .unwrap();
+        sys_role_dept::ActiveModel {
+            id: NotSet,
+            role_id: Set(1),
+            dept_id: Set(sales.id),
+        }
+        .insert(conn)
+        .await
+        .unwrap();
+        let links = || async {
+            let members = sys_user_dept::Entity::find()
+                .filter(sys_user_dept::Column::DeptId.eq(sales.id))
+                .count(conn)
+                .await
+                .unwrap();
+            let roles = sys_role_dept::Entity::find()
+                .filter(sys_role_dept::Column::DeptId.eq(sales.id))
+                .count(conn)
+                .await
+                .unwrap();
+            (members, roles)
+        };
+
+        let err = do_remove(conn, &claims, hq.id).await.unwrap_err();
+        assert_eq!(err, ERR_DEPT_HAS_CHILDREN);
+
+        // 删除部门失败时成员关系和数据范围都不会被删除
+        conn.execute_unprepared(
+            "CREATE TRIGGER keep_dept BEFORE DELETE ON sys_dept \
+             BEGIN SELECT RAISE(ABORT, 'locked'); END",
+        )
+        .await
+        .unwrap();
+        let err = do_remove(conn, &claims, sales.id).await.unwrap_err();
+        assert_eq!(err, ERR_INTERNAL);
+        assert_eq!(links().await, (1, 1));
+        find_dept(conn, &claims.domain, sales.id).await.unwrap();
+
+        conn.execute_unprepared("DROP TRIGGER keep_dept")
+            .await
+            .unwrap();
+        do_remove(conn, &claims, sales.id).await.unwrap();
+        assert_eq!(links().await, (0, 0));
+        let err = find_dept(conn, &claims.domain, sales.id).await.unwrap_err();
+        assert_eq!(err, ERR_DEPT_NOT_FOUND);
+        do_remove(conn, &claims, hq.id).await.unwrap();
+    }
+}
@@ -0,0 +1,577 @@
+use std::{collections::HashMap, sync::RwLock, time::Duration};
+
+use axum::{
+    extract::{Path, State},
+    routing::{get, put},
+    Extension, Json, Router,
+};
+use casbin::{MgmtApi, RbacApi};
+use entity::{
+    sys_api_key, sys_dept, sys_domain, sys_menu, sys_menu_operation,
+    sys_role::{self, DataScope},
+    sys_role_dept, sys_user, sys_user_dept, sys_user_role,
+};
+use jsonwebtoken::get_current_timestamp;
+use once_cell::sync::Lazy;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, DatabaseConnection, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, Set, TransactionTrait,
+};
+use serde::Deserialize;
+
+use crate::config::CFG;
+use crate::constants::{TENANT_ADMIN_RESOURCES, TENANT_ADMIN_ROLE};
+use crate::context::{AppState, SharedEnforcer};
+use crate::error::{
+    Result, ERR_ACCOUNT_EXISTS, ERR_DOMAIN_DISABLED, ERR_DOMAIN_EXISTS, ERR_DOMAIN_NOT_EMPTY,
+    ERR_DOMAIN_NOT_FOUND, ERR_DOMAIN_PROTECTED, ERR_INVALID_PARAMS, ERR_NOT_SUPER_ADMIN,
+    ERR_PASSWORD_TOO_WEAK, OK_DELETE_DOMAIN,
+};
+use crate::service::auth::{self, SignupReq};
+use crate::service::role;
+use crate::util::jwt::Claims;
+use crate::util::password;
+use crate::util::res::Res;
+
+// 域状态缓存的同步间隔，多实例部署时其他实例的修改最多延迟这么久生效
+const SYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+const METHODS: [&str; 4] = ["GET", "POST", "PUT", "DELETE"];
+
+// 域 code -> 是否启用
+static DOMAINS: Lazy<RwLock<HashMap<String, bool>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list).post(create))
+        .route("/:code", get(detail).put(update).delete(remove))
+        .route("/:code/state", put(set_state))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateDomainReq {
+    pub code: String,
+    pub name: String,
+    // 同时创建的域管理员，加入 tenant_admin 角色
+    pub admin: Option<DomainAdminReq>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DomainAdminReq {
+    pub account: String,
+    pub password: String,
+    pub name: String,
+    #[serde(default)]
+    pub email: String,
+    #[serde(default)]
+    pub phone: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UpdateDomainReq {
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DomainStateReq {
+    pub state: bool,
+}
+
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Res<Vec<sys_domain::Model>> {
+    let result = async {
+        ensure_super_admin(&state.enforcer, &claims).await?;
+        let domains = sys_domain::Entity::find()
+            .order_by_asc(sys_domain::Column::Id)
+            .all(&state.conn)
+            .await?;
+        Ok(domains)
+    };
+    result.await.into()
+}
+
+pub async fn detail(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(code): Path<String>,
+) -> Res<sys_domain::Model> {
+    let result = async {
+        ensure_super_admin(&state.enforcer, &claims).await?;
+        find_domain(&state.conn, &code).await
+    };
+    result.await.into()
+}
+
+pub async fn create(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<CreateDomainReq>,
+) -> Res<sys_domain::Model> {
+    do_create(&state, &claims, req).await.into()
+}
+
+pub async fn update(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(code): Path<String>,
+    Json(req): Json<UpdateDomainReq>,
+) -> Res<sys_domain::Model> {
+    do_update(&state, &claims, &code, req).await.into()
+}
+
+// 禁用后该域的用户无法登录，已签发的 token 和 API key 也立即失效
+pub async fn set_state(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(code): Path<String>,
+    Json(req): Json<DomainStateReq>,
+) -> Res<sys_domain::Model> {
+    do_set_state(&state, &claims, &code, req.state).await.into()
+}
+
+// 只能删除没有用户的域，域内的角色、菜单、部门、API key 和 casbin 规则一并删除
+pub async fn remove(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(code): Path<String>,
+) -> Res<()> {
+    match do_remove(&state, &claims, &code).await {
+        Ok(()) => Res::with_msg(&OK_DELETE_DOMAIN),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+pub fn is_enabled(code: &str) -> bool {
+    DOMAINS.read().unwrap().get(code).copied().unwrap_or(false)
+}
+
+// 不存在的域和禁用的域都视为不可用
+pub fn ensure_enabled(code: &str) -> Result<()> {
+    match is_enabled(code) {
+        true => Ok(()),
+        false => Err(ERR_DOMAIN_DISABLED),
+    }
+}
+
+// 超级管理员是平台域中拥有 super_admin_role 角色（包括通过角色继承）的主体
+pub async fn is_super_admin(enforcer: &SharedEnforcer, claims: &Claims) -> bool {
+    let home = claims.home_domain.as_deref().unwrap_or(&claims.domain);
+    if home != CFG.auth.platform_domain {
+        return false;
+    }
+    enforcer
+        .write()
+        .await
+        .get_implicit_roles_for_user(&claims.sub, Some(home))
+        .contains(&CFG.auth.super_admin_role)
+}
+
+pub async fn ensure_super_admin(enforcer: &SharedEnforcer, claims: &Claims) -> Result<()> {
+    match is_super_admin(enforcer, claims).await {
+        true => Ok(()),
+        false => Err(ERR_NOT_SUPER_ADMIN),
+    }
+}
+
+pub async fn find_domain(conn: &DatabaseConnection, code: &str) -> Result<sys_domain::Model> {
+    sys_domain::Entity::find()
+        .filter(sys_domain::Column::Code.eq(code))
+        .one(conn)
+        .await?
+        .ok_or(ERR_DOMAIN_NOT_FOUND)
+}
+
+pub async fn load_domains(conn: &DatabaseConnection) -> Result<()> {
+    let domains = sys_domain::Entity::find().all(conn).await?;
+    let mut cache = DOMAINS.write().unwrap();
+    cache.clear();
+    cache.extend(domains.into_iter().map(|d| (d.code, d.state)));
+    Ok(())
+}
+
+pub async fn sync_domains(conn: DatabaseConnection) {
+    let mut interval = tokio::time::interval(SYNC_INTERVAL);
+    loop {
+        interval.tick().await;
+        if load_domains(&conn).await.is_err() {
+            warn!("failed to sync domains");
+        }
+    }
+}
+
+// 启动时确保平台域和超级管理员角色存在，并把配置中的账号加入该角色
+pub async fn bootstrap(conn: &DatabaseConnection, enforcer: &SharedEnforcer) -> Result<()> {
+    let platform = &CFG.auth.platform_domain;
+    if find_domain(conn, platform).await.is_err() {
+        insert_domain(conn, platform, platform).await?;
+    }
+    let role = provision_role(
+        conn,
+        enforcer,
+        platform,
+        &CFG.auth.super_admin_role,
+        &["/api/*"],
+    )
+    .await?;
+
+    let mut users = Vec::new();
+    for account in &CFG.auth.super_admins {
+        match auth::find_user(conn, platform, account).await? {
+            Some(user) => users.push(user),
+            None => warn!(
+                "super admin {} does not exist in domain {}",
+                account, platform
+            ),
+        }
+    }
+    role::assign_users(conn, enforcer, &role, &users).await?;
+    load_domains(conn).await
+}
+
+// 创建角色（已存在时直接使用），并为其授予 paths 上所有请求方法的权限
+async fn provision_role(
+    conn: &DatabaseConnection,
+    enforcer: &SharedEnforcer,
+    domain: &str,
+    name: &str,
+    paths: &[&str],
+) -> Result<sys_role::Model> {
+    let existing = sys_role::Entity::find()
+        .filter(sys_role::Column::Domain.eq(domain))
+        .filter(sys_role::Column::Name.eq(name))
+        .one(conn)
+        .await?;
+    let role = match existing {
+        Some(role) => role,
+        None => {
+            sys_role::ActiveModel {
+                id: NotSet,
+                name: Set(name.to_string()),
+                state: Set(true),
+                parent_id: Set(0),
+                domain: Set(domain.to_string()),
+                data_scope: Set(DataScope::All),
+            }
+            .insert(conn)
+            .await?
+        }
+    };
+
+    let mut enforcer = enforcer.write().await;
+    let policies: Vec<Vec<String>> = paths
+        .iter()
+        .flat_map(|path| {
+            METHODS.iter().map(move |method| {
+                vec![
+                    name.to_string(),
+                    domain.to_string(),
+                    path.to_string(),
+                    method.to_string(),
+                ]
+            })
+        })
+        .filter(|p| !enforcer.has_policy(p.clone()))
+        .collect();
+    if !policies.is_empty() {
+        enforcer.add_policies(policies).await?;
+    }
+    Ok(role)
+}
+
+pub async fn insert_domain(
+    conn: &DatabaseConnection,
+    code: &str,
+    name: &str,
+) -> Result<sys_domain::Model> {
+    let domain = sys_domain::ActiveModel {
+        id: NotSet,
+        code: Set(code.to_string()),
+        name: Set(name.to_string()),
+        state: Set(true),
+        created_at: Set(get_current_timestamp() as i64),
+    }
+    .insert(conn)
+    .await?;
+    DOMAINS
+        .write()
+        .unwrap()
+        .insert(domain.code.clone(), domain.state);
+    Ok(domain)
+}
+
+// code 会出现在 casbin 规则和请求头中，只允许字母、数字、下划线、中划线和点
+fn valid_code(code: &str) -> bool {
+    !code.is_empty()
+        && code.len() <= 64
+        && code
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+async fn do_create(
+    state: &AppState,
+    claims: &Claims,
+    req: CreateDomainReq,
+) -> Result<sys_domain::Model> {
+    ensure_super_admin(&state.enforcer, claims).await?;
+    let conn = &state.conn;
+    let code = req.code.trim().to_string();
+    let name = req.name.trim().to_string();
+    if !valid_code(&code) || name.is_empty() {
+        return Err(ERR_INVALID_PARAMS);
+    }
+    if find_domain(conn, &code).await.is_ok() {
+        return Err(ERR_DOMAIN_EXISTS);
+    }
+    // 提前做 create_user 中的校验，避免域已经创建而管理员账号创建失败
+    if let Some(admin) = &req.admin {
+        if !password::check_policy(&admin.password) {
+            return Err(ERR_PASSWORD_TOO_WEAK);
+        }
+        if role::subject_taken(conn, &code, &admin.account).await? {
+            return Err(ERR_ACCOUNT_EXISTS);
+        }
+    }
+
+    let domain = insert_domain(conn, &code, &name).await?;
+    let paths: Vec<String> = TENANT_ADMIN_RESOURCES
+        .iter()
+        .flat_map(|r| [format!("/api/{}", r), format!("/api/{}/*", r)])
+        .collect();
+    let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+    let role = provision_role(conn, &state.enforcer, &code, TENANT_ADMIN_ROLE, &paths).await?;
+
+    if let Some(admin) = req.admin {
+        let user = auth::create_user(
+            conn,
+            SignupReq {
+                account: admin.account,
+                password: admin.password,
+                name: admin.name,
+                domain: code.clone(),
+                email: admin.email,
+                phone: admin.phone,
+            },
+        )
+        .await?;
+        role::assign_users(conn, &state.enforcer, &role, &[user]).await?;
+    }
+    info!("user {} created domain {}", claims.sub, domain.code);
+    Ok(domain)
+}
+
+async fn do_update(
+    state: &AppState,
+    claims: &Claims,
+    code: &str,
+    req: UpdateDomainReq,
+) -> Result<sys_domain::Model> {
+    ensure_super_admin(&state.enforcer, claims).await?;
+    let name = req.name.trim().to_string();
+    if name.is_empty() {
+        return Err(ERR_INVALID_PARAMS);
+    }
+    let mut model: sys_domain::ActiveModel = find_domain(&state.conn, code).await?.into();
+    model.name = Set(name);
+    Ok(model.update(&state.conn).await?)
+}
+
+async fn do_set_state(
+    state: &AppState,
+    claims: &Claims,
+    code: &str,
+    enabled: bool,
+) -> Result<sys_domain::Model> {
+    ensure_super_admin(&state.enforcer, claims).await?;
+    if code == CFG.auth.platform_domain {
+        return Err(ERR_DOMAIN_PROTECTED);
+    }
+    let mut model: sys_domain::ActiveModel = find_domain(&state.conn, code).await?.into();
+    model.state = Set(enabled);
+    let domain = model.update(&state.conn).await?;
+    DOMAINS
+        .write()
+        .unwrap()
+        .insert(domain.code.clone(), domain.state);
+    info!(
+        "user {} set domain {} state to {}",
+        claims.sub, domain.code, domain.state
+    );
+    Ok(domain)
+}
+
+async fn do_remove(state: &AppState, claims: &Claims, code: &str) -> Result<()> {
+    ensure_super_admin(&state.enforcer, claims).await?;
+    let conn = &state.conn;
+    if code == CFG.auth.platform_domain {
+        return Err(ERR_DOMAIN_PROTECTED);
+    }
+    let domain = find_domain(conn, code).await?;
+    let users = sys_user::Entity::find()
+        .filter(sys_user::Column::Domain.eq(code))
+        .count(conn)
+        .await?;
+    if users > 0 {
+        return Err(ERR_DOMAIN_NOT_EMPTY);
+    }
+
+    // 数据库中的级联删除放在一个事务中，提交后再修改 casbin 规则
+    let txn = conn.begin().await?;
+    let role_ids: Vec<i32> = sys_role::Entity::find()
+        .filter(sys_role::Column::Domain.eq(code))
+        .all(&txn)
+        .await?
+        .into_iter()
+        .map(|r| r.id)
+        .collect();
+    sys_role_dept::Entity::delete_many()
+        .filter(sys_role_dept::Column::RoleId.is_in(role_ids.clone()))
+        .exec(&txn)
+        .await?;
+    sys_user_role::Entity::delete_many()
+        .filter(sys_user_role::Column::RoleId.is_in(role_ids))
+        .exec(&txn)
+        .await?;
+    sys_role::Entity::delete_many()
+        .filter(sys_role::Column::Domain.eq(code))
+        .exec(&txn)
+        .await?;
+
+    let menu_ids: Vec<i32> = sys_menu::Entity::find()
+        .filter(sys_menu::Column::Domain.eq(code))
+        .all(&txn)
+        .await?
+        .into_iter()
+        .map(|m| m.id)
+        .collect();
+    sys_menu_operation::Entity::delete_many()
+        .filter(sys_menu_operation::Column::MenuId.is_in(menu_ids))
+        .exec(&txn)
+        .await?;
+    sys_menu::Entity::delete_many()
+        .filter(sys_menu::Column::Domain.eq(code))
+        .exec(&txn)
+        .await?;
+
+    let dept_ids: Vec<i32> = sys_dept::Entity::find()
+        .filter(sys_dept::Column::Domain.eq(code))
+        .all(&txn)
+        .await?
+        .into_iter()
+        .map(|d| d.id)
+        .collect();
+    sys_user_dept::Entity::delete_many()
+        .filter(sys_user_dept::Column::DeptId.is_in(dept_ids))
+        .exec(&txn)
+        .await?;
+    sys_dept::Entity::delete_many()
+        .filter(sys_dept::Column::Domain.eq(code))
+        .exec(&txn)
+        .await?;
+    sys_api_key::Entity::delete_many()
+        .filter(sys_api_key::Column::Domain.eq(code))
+        .exec(&txn)
+        .await?;
+
+    sys_domain::Entity::delete_by_id(domain.id)
+        .exec(&txn)
+        .await?;
+    txn.commit().await?;
+
+    {
+        let mut enforcer = state.enforcer.write().await;
+        let policies = enforcer.get_filtered_policy(1, vec![code.to_string()]);
+        if !policies.is_empty() {
+            enforcer.remove_policies(policies).await?;
+        }
+        let rules = enforcer.get_filtered_grouping_policy(2, vec![code.to_string()]);
+        if !rules.is_empty() {
+            enforcer.remove_grouping_policies(rules).await?;
+        }
+    }
+
+    DOMAINS.write().unwrap().remove(code);
+    info!("user {} deleted domain {}", claims.sub, code);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{test_state, test_super_admin};
+    use sea_orm::ConnectionTrait;
+
+    fn create_req(code: &str, account: &str, password: &str) -> CreateDomainReq {
+        CreateDomainReq {
+            code: code.to_string(),
+            name: code.to_string(),
+            admin: Some(DomainAdminReq {
+                account: account.to_string(),
+                password: password.to_string(),
+                name: account.to_string(),
+                email: String::new(),
+                phone: String::new(),
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_admin_account_creates_nothing() {
+        let state = test_state().await;
+        let claims = test_super_admin(&state, "root").await;
+
+        let cases = [
+            (TENANT_ADMIN_ROLE, "Secret-123", ERR_ACCOUNT_EXISTS),
+            (
+                CFG.auth.super_admin_role.as_str(),
+                "Secret-123",
+                ERR_ACCOUNT_EXISTS,
+            ),
+            ("owner", "weak", ERR_PASSWORD_TOO_WEAK),
+        ];
+        for (account, password, expected) in cases {
+            let req = create_req("tenant-invalid", account, password);
+            assert_eq!(do_create(&state, &claims, req).await.unwrap_err(), expected);
+            assert!(find_domain(&state.conn, "tenant-invalid").await.is_err());
+        }
+
+        let req = create_req("tenant-valid", "owner", "Secret-123");
+        do_create(&state, &claims, req).await.unwrap();
+        assert!(auth::find_user(&state.conn, "tenant-valid", "owner")
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn failed_remove_rolls_back() {
+        let state = test_state().await;
+        let claims = test_super_admin(&state, "root").await;
+        let mut req = create_req("tenant-remove", "owner", "Secret-123");
+        req.admin = None;
+        do_create(&state, &claims, req).await.unwrap();
+
+        let conn = &state.conn;
+        conn.execute_unprepared("DROP TABLE sys_api_key")
+            .await
+            .unwrap();
+        assert!(do_remove(&state, &claims, "tenant-remove").await.is_err());
+
+        assert!(find_domain(conn, "tenant-remove").await.is_ok());
+        assert!(is_enabled("tenant-remove"));
+        let roles = sys_role::Entity::find()
+            .filter(sys_role::Column::Domain.eq("tenant-remove"))
+            .count(conn)
+            .await
+            .unwrap();
+        assert_eq!(roles, 1);
+        let policies = state
+            .enforcer
+            .read()
+            .await
+            .get_filtered_policy(1, vec!["tenant-remove".to_string()]);
+        assert!(!policies.is_empty());
+    }
+}
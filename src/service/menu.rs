@@ -0,0 +1,820 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::{
+    extract::{Path, State},
+    routing::{get, put},
+    Extension, Json, Router,
+};
+use casbin::{CoreApi, MgmtApi};
+use entity::{
+    sys_menu::{self, OperatorType},
+    sys_menu_operation,
+};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, DatabaseConnection, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, Set, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::context::{AppState, SharedEnforcer};
+use crate::error::{
+    Result, ERR_INVALID_PARAMS, ERR_MENU_HAS_CHILDREN, ERR_MENU_NOT_FOUND, OK_DELETE_MENU,
+};
+use crate::util::jwt::Claims;
+use crate::util::res::Res;
+use crate::util::tree::{self, Tree};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list).post(create))
+        .route("/routes", get(routes))
+        .route("/reorder", put(reorder))
+        .route("/:id", get(detail).put(update).delete(remove))
+        .route("/:id/move", put(move_to))
+        .route("/:id/state", put(change_state))
+        .route("/:id/operations", get(operations).put(set_operations))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateMenuReq {
+    pub path: String,
+    pub name: String,
+    pub title: String,
+    #[serde(default)]
+    pub icon: String,
+    // 0 表示顶级菜单
+    #[serde(default)]
+    pub parent_id: i32,
+    // 为空时排在同级菜单的最后
+    pub sort: Option<i32>,
+    #[serde(default = "default_state")]
+    pub state: bool,
+}
+
+// 只修改传入的字段，层级和顺序通过 move、reorder 修改
+#[derive(Deserialize, Debug, Default)]
+pub struct UpdateMenuReq {
+    pub path: Option<String>,
+    pub name: Option<String>,
+    pub title: Option<String>,
+    pub icon: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MoveMenuReq {
+    pub parent_id: i32,
+    pub sort: Option<i32>,
+}
+
+// ids 为同一父菜单下子菜单的新顺序
+#[derive(Deserialize, Debug)]
+pub struct ReorderMenuReq {
+    #[serde(default)]
+    pub parent_id: i32,
+    pub ids: Vec<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MenuStateReq {
+    pub state: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OperationReq {
+    pub operator: OperatorType,
+    pub code: String,
+    #[serde(default)]
+    pub title: String,
+}
+
+// 整体替换菜单上的操作，每种操作最多一个
+#[derive(Deserialize, Debug)]
+pub struct SetOperationsReq {
+    pub operations: Vec<OperationReq>,
+}
+
+// 角色在某个菜单上被授予的操作
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MenuGrant {
+    pub menu_id: i32,
+    pub operators: Vec<OperatorType>,
+}
+
+// 前端路由的结构，与常见的 vue-router / react-router 管理后台模板一致
+#[derive(Serialize, Debug)]
+pub struct RouteNode {
+    pub path: String,
+    pub name: String,
+    pub meta: RouteMeta,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<RouteNode>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RouteMeta {
+    pub title: String,
+    pub icon: String,
+    // 当前用户在该菜单上拥有的按钮权限标识
+    pub permissions: Vec<String>,
+}
+
+fn route_node(
+    tree: Tree<sys_menu::Model>,
+    permissions: &mut HashMap<i32, Vec<String>>,
+) -> RouteNode {
+    RouteNode {
+        meta: RouteMeta {
+            title: tree.node.title,
+            icon: tree.node.icon,
+            permissions: permissions.remove(&tree.node.id).unwrap_or_default(),
+        },
+        path: tree.node.path,
+        name: tree.node.name,
+        children: tree
+            .children
+            .into_iter()
+            .map(|child| route_node(child, permissions))
+            .collect(),
+    }
+}
+
+fn default_state() -> bool {
+    true
+}
+
+// 管理用的完整菜单树，包含禁用的菜单
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Res<Vec<Tree<sys_menu::Model>>> {
+    menu_tree(&state.conn, &claims.domain).await.into()
+}
+
+// 当前用户可以访问的前端路由
+pub async fn routes(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Res<Vec<RouteNode>> {
+    user_routes(&state.conn, &state.enforcer, &claims)
+        .await
+        .into()
+}
+
+pub async fn detail(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+) -> Res<sys_menu::Model> {
+    find_menu(&state.conn, &claims.domain, id).await.into()
+}
+
+pub async fn create(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<CreateMenuReq>,
+) -> Res<sys_menu::Model> {
+    do_create(&state.conn, &claims, req).await.into()
+}
+
+pub async fn update(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+    Json(req): Json<UpdateMenuReq>,
+) -> Res<sys_menu::Model> {
+    do_update(&state, &claims, id, req).await.into()
+}
+
+pub async fn remove(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+) -> Res<()> {
+    match do_remove(&state, &claims, id).await {
+        Ok(()) => Res::with_msg(&OK_DELETE_MENU),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+pub async fn move_to(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+    Json(req): Json<MoveMenuReq>,
+) -> Res<sys_menu::Model> {
+    do_move(&state.conn, &claims, id, req).await.into()
+}
+
+pub async fn reorder(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<ReorderMenuReq>,
+) -> Res<Vec<Tree<sys_menu::Model>>> {
+    do_reorder(&state.conn, &claims, req).await.into()
+}
+
+pub async fn change_state(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+    Json(req): Json<MenuStateReq>,
+) -> Res<sys_menu::Model> {
+    do_change_state(&state.conn, &claims, id, req.state)
+        .await
+        .into()
+}
+
+pub async fn operations(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+) -> Res<Vec<sys_menu_operation::Model>> {
+    let conn = &state.conn;
+    match find_menu(conn, &claims.domain, id).await {
+        Ok(menu) => menu_operations(conn, &[menu.id]).await.into(),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+pub async fn set_operations(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+    Json(req): Json<SetOperationsReq>,
+) -> Res<Vec<sys_menu_operation::Model>> {
+    do_set_operations(&state, &claims, id, req).await.into()
+}
+
+pub async fn menu_tree(
+    conn: &DatabaseConnection,
+    domain: &str,
+) -> Result<Vec<Tree<sys_menu::Model>>> {
+    Ok(tree::build(domain_menus(conn, domain).await?))
+}
+
+// 禁用的菜单连同其子菜单一起隐藏；目录本身没有权限时，只要有可见的子菜单也会保留
+pub async fn user_routes(
+    conn: &DatabaseConnection,
+    enforcer: &SharedEnforcer,
+    claims: &Claims,
+) -> Result<Vec<RouteNode>> {
+    let menus: Vec<sys_menu::Model> = domain_menus(conn, &claims.domain)
+        .await?
+        .into_iter()
+        .filter(|m| m.state)
+        .collect();
+    let ids: Vec<i32> = menus.iter().map(|m| m.id).collect();
+    let mut operations: HashMap<i32, Vec<sys_menu_operation::Model>> = HashMap::new();
+    for op in menu_operations(conn, &ids).await? {
+        operations.entry(op.menu_id).or_default().push(op);
+    }
+    let trees = tree::build(menus);
+
+    let mut enforcer = enforcer.write().await;
+    let mut enforce = |path: &str, method: &str| {
+        let rvals = vec![
+            claims.sub.clone(),
+            claims.domain.clone(),
+            path.to_string(),
+            method.to_string(),
+        ];
+        enforcer.enforce_mut(rvals).unwrap_or_else(|err| {
+            error!(
+                "failed to enforce menu {} for {}: {}",
+                path, claims.sub, err
+            );
+            false
+        })
+    };
+    // 有 GET 权限的菜单可见，同时收集其余操作对应的按钮权限
+    let mut permissions: HashMap<i32, Vec<String>> = HashMap::new();
+    let mut visible = |menu: &sys_menu::Model| {
+        if !enforce(&menu.path, OperatorType::GET.method()) {
+            return false;
+        }
+        let codes = operations
+            .remove(&menu.id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|op| enforce(&menu.path, op.operator.method()))
+            .map(|op| op.code)
+            .collect();
+        permissions.insert(menu.id, codes);
+        true
+    };
+    let trees = tree::prune(trees, &mut visible);
+    Ok(trees
+        .into_iter()
+        .map(|tree| route_node(tree, &mut permissions))
+        .collect())
+}
+
+pub async fn menu_operations(
+    conn: &DatabaseConnection,
+    menu_ids: &[i32],
+) -> Result<Vec<sys_menu_operation::Model>> {
+    let operations = sys_menu_operation::Entity::find()
+        .filter(sys_menu_operation::Column::MenuId.is_in(menu_ids.to_vec()))
+        .order_by_asc(sys_menu_operation::Column::Id)
+        .all(conn)
+        .await?;
+    Ok(operations)
+}
+
+// 角色在各菜单上的授权，以 casbin 中该角色的 p 策略为准
+pub async fn role_grants(
+    conn: &DatabaseConnection,
+    enforcer: &SharedEnforcer,
+    domain: &str,
+    role: &str,
+) -> Result<Vec<MenuGrant>> {
+    let managed = managed_policies(conn, domain).await?;
+    let policies = enforcer
+        .read()
+        .await
+        .get_filtered_policy(0, vec![role.to_string(), domain.to_string()]);
+
+    let mut grants: Vec<MenuGrant> = Vec::new();
+    for policy in policies {
+        let key = (policy[2].clone(), policy[3].clone());
+        for &(menu_id, operator) in managed.get(&key).into_iter().flatten() {
+            match grants.iter_mut().find(|g| g.menu_id == menu_id) {
+                Some(grant) => grant.operators.push(operator),
+                None => grants.push(MenuGrant {
+                    menu_id,
+                    operators: vec![operator],
+                }),
+            }
+        }
+    }
+    grants.sort_by_key(|g| g.menu_id);
+    Ok(grants)
+}
+
+// 用 grants 整体替换角色在菜单上的授权，只增删与菜单操作对应的 p 策略，其他策略保持不变
+pub async fn grant_role(
+    conn: &DatabaseConnection,
+    enforcer: &SharedEnforcer,
+    domain: &str,
+    role: &str,
+    grants: Vec<MenuGrant>,
+) -> Result<Vec<MenuGrant>> {
+    let menus: HashMap<i32, sys_menu::Model> = domain_menus(conn, domain)
+        .await?
+        .into_iter()
+        .map(|m| (m.id, m))
+        .collect();
+    let managed = managed_policies(conn, domain).await?;
+
+    let mut desired: HashSet<Vec<String>> = HashSet::new();
+    for grant in &grants {
+        let menu = menus.get(&grant.menu_id).ok_or(ERR_MENU_NOT_FOUND)?;
+        for operator in &grant.operators {
+            let key = (menu.path.clone(), operator.method().to_string());
+            if !managed
+                .get(&key)
+                .is_some_and(|ops| ops.contains(&(menu.id, *operator)))
+            {
+                return Err(ERR_INVALID_PARAMS);
+            }
+            desired.insert(vec![role.to_string(), domain.to_string(), key.0, key.1]);
+        }
+    }
+
+    {
+        let mut enforcer = enforcer.write().await;
+        let current: HashSet<Vec<String>> = enforcer
+            .get_filtered_policy(0, vec![role.to_string(), domain.to_string()])
+            .into_iter()
+            .filter(|p| managed.contains_key(&(p[2].clone(), p[3].clone())))
+            .collect();
+        let removed: Vec<Vec<String>> = current.difference(&desired).cloned().collect();
+        let added: Vec<Vec<String>> = desired.difference(&current).cloned().collect();
+        if !removed.is_empty() {
+            enforcer.remove_policies(removed).await?;
+        }
+        if !added.is_empty() {
+            enforcer.add_policies(added).await?;
+        }
+    }
+    role_grants(conn, enforcer, domain, role).await
+}
+
+// 域内所有菜单操作对应的 (path, method)，不同菜单可能共用同一个 path
+async fn managed_policies(
+    conn: &DatabaseConnection,
+    domain: &str,
+) -> Result<HashMap<(String, String), Vec<(i32, OperatorType)>>> {
+    let menus = domain_menus(conn, domain).await?;
+    let ids: Vec<i32> = menus.iter().map(|m| m.id).collect();
+    let paths: HashMap<i32, String> = menus.into_iter().map(|m| (m.id, m.path)).collect();
+
+    let mut managed: HashMap<(String, String), Vec<(i32, OperatorType)>> = HashMap::new();
+    for op in menu_operations(conn, &ids).await? {
+        if let Some(path) = paths.get(&op.menu_id) {
+            managed
+                .entry((path.clone(), op.operator.method().to_string()))
+                .or_default()
+                .push((op.menu_id, op.operator));
+        }
+    }
+    Ok(managed)
+}
+
+// 删除域内所有主体在 path 上指定方法的 p 策略，仍被其他菜单操作使用的保留
+async fn release_policies(
+    conn: &DatabaseConnection,
+    enforcer: &SharedEnforcer,
+    domain: &str,
+    path: &str,
+    methods: &[&str],
+) -> Result<()> {
+    let managed = managed_policies(conn, domain).await?;
+    let mut enforcer = enforcer.write().await;
+    let policies: Vec<Vec<String>> = methods
+        .iter()
+        .filter(|method| !managed.contains_key(&(path.to_string(), method.to_string())))
+        .flat_map(|method| {
+            enforcer.get_filtered_policy(
+                1,
+                vec![domain.to_string(), path.to_string(), method.to_string()],
+            )
+        })
+        .collect();
+    if !policies.is_empty() {
+        enforcer.remove_policies(policies).await?;
+    }
+    Ok(())
+}
+
+// 菜单 path 修改后把已授权的 p 策略复制到新 path，旧 path 不再被其他菜单操作使用时才删除
+async fn move_path_policies(
+    conn: &DatabaseConnection,
+    enforcer: &SharedEnforcer,
+    domain: &str,
+    old: &str,
+    new: &str,
+    methods: &[&str],
+) -> Result<()> {
+    let managed = managed_policies(conn, domain).await?;
+    let methods: HashSet<&str> = methods.iter().copied().collect();
+    let mut enforcer = enforcer.write().await;
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    for method in methods {
+        let policies = enforcer.get_filtered_policy(
+            1,
+            vec![domain.to_string(), old.to_string(), method.to_string()],
+        );
+        if !managed.contains_key(&(old.to_string(), method.to_string())) {
+            removed.extend(policies.iter().cloned());
+        }
+        added.extend(policies.into_iter().map(|mut p| {
+            p[2] = new.to_string();
+            p
+        }));
+    }
+    // 新 path 可能已被其他菜单使用，已存在的策略不再重复添加
+    let added: Vec<Vec<String>> = added
+        .into_iter()
+        .filter(|p| !enforcer.has_policy(p.clone()))
+        .collect();
+    if !removed.is_empty() {
+        enforcer.remove_policies(removed).await?;
+    }
+    if !added.is_empty() {
+        enforcer.add_policies(added).await?;
+    }
+    Ok(())
+}
+
+pub async fn find_menu(
+    conn: &DatabaseConnection,
+    domain: &str,
+    id: i32,
+) -> Result<sys_menu::Model> {
+    sys_menu::Entity::find_by_id(id)
+        .filter(sys_menu::Column::Domain.eq(domain))
+        .one(conn)
+        .await?
+        .ok_or(ERR_MENU_NOT_FOUND)
+}
+
+async fn domain_menus(conn: &DatabaseConnection, domain: &str) -> Result<Vec<sys_menu::Model>> {
+    let menus = sys_menu::Entity::find()
+        .filter(sys_menu::Column::Domain.eq(domain))
+        .order_by_asc(sys_menu::Column::Sort)
+        .order_by_asc(sys_menu::Column::Id)
+        .all(conn)
+        .await?;
+    Ok(menus)
+}
+
+fn not_blank(value: String) -> Result<String> {
+    let value = value.trim().to_string();
+    match value.is_empty() {
+        true => Err(ERR_INVALID_PARAMS),
+        false => Ok(value),
+    }
+}
+
+async fn do_create(
+    conn: &DatabaseConnection,
+    claims: &Claims,
+    req: CreateMenuReq,
+) -> Result<sys_menu::Model> {
+    tree::ensure_parent_valid::<sys_menu::Entity>(conn, &claims.domain, None, req.parent_id)
+        .await?;
+    let sort = match req.sort {
+        Some(sort) => sort,
+        None => tree::next_sort::<sys_menu::Entity>(conn, &claims.domain, req.parent_id).await?,
+    };
+
+    let menu = sys_menu::ActiveModel {
+        id: NotSet,
+        path: Set(not_blank(req.path)?),
+        name: Set(not_blank(req.name)?),
+        title: Set(not_blank(req.title)?),
+        domain: Set(claims.domain.clone()),
+        icon: Set(req.icon.trim().to_string()),
+        state: Set(req.state),
+        parent_id: Set(req.parent_id),
+        sort: Set(sort),
+    }
+    .insert(conn)
+    .await?;
+    info!(
+        "user {} created menu {} in domain {}",
+        claims.sub, menu.path, menu.domain
+    );
+    Ok(menu)
+}
+
+async fn do_update(
+    state: &AppState,
+    claims: &Claims,
+    id: i32,
+    req: UpdateMenuReq,
+) -> Result<sys_menu::Model> {
+    let conn = &state.conn;
+    let menu = find_menu(conn, &claims.domain, id).await?;
+    let old_path = menu.path.clone();
+    let mut model: sys_menu::ActiveModel = menu.into();
+    if let Some(path) = req.path {
+        model.path = Set(not_blank(path)?);
+    }
+    if let Some(name) = req.name {
+        model.name = Set(not_blank(name)?);
+    }
+    if let Some(title) = req.title {
+        model.title = Set(not_blank(title)?);
+    }
+    if let Some(icon) = req.icon {
+        model.icon = Set(icon.trim().to_string());
+    }
+    let menu = model.update(conn).await?;
+
+    if menu.path != old_path {
+        let operations = menu_operations(conn, &[menu.id]).await?;
+        let methods: Vec<&str> = operations.iter().map(|op| op.operator.method()).collect();
+        move_path_policies(
+            conn,
+            &state.enforcer,
+            &claims.domain,
+            &old_path,
+            &menu.path,
+            &methods,
+        )
+        .await?;
+    }
+    Ok(menu)
+}
+
+// 只允许删除叶子菜单，避免误删整棵子树；菜单上的操作和对应的授权一并删除
+async fn do_remove(state: &AppState, claims: &Claims, id: i32) -> Result<()> {
+    let conn = &state.conn;
+    let menu = find_menu(conn, &claims.domain, id).await?;
+    let children = sys_menu::Entity::find()
+        .filter(sys_menu::Column::Domain.eq(&claims.domain))
+        .filter(sys_menu::Column::ParentId.eq(menu.id))
+        .count(conn)
+        .await?;
+    if children > 0 {
+        return Err(ERR_MENU_HAS_CHILDREN);
+    }
+    let operations = menu_operations(conn, &[menu.id]).await?;
+    let methods: Vec<&str> = operations.iter().map(|op| op.operator.method()).collect();
+    let txn = conn.begin().await?;
+    sys_menu_operation::Entity::delete_many()
+        .filter(sys_menu_operation::Column::MenuId.eq(menu.id))
+        .exec(&txn)
+        .await?;
+    sys_menu::Entity::delete_by_id(menu.id).exec(&txn).await?;
+    txn.commit().await?;
+    release_policies(conn, &state.enforcer, &claims.domain, &menu.path, &methods).await?;
+    info!(
+        "user {} deleted menu {} in domain {}",
+        claims.sub, menu.path, menu.domain
+    );
+    Ok(())
+}
+
+// 禁用后该菜单及其子菜单都不会出现在前端路由中
+async fn do_change_state(
+    conn: &DatabaseConnection,
+    claims: &Claims,
+    id: i32,
+    state: bool,
+) -> Result<sys_menu::Model> {
+    let mut model: sys_menu::ActiveModel = find_menu(conn, &claims.domain, id).await?.into();
+    model.state = Set(state);
+    let menu = model.update(conn).await?;
+    info!(
+        "user {} set menu {} state to {}",
+        claims.sub, menu.id, menu.state
+    );
+    Ok(menu)
+}
+
+// 被移除的操作在域内的授权一并删除
+async fn do_set_operations(
+    state: &AppState,
+    claims: &Claims,
+    id: i32,
+    req: SetOperationsReq,
+) -> Result<Vec<sys_menu_operation::Model>> {
+    let conn = &state.conn;
+    let menu = find_menu(conn, &claims.domain, id).await?;
+    let mut operators = HashSet::new();
+    let mut models = Vec::with_capacity(req.operations.len());
+    for op in req.operations {
+        if !operators.insert(op.operator) {
+            return Err(ERR_INVALID_PARAMS);
+        }
+        models.push(sys_menu_operation::ActiveModel {
+            id: NotSet,
+            menu_id: Set(menu.id),
+            operator: Set(op.operator),
+            code: Set(not_blank(op.code)?),
+            title: Set(op.title.trim().to_string()),
+        });
+    }
+
+    let removed: Vec<&str> = menu_operations(conn, &[menu.id])
+        .await?
+        .iter()
+        .filter(|op| !operators.contains(&op.operator))
+        .map(|op| op.operator.method())
+        .collect();
+
+    let txn = conn.begin().await?;
+    sys_menu_operation::Entity::delete_many()
+        .filter(sys_menu_operation::Column::MenuId.eq(menu.id))
+        .exec(&txn)
+        .await?;
+    if !models.is_empty() {
+        sys_menu_operation::Entity::insert_many(models)
+            .exec_without_returning(&txn)
+            .await?;
+    }
+    txn.commit().await?;
+
+    release_policies(conn, &state.enforcer, &claims.domain, &menu.path, &removed).await?;
+    info!(
+        "user {} set operations {:?} on menu {} in domain {}",
+        claims.sub, operators, menu.path, menu.domain
+    );
+    menu_operations(conn, &[menu.id]).await
+}
+
+async fn do_move(
+    conn: &DatabaseConnection,
+    claims: &Claims,
+    id: i32,
+    req: MoveMenuReq,
+) -> Result<sys_menu::Model> {
+    let menu = find_menu(conn, &claims.domain, id).await?;
+    tree::ensure_parent_valid::<sys_menu::Entity>(conn, &claims.domain, Some(id), req.parent_id)
+        .await?;
+    let sort = match req.sort {
+        Some(sort) => sort,
+        None if menu.parent_id == req.parent_id => menu.sort,
+        None => tree::next_sort::<sys_menu::Entity>(conn, &claims.domain, req.parent_id).await?,
+    };
+
+    let mut model: sys_menu::ActiveModel = menu.into();
+    model.parent_id = Set(req.parent_id);
+    model.sort = Set(sort);
+    let menu = model.update(conn).await?;
+    info!(
+        "user {} moved menu {} under {} at {}",
+        claims.sub, menu.id, menu.parent_id, menu.sort
+    );
+    Ok(menu)
+}
+
+// ids 必须恰好是该父菜单下的全部子菜单，按顺序把 sort 设为下标
+async fn do_reorder(
+    conn: &DatabaseConnection,
+    claims: &Claims,
+    req: ReorderMenuReq,
+) -> Result<Vec<Tree<sys_menu::Model>>> {
+    let siblings = sys_menu::Entity::find()
+        .filter(sys_menu::Column::Domain.eq(&claims.domain))
+        .filter(sys_menu::Column::ParentId.eq(req.parent_id))
+        .all(conn)
+        .await?;
+    let mut expected: Vec<i32> = siblings.into_iter().map(|m| m.id).collect();
+    let mut given = req.ids.clone();
+    expected.sort_unstable();
+    given.sort_unstable();
+    if expected != given {
+        return Err(ERR_INVALID_PARAMS);
+    }
+
+    let txn = conn.begin().await?;
+    for (sort, id) in req.ids.into_iter().enumerate() {
+        sys_menu::ActiveModel {
+            id: Set(id),
+            sort: Set(sort as i32),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await?;
+    }
+    txn.commit().await?;
+    menu_tree(conn, &claims.domain).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{test_state, test_super_admin};
+
+    async fn create_menu(state: &AppState, claims: &Claims, name: &str, path: &str) -> i32 {
+        let req = CreateMenuReq {
+            path: path.to_string(),
+            name: name.to_string(),
+            title: name.to_string(),
+            icon: String::new(),
+            parent_id: 0,
+            sort: None,
+            state: true,
+        };
+        let menu = do_create(&state.conn, claims, req).await.unwrap();
+        let req = SetOperationsReq {
+            operations: vec![OperationReq {
+                operator: OperatorType::GET,
+                code: format!("{}:view", name),
+                title: String::new(),
+            }],
+        };
+        do_set_operations(state, claims, menu.id, req)
+            .await
+            .unwrap();
+        menu.id
+    }
+
+    async fn has_policy(state: &AppState, domain: &str, path: &str) -> bool {
+        let policy = vec!["auditor", domain, path, "GET"];
+        state
+            .enforcer
+            .read()
+            .await
+            .has_policy(policy.into_iter().map(String::from).collect())
+    }
+
+    #[tokio::test]
+    async fn path_change_keeps_policies_of_shared_paths() {
+        let state = test_state().await;
+        let claims = test_super_admin(&state, "root").await;
+        let domain = claims.domain.clone();
+        let logs = create_menu(&state, &claims, "logs", "/api/logs").await;
+        let audit = create_menu(&state, &claims, "audit", "/api/logs").await;
+        let grants = vec![MenuGrant {
+            menu_id: logs,
+            operators: vec![OperatorType::GET],
+        }];
+        grant_role(&state.conn, &state.enforcer, &domain, "auditor", grants)
+            .await
+            .unwrap();
+
+        // 旧 path 仍被 audit 菜单使用，只复制策略
+        let req = UpdateMenuReq {
+            path: Some("/api/logs/v2".to_string()),
+            ..Default::default()
+        };
+        do_update(&state, &claims, logs, req).await.unwrap();
+        assert!(has_policy(&state, &domain, "/api/logs").await);
+        assert!(has_policy(&state, &domain, "/api/logs/v2").await);
+
+        // 新 path 上已有的策略不重复添加，旧 path 不再使用后策略被转移
+        let req = UpdateMenuReq {
+            path: Some("/api/logs/v2".to_string()),
+            ..Default::default()
+        };
+        do_update(&state, &claims, audit, req).await.unwrap();
+        assert!(!has_policy(&state, &domain, "/api/logs").await);
+        assert!(has_policy(&state, &domain, "/api/logs/v2").await);
+    }
+}
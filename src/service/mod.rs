@@ -1 +1,32 @@
-pub mod auth;
\ No newline at end of file
+use axum::Router;
+
+use crate::context::AppState;
+
+pub mod api_key;
+pub mod auth;
+pub mod client_cert;
+pub mod data_scope;
+pub mod dept;
+pub mod domain;
+pub mod menu;
+pub mod policy;
+pub mod role;
+pub mod session;
+pub mod system;
+pub mod token;
+pub mod totp;
+pub mod user;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .nest("/api-keys", api_key::router())
+        .nest("/auth", auth::router())
+        .nest("/depts", dept::router())
+        .nest("/domains", domain::router())
+        .nest("/menus", menu::router())
+        .nest("/policies", policy::router())
+        .nest("/roles", role::router())
+        .nest("/sessions", session::router())
+        .nest("/system", system::router())
+        .nest("/users", user::router())
+}
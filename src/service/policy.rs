@@ -0,0 +1,877 @@
+use std::collections::{BTreeSet, HashSet};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use casbin::{function_map::key_match2, CachedApi, CachedEnforcer, CoreApi, MgmtApi, RbacApi};
+use entity::sys_role;
+use sea_orm::EntityTrait;
+use serde::{Deserialize, Serialize};
+
+use crate::context::{AppState, SharedEnforcer};
+use crate::error::{
+    Result, ERR_INTERNAL, ERR_INVALID_PARAMS, ERR_POLICY_FILE_INVALID, ERR_POLICY_MANAGED,
+};
+use crate::service::{domain, role};
+use crate::util::jwt::Claims;
+use crate::util::res::{PageData, PageParams, Res};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list).post(add).delete(remove))
+        .route(
+            "/groupings",
+            get(list_groupings)
+                .post(add_groupings)
+                .delete(remove_groupings),
+        )
+        .route("/subjects/:sub/roles", get(subject_roles))
+        .route("/subjects/:sub/permissions", get(subject_permissions))
+        .route("/explain", post(explain))
+        .route("/export", get(export))
+        .route("/import", post(import))
+}
+
+// 当前域中的一条 p 规则，域由请求所在的域决定
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Policy {
+    pub sub: String,
+    pub obj: String,
+    pub act: String,
+}
+
+// 当前域中的一条 g 规则：sub 继承 role 的权限
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grouping {
+    pub sub: String,
+    pub role: String,
+}
+
+// sub 和 act 精确匹配，obj 按子串搜索
+#[derive(Deserialize, Debug, Default)]
+pub struct PolicyFilter {
+    pub sub: Option<String>,
+    pub obj: Option<String>,
+    pub act: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct GroupingFilter {
+    pub sub: Option<String>,
+    pub role: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PoliciesReq {
+    pub policies: Vec<Policy>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GroupingsReq {
+    pub groupings: Vec<Grouping>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SubjectRoles {
+    pub roles: Vec<String>,
+    // 包括通过角色继承得到的角色
+    pub implicit_roles: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SubjectPermissions {
+    pub permissions: Vec<Policy>,
+    // 包括所有角色（含继承的角色）的权限
+    pub implicit_permissions: Vec<Policy>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Explanation {
+    pub allowed: bool,
+    // 请求主体在当前域中的所有角色
+    pub roles: Vec<String>,
+    // 允许这个请求的规则，为空表示没有规则匹配，请求被拒绝
+    pub matched: Vec<Policy>,
+}
+
+// 导入导出使用 casbin 的 CSV 格式（每行一条 "p, sub, dom, obj, act" 或 "g, sub, role, dom"）或 JSON
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+// all 为 true 时针对所有域的规则，只有超级管理员可以使用，否则只针对当前域
+#[derive(Deserialize, Debug, Default)]
+pub struct ExportParams {
+    #[serde(default)]
+    pub format: PolicyFormat,
+    #[serde(default)]
+    pub all: bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ImportParams {
+    #[serde(default)]
+    pub format: PolicyFormat,
+    #[serde(default)]
+    pub all: bool,
+    // 只返回差异，不修改规则
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+// 完整的一条 casbin 规则，包括域
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PolicyRule {
+    pub ptype: String,
+    pub rule: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PolicyDiff {
+    pub added: Vec<PolicyRule>,
+    pub removed: Vec<PolicyRule>,
+    pub applied: bool,
+}
+
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(page): Query<PageParams>,
+    Query(filter): Query<PolicyFilter>,
+) -> Res<PageData<Policy>> {
+    let policies = domain_policies(&state.enforcer, &claims.domain).await;
+    let policies = policies.into_iter().filter(|p| {
+        filter.sub.as_ref().is_none_or(|sub| &p.sub == sub)
+            && filter
+                .obj
+                .as_ref()
+                .is_none_or(|obj| p.obj.contains(obj.as_str()))
+            && filter
+                .act
+                .as_ref()
+                .is_none_or(|act| p.act.eq_ignore_ascii_case(act))
+    });
+    Res::with_data(paginate(policies.collect(), &page))
+}
+
+pub async fn add(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<PoliciesReq>,
+) -> Res<usize> {
+    let result = add_policies(&state.enforcer, &claims.domain, req.policies).await;
+    if let Ok(count) = &result {
+        info!(
+            "user {} added {} policies in domain {}",
+            claims.sub, count, claims.domain
+        );
+    }
+    result.into()
+}
+
+pub async fn remove(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<PoliciesReq>,
+) -> Res<usize> {
+    let result = remove_policies(&state.enforcer, &claims.domain, req.policies).await;
+    if let Ok(count) = &result {
+        info!(
+            "user {} removed {} policies in domain {}",
+            claims.sub, count, claims.domain
+        );
+    }
+    result.into()
+}
+
+pub async fn list_groupings(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(page): Query<PageParams>,
+    Query(filter): Query<GroupingFilter>,
+) -> Res<PageData<Grouping>> {
+    let groupings = domain_groupings(&state.enforcer, &claims.domain).await;
+    let groupings = groupings.into_iter().filter(|g| {
+        filter.sub.as_ref().is_none_or(|sub| &g.sub == sub)
+            && filter.role.as_ref().is_none_or(|role| &g.role == role)
+    });
+    Res::with_data(paginate(groupings.collect(), &page))
+}
+
+pub async fn add_groupings(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<GroupingsReq>,
+) -> Res<usize> {
+    do_add_groupings(&state, &claims, req.groupings)
+        .await
+        .into()
+}
+
+pub async fn remove_groupings(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<GroupingsReq>,
+) -> Res<usize> {
+    do_remove_groupings(&state, &claims, req.groupings)
+        .await
+        .into()
+}
+
+pub async fn subject_roles(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(sub): Path<String>,
+) -> Res<SubjectRoles> {
+    let domain = Some(claims.domain.as_str());
+    let mut enforcer = state.enforcer.write().await;
+    let mut roles = enforcer.get_roles_for_user(&sub, domain);
+    let mut implicit_roles = enforcer.get_implicit_roles_for_user(&sub, domain);
+    roles.sort();
+    implicit_roles.sort();
+    Res::with_data(SubjectRoles {
+        roles,
+        implicit_roles,
+    })
+}
+
+pub async fn subject_permissions(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(sub): Path<String>,
+) -> Res<SubjectPermissions> {
+    let domain = Some(claims.domain.as_str());
+    let mut enforcer = state.enforcer.write().await;
+    let permissions = enforcer.get_permissions_for_user(&sub, domain);
+    let implicit_permissions = enforcer.get_implicit_permissions_for_user(&sub, domain);
+    Res::with_data(SubjectPermissions {
+        permissions: to_policies(permissions),
+        implicit_permissions: to_policies(implicit_permissions),
+    })
+}
+
+// 按 casbin 模型的匹配规则找出允许 (sub, 当前域, obj, act) 的规则
+pub async fn explain(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<Policy>,
+) -> Res<Explanation> {
+    do_explain(&state.enforcer, &claims.domain, req)
+        .await
+        .into()
+}
+
+pub async fn export(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(params): Query<ExportParams>,
+) -> Response {
+    match do_export(&state, &claims, &params).await {
+        Ok(response) => response,
+        Err(err) => Res::<()>::with_err(&err).into_response(),
+    }
+}
+
+// 导入的规则整体替换范围内现有的规则
+pub async fn import(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(params): Query<ImportParams>,
+    body: String,
+) -> Res<PolicyDiff> {
+    do_import(&state, &claims, &params, &body).await.into()
+}
+
+pub async fn domain_policies(enforcer: &SharedEnforcer, domain: &str) -> Vec<Policy> {
+    let policies = enforcer
+        .read()
+        .await
+        .get_filtered_policy(1, vec![domain.to_string()]);
+    to_policies(policies)
+}
+
+pub async fn domain_groupings(enforcer: &SharedEnforcer, domain: &str) -> Vec<Grouping> {
+    let rules = enforcer
+        .read()
+        .await
+        .get_filtered_grouping_policy(2, vec![domain.to_string()]);
+    let mut groupings: Vec<Grouping> = rules
+        .into_iter()
+        .filter(|g| g.len() >= 2)
+        .map(|g| Grouping {
+            sub: g[0].clone(),
+            role: g[1].clone(),
+        })
+        .collect();
+    groupings.sort_by(|a, b| (&a.sub, &a.role).cmp(&(&b.sub, &b.role)));
+    groupings
+}
+
+// 已存在的规则会被跳过，返回实际新增的数量
+pub async fn add_policies(
+    enforcer: &SharedEnforcer,
+    domain: &str,
+    policies: Vec<Policy>,
+) -> Result<usize> {
+    let rules = policy_rules(domain, policies)?;
+    let mut enforcer = enforcer.write().await;
+    let rules: Vec<Vec<String>> = rules
+        .into_iter()
+        .filter(|rule| !enforcer.has_policy(rule.clone()))
+        .collect();
+    if !rules.is_empty() {
+        enforcer.add_policies(rules.clone()).await?;
+    }
+    Ok(rules.len())
+}
+
+// 不存在的规则会被跳过，返回实际删除的数量
+pub async fn remove_policies(
+    enforcer: &SharedEnforcer,
+    domain: &str,
+    policies: Vec<Policy>,
+) -> Result<usize> {
+    let rules = policy_rules(domain, policies)?;
+    let mut enforcer = enforcer.write().await;
+    let rules: Vec<Vec<String>> = rules
+        .into_iter()
+        .filter(|rule| enforcer.has_policy(rule.clone()))
+        .collect();
+    if !rules.is_empty() {
+        enforcer.remove_policies(rules.clone()).await?;
+    }
+    Ok(rules.len())
+}
+
+fn to_policies(rules: Vec<Vec<String>>) -> Vec<Policy> {
+    let mut policies: Vec<Policy> = rules
+        .into_iter()
+        .filter(|p| p.len() >= 4)
+        .map(|p| Policy {
+            sub: p[0].clone(),
+            obj: p[2].clone(),
+            act: p[3].clone(),
+        })
+        .collect();
+    policies.sort_by(|a, b| (&a.sub, &a.obj, &a.act).cmp(&(&b.sub, &b.obj, &b.act)));
+    policies.dedup();
+    policies
+}
+
+fn paginate<T>(items: Vec<T>, page: &PageParams) -> PageData<T> {
+    let total = items.len() as u64;
+    let (page_num, page_size) = (page.page_num(), page.page_size());
+    let list = items
+        .into_iter()
+        .skip(((page_num - 1) * page_size) as usize)
+        .take(page_size as usize)
+        .collect();
+    PageData::new(list, total, total.div_ceil(page_size), page_num)
+}
+
+// 校验并转换为 casbin 规则，请求方法统一为大写
+fn policy_rules(domain: &str, policies: Vec<Policy>) -> Result<Vec<Vec<String>>> {
+    let mut rules = Vec::new();
+    let mut seen = HashSet::new();
+    for policy in policies {
+        let (sub, obj, act) = (policy.sub.trim(), policy.obj.trim(), policy.act.trim());
+        if sub.is_empty() || !obj.starts_with('/') || act.is_empty() {
+            return Err(ERR_INVALID_PARAMS);
+        }
+        if !act.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(ERR_INVALID_PARAMS);
+        }
+        let rule = vec![
+            sub.to_string(),
+            domain.to_string(),
+            obj.to_string(),
+            act.to_ascii_uppercase(),
+        ];
+        if seen.insert(rule.clone()) {
+            rules.push(rule);
+        }
+    }
+    Ok(rules)
+}
+
+// 角色管理维护的角色的成员和继承关系会在同步时被覆盖，不允许在这里修改
+async fn grouping_rules(
+    state: &AppState,
+    domain: &str,
+    groupings: Vec<Grouping>,
+) -> Result<Vec<Vec<String>>> {
+    let managed: HashSet<String> = role::domain_roles(&state.conn, domain)
+        .await?
+        .into_iter()
+        .map(|r| r.name)
+        .collect();
+    let mut rules = Vec::new();
+    let mut seen = HashSet::new();
+    for grouping in groupings {
+        let (sub, role) = (grouping.sub.trim(), grouping.role.trim());
+        if sub.is_empty() || role.is_empty() || sub == role {
+            return Err(ERR_INVALID_PARAMS);
+        }
+        if managed.contains(role) {
+            return Err(ERR_POLICY_MANAGED);
+        }
+        let rule = vec![sub.to_string(), role.to_string(), domain.to_string()];
+        if seen.insert(rule.clone()) {
+            rules.push(rule);
+        }
+    }
+    Ok(rules)
+}
+
+async fn do_add_groupings(
+    state: &AppState,
+    claims: &Claims,
+    groupings: Vec<Grouping>,
+) -> Result<usize> {
+    let rules = grouping_rules(state, &claims.domain, groupings).await?;
+    let mut enforcer = state.enforcer.write().await;
+    let rules: Vec<Vec<String>> = rules
+        .into_iter()
+        .filter(|rule| !enforcer.has_grouping_policy(rule.clone()))
+        .collect();
+    if !rules.is_empty() {
+        enforcer.add_grouping_policies(rules.clone()).await?;
+    }
+    info!(
+        "user {} added {} grouping rules in domain {}",
+        claims.sub,
+        rules.len(),
+        claims.domain
+    );
+    Ok(rules.len())
+}
+
+async fn do_remove_groupings(
+    state: &AppState,
+    claims: &Claims,
+    groupings: Vec<Grouping>,
+) -> Result<usize> {
+    let rules = grouping_rules(state, &claims.domain, groupings).await?;
+    let mut enforcer = state.enforcer.write().await;
+    let rules: Vec<Vec<String>> = rules
+        .into_iter()
+        .filter(|rule| enforcer.has_grouping_policy(rule.clone()))
+        .collect();
+    if !rules.is_empty() {
+        enforcer.remove_grouping_policies(rules.clone()).await?;
+    }
+    info!(
+        "user {} removed {} grouping rules in domain {}",
+        claims.sub,
+        rules.len(),
+        claims.domain
+    );
+    Ok(rules.len())
+}
+
+async fn do_explain(enforcer: &SharedEnforcer, domain: &str, req: Policy) -> Result<Explanation> {
+    let (sub, obj, act) = (
+        req.sub.trim(),
+        req.obj.trim(),
+        req.act.trim().to_ascii_uppercase(),
+    );
+    if sub.is_empty() || obj.is_empty() || act.is_empty() {
+        return Err(ERR_INVALID_PARAMS);
+    }
+    let mut enforcer = enforcer.write().await;
+    let allowed = enforcer.enforce_mut(vec![
+        sub.to_string(),
+        domain.to_string(),
+        obj.to_string(),
+        act.clone(),
+    ])?;
+    let mut roles = enforcer.get_implicit_roles_for_user(sub, Some(domain));
+    roles.sort();
+    let matched = enforcer
+        .get_implicit_permissions_for_user(sub, Some(domain))
+        .into_iter()
+        .filter(|p| p.len() >= 4 && key_match2(obj, &p[2]) && p[3] == act)
+        .collect();
+    Ok(Explanation {
+        allowed,
+        roles,
+        matched: to_policies(matched),
+    })
+}
+
+impl PolicyRule {
+    fn domain(&self) -> Option<&str> {
+        let index = match self.ptype.as_str() {
+            "p" => 1,
+            "g" => 2,
+            _ => return None,
+        };
+        self.rule.get(index).map(String::as_str)
+    }
+
+    fn in_scope(&self, domain: Option<&str>) -> bool {
+        domain.is_none_or(|d| self.domain() == Some(d))
+    }
+}
+
+// 返回导入导出针对的域，None 表示所有域
+async fn transfer_scope(
+    enforcer: &SharedEnforcer,
+    claims: &Claims,
+    all: bool,
+) -> Result<Option<String>> {
+    if !all {
+        return Ok(Some(claims.domain.clone()));
+    }
+    domain::ensure_super_admin(enforcer, claims).await?;
+    Ok(None)
+}
+
+fn scoped_rules(enforcer: &CachedEnforcer, domain: Option<&str>) -> BTreeSet<PolicyRule> {
+    let policies = enforcer.get_policy().into_iter().map(|rule| PolicyRule {
+        ptype: "p".to_string(),
+        rule,
+    });
+    let groupings = enforcer
+        .get_grouping_policy()
+        .into_iter()
+        .map(|rule| PolicyRule {
+            ptype: "g".to_string(),
+            rule,
+        });
+    policies
+        .chain(groupings)
+        .filter(|r| r.in_scope(domain))
+        .collect()
+}
+
+fn to_csv(rules: &BTreeSet<PolicyRule>) -> String {
+    rules
+        .iter()
+        .map(|r| format!("{}, {}\n", r.ptype, r.rule.join(", ")))
+        .collect()
+}
+
+// 空行和 # 开头的注释行会被忽略
+fn parse_csv(body: &str) -> Vec<PolicyRule> {
+    body.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let mut values = line.split(',').map(|v| v.trim().to_string());
+            PolicyRule {
+                ptype: values.next().unwrap_or_default(),
+                rule: values.collect(),
+            }
+        })
+        .collect()
+}
+
+// 只接受模型中定义的 p 和 g 规则，并且必须属于导入的范围
+fn check_rules(rules: Vec<PolicyRule>, domain: Option<&str>) -> Result<BTreeSet<PolicyRule>> {
+    let mut checked = BTreeSet::new();
+    for (i, mut rule) in rules.into_iter().enumerate() {
+        rule.ptype = rule.ptype.trim().to_string();
+        rule.rule = rule.rule.iter().map(|v| v.trim().to_string()).collect();
+        let arity = match rule.ptype.as_str() {
+            "p" => 4,
+            "g" => 3,
+            _ => 0,
+        };
+        if arity == 0
+            || rule.rule.len() != arity
+            || rule.rule.iter().any(String::is_empty)
+            || !rule.in_scope(domain)
+        {
+            debug!("invalid policy rule #{}: {:?}", i + 1, rule);
+            return Err(ERR_POLICY_FILE_INVALID);
+        }
+        checked.insert(rule);
+    }
+    Ok(checked)
+}
+
+async fn do_export(state: &AppState, claims: &Claims, params: &ExportParams) -> Result<Response> {
+    let scope = transfer_scope(&state.enforcer, claims, params.all).await?;
+    let rules = scoped_rules(&*state.enforcer.read().await, scope.as_deref());
+    let (content_type, filename, body) = match params.format {
+        PolicyFormat::Csv => ("text/csv; charset=utf-8", "policy.csv", to_csv(&rules)),
+        PolicyFormat::Json => (
+            "application/json",
+            "policy.json",
+            serde_json::to_string_pretty(&rules).map_err(|_| ERR_INTERNAL)?,
+        ),
+    };
+    let headers = [
+        (CONTENT_TYPE, content_type.to_string()),
+        (
+            CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", filename),
+        ),
+    ];
+    Ok((headers, body).into_response())
+}
+
+async fn do_import(
+    state: &AppState,
+    claims: &Claims,
+    params: &ImportParams,
+    body: &str,
+) -> Result<PolicyDiff> {
+    let scope = transfer_scope(&state.enforcer, claims, params.all).await?;
+    let rules = match params.format {
+        PolicyFormat::Csv => parse_csv(body),
+        PolicyFormat::Json => serde_json::from_str(body).map_err(|_| ERR_POLICY_FILE_INVALID)?,
+    };
+    let imported = check_rules(rules, scope.as_deref())?;
+    let managed = managed_roles(state, scope.as_deref()).await?;
+
+    let mut enforcer = state.enforcer.write().await;
+    let current = scoped_rules(&enforcer, scope.as_deref());
+    let added: Vec<PolicyRule> = imported.difference(&current).cloned().collect();
+    let removed: Vec<PolicyRule> = current.difference(&imported).cloned().collect();
+    // 与 grouping_rules 一致，角色管理维护的 g 规则不允许通过导入修改
+    let touches_managed = added
+        .iter()
+        .chain(&removed)
+        .any(|r| r.ptype == "g" && managed.contains(&(r.rule[2].clone(), r.rule[1].clone())));
+    if touches_managed {
+        return Err(ERR_POLICY_MANAGED);
+    }
+    let changed = !added.is_empty() || !removed.is_empty();
+    let applied = changed && !params.dry_run;
+    if applied {
+        replace_rules(&mut enforcer, scope.as_deref(), imported).await?;
+        info!(
+            "user {} imported policies into {}: {} added, {} removed",
+            claims.sub,
+            scope.as_deref().unwrap_or("all domains"),
+            added.len(),
+            removed.len()
+        );
+    }
+    Ok(PolicyDiff {
+        added,
+        removed,
+        applied,
+    })
+}
+
+// 范围内由角色管理维护的角色，元素为 (域, 角色名)
+async fn managed_roles(
+    state: &AppState,
+    domain: Option<&str>,
+) -> Result<HashSet<(String, String)>> {
+    let roles = match domain {
+        Some(domain) => role::domain_roles(&state.conn, domain).await?,
+        None => sys_role::Entity::find().all(&state.conn).await?,
+    };
+    Ok(roles.into_iter().map(|r| (r.domain, r.name)).collect())
+}
+
+// 在内存中组装新的规则集，由适配器在一个事务中整体保存，再从数据库重新加载
+async fn replace_rules(
+    enforcer: &mut CachedEnforcer,
+    domain: Option<&str>,
+    rules: BTreeSet<PolicyRule>,
+) -> Result<()> {
+    let kept: Vec<PolicyRule> = scoped_rules(enforcer, None)
+        .into_iter()
+        .filter(|r| !r.in_scope(domain))
+        .collect();
+    let model = enforcer.get_mut_model();
+    model.clear_policy();
+    for r in kept.into_iter().chain(rules) {
+        model.add_policy(&r.ptype, &r.ptype, r.rule);
+    }
+
+    let saved = enforcer.save_policy().await;
+    // 保存失败时事务已回滚，重新加载后内存中的规则也恢复原状
+    let loaded = enforcer.load_policy().await;
+    enforcer.get_mut_cache().clear();
+    saved?;
+    loaded?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CFG;
+    use crate::context::{test_state, test_super_admin};
+    use sea_orm::{ActiveModelTrait, ActiveValue::NotSet, Set};
+
+    fn rule(ptype: &str, values: &[&str]) -> PolicyRule {
+        PolicyRule {
+            ptype: ptype.to_string(),
+            rule: values.iter().map(|v| v.to_string()).collect(),
+        }
+    }
+
+    fn params(dry_run: bool) -> ImportParams {
+        ImportParams {
+            format: PolicyFormat::Csv,
+            all: false,
+            dry_run,
+        }
+    }
+
+    #[test]
+    fn parse_csv_rules() {
+        let body = "# exported\n\np, alice, d1, /api/users, GET\n  g , bob,admin , d1  \n";
+        let rules = parse_csv(body);
+        assert_eq!(
+            rules,
+            vec![
+                rule("p", &["alice", "d1", "/api/users", "GET"]),
+                rule("g", &["bob", "admin", "d1"]),
+            ]
+        );
+        let checked = check_rules(rules.clone(), Some("d1")).unwrap();
+        assert_eq!(checked.len(), 2);
+        assert_eq!(
+            to_csv(&checked),
+            "g, bob, admin, d1\np, alice, d1, /api/users, GET\n"
+        );
+        assert_eq!(
+            parse_csv(&to_csv(&checked)),
+            checked.into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn parse_json_rules() {
+        let body = r#"[{"ptype": "p", "rule": [" alice", "d1", "/api/users", "GET"]},
+            {"ptype": "g", "rule": ["bob", "admin", "d1"]},
+            {"ptype": "g", "rule": ["bob", "admin", "d1"]}]"#;
+        let rules: Vec<PolicyRule> = serde_json::from_str(body).unwrap();
+        let checked = check_rules(rules, None).unwrap();
+        let expected: BTreeSet<PolicyRule> = [
+            rule("p", &["alice", "d1", "/api/users", "GET"]),
+            rule("g", &["bob", "admin", "d1"]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(checked, expected);
+        let json = serde_json::to_string(&checked).unwrap();
+        assert_eq!(
+            serde_json::from_str::<BTreeSet<PolicyRule>>(&json).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        let invalid = [
+            rule("p", &["alice", "d1", "/api/users"]),
+            rule("g", &["bob", "admin", "d1", "extra"]),
+            rule("p", &["alice", "d1", "", "GET"]),
+            rule("g2", &["bob", "admin", "d1"]),
+            // 不属于导入的域
+            rule("p", &["alice", "d2", "/api/users", "GET"]),
+        ];
+        for r in invalid {
+            let err = check_rules(vec![r], Some("d1")).unwrap_err();
+            assert_eq!(err, ERR_POLICY_FILE_INVALID);
+        }
+    }
+
+    #[tokio::test]
+    async fn import_diff_and_dry_run() {
+        let state = test_state().await;
+        let claims = test_super_admin(&state, "root").await;
+        let domain = claims.domain.as_str();
+        let current = scoped_rules(&*state.enforcer.read().await, Some(domain));
+        let extra = rule("p", &["auditor", domain, "/api/logs", "GET"]);
+        let mut imported = current.clone();
+        imported.insert(extra.clone());
+        let body = to_csv(&imported);
+
+        let diff = do_import(&state, &claims, &params(true), &body)
+            .await
+            .unwrap();
+        assert_eq!(diff.added, vec![extra.clone()]);
+        assert!(diff.removed.is_empty());
+        assert!(!diff.applied);
+        assert_eq!(
+            scoped_rules(&*state.enforcer.read().await, Some(domain)),
+            current
+        );
+
+        let diff = do_import(&state, &claims, &params(false), &body)
+            .await
+            .unwrap();
+        assert!(diff.applied);
+        assert_eq!(
+            scoped_rules(&*state.enforcer.read().await, Some(domain)),
+            imported
+        );
+
+        // 再次导入没有差异，导入原来的规则则删除新增的规则
+        let diff = do_import(&state, &claims, &params(false), &body)
+            .await
+            .unwrap();
+        assert!(diff.added.is_empty() && diff.removed.is_empty() && !diff.applied);
+        let diff = do_import(&state, &claims, &params(false), &to_csv(&current))
+            .await
+            .unwrap();
+        assert_eq!(diff.removed, vec![extra]);
+        assert_eq!(
+            scoped_rules(&*state.enforcer.read().await, Some(domain)),
+            current
+        );
+    }
+
+    #[tokio::test]
+    async fn import_can_not_change_managed_groupings() {
+        let state = test_state().await;
+        let claims = test_super_admin(&state, "root").await;
+        let domain = claims.domain.as_str();
+        sys_role::ActiveModel {
+            id: NotSet,
+            name: Set("auditor".to_string()),
+            state: Set(true),
+            parent_id: Set(0),
+            domain: Set(domain.to_string()),
+            data_scope: Set(sys_role::DataScope::All),
+        }
+        .insert(&state.conn)
+        .await
+        .unwrap();
+        let current = scoped_rules(&*state.enforcer.read().await, Some(domain));
+
+        let mut added = current.clone();
+        added.insert(rule("g", &["paul", "auditor", domain]));
+        let mut removed = current.clone();
+        removed.remove(&rule("g", &["root", &CFG.auth.super_admin_role, domain]));
+        for imported in [added, removed] {
+            for dry_run in [true, false] {
+                let body = to_csv(&imported);
+                let err = do_import(&state, &claims, &params(dry_run), &body)
+                    .await
+                    .unwrap_err();
+                assert_eq!(err, ERR_POLICY_MANAGED);
+            }
+        }
+        assert_eq!(
+            scoped_rules(&*state.enforcer.read().await, Some(domain)),
+            current
+        );
+
+        // 不属于角色管理的角色仍然可以导入
+        let mut imported = current.clone();
+        imported.insert(rule("g", &["paul", "reviewer", domain]));
+        let diff = do_import(&state, &claims, &params(false), &to_csv(&imported))
+            .await
+            .unwrap();
+        assert!(diff.applied);
+    }
+}
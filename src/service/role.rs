@@ -0,0 +1,868 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::{
+    extract::{Path, Query, State},
+    routing::{delete, get},
+    Extension, Json, Router,
+};
+use casbin::MgmtApi;
+use entity::{
+    sys_role::{self, DataScope},
+    sys_role_dept, sys_user, sys_user_role,
+};
+use jsonwebtoken::get_current_timestamp;
+use sea_orm::{
+    sea_query::OnConflict, ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, ConnectionTrait,
+    DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, Set,
+    TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::config::CFG;
+use crate::constants::TENANT_ADMIN_ROLE;
+use crate::context::{AppState, SharedEnforcer};
+use crate::error::{
+    Result, ERR_INTERNAL, ERR_INVALID_PARAMS, ERR_ROLE_EXISTS, ERR_ROLE_NOT_FOUND,
+    ERR_USER_NOT_FOUND, OK_DELETE_ROLE,
+};
+use crate::service::menu::{self, MenuGrant};
+use crate::service::{api_key, auth, data_scope, dept};
+use crate::util::jwt::Claims;
+use crate::util::query::contains;
+use crate::util::res::{PageData, PageParams, Res};
+use crate::util::tree::{self, Tree};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list).post(create))
+        .route("/tree", get(tree))
+        .route("/:id", get(detail).put(update).delete(remove))
+        .route("/:id/users", get(members).post(add_members))
+        .route("/:id/users/:account", delete(remove_member))
+        .route("/:id/menus", get(menus).put(grant_menus))
+        .route("/:id/data-scope", get(data_scope).put(set_data_scope))
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct RoleFilter {
+    pub name: Option<String>,
+    pub state: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateRoleReq {
+    pub name: String,
+    // 0 表示顶级角色
+    #[serde(default)]
+    pub parent_id: i32,
+    #[serde(default = "default_state")]
+    pub state: bool,
+    #[serde(default)]
+    pub data_scope: DataScope,
+}
+
+// 只修改传入的字段
+#[derive(Deserialize, Debug, Default)]
+pub struct UpdateRoleReq {
+    pub name: Option<String>,
+    pub parent_id: Option<i32>,
+    pub state: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AddMembersReq {
+    pub accounts: Vec<String>,
+}
+
+// dept_ids 只在数据范围为 custom 时有效
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RoleDataScope {
+    pub data_scope: DataScope,
+    #[serde(default)]
+    pub dept_ids: Vec<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GrantMenusReq {
+    pub grants: Vec<MenuGrant>,
+}
+
+fn default_state() -> bool {
+    true
+}
+
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(page): Query<PageParams>,
+    Query(filter): Query<RoleFilter>,
+) -> Res<PageData<sys_role::Model>> {
+    list_roles(&state.conn, &claims.domain, &page, filter)
+        .await
+        .into()
+}
+
+pub async fn tree(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Res<Vec<Tree<sys_role::Model>>> {
+    role_tree(&state.conn, &claims.domain).await.into()
+}
+
+pub async fn detail(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+) -> Res<sys_role::Model> {
+    find_role(&state.conn, &claims.domain, id).await.into()
+}
+
+pub async fn create(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<CreateRoleReq>,
+) -> Res<sys_role::Model> {
+    do_create(&state, &claims, req).await.into()
+}
+
+pub async fn update(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+    Json(req): Json<UpdateRoleReq>,
+) -> Res<sys_role::Model> {
+    do_update(&state, &claims, id, req).await.into()
+}
+
+// 删除角色及其所有子角色，同时清理成员关系和 casbin 中的策略
+pub async fn remove(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+) -> Res<()> {
+    match do_remove(&state, &claims, id).await {
+        Ok(()) => Res::with_msg(&OK_DELETE_ROLE),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+pub async fn members(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+) -> Res<Vec<sys_user::Model>> {
+    role_members(&state.conn, &claims.domain, id).await.into()
+}
+
+pub async fn add_members(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+    Json(req): Json<AddMembersReq>,
+) -> Res<()> {
+    do_add_members(&state, &claims, id, req.accounts)
+        .await
+        .into()
+}
+
+pub async fn remove_member(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((id, account)): Path<(i32, String)>,
+) -> Res<()> {
+    do_remove_member(&state, &claims, id, &account).await.into()
+}
+
+pub async fn menus(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+) -> Res<Vec<MenuGrant>> {
+    let conn = &state.conn;
+    match find_role(conn, &claims.domain, id).await {
+        Ok(role) => menu::role_grants(conn, &state.enforcer, &claims.domain, &role.name)
+            .await
+            .into(),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+// 整体替换角色的菜单授权
+pub async fn grant_menus(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+    Json(req): Json<GrantMenusReq>,
+) -> Res<Vec<MenuGrant>> {
+    do_grant_menus(&state, &claims, id, req.grants).await.into()
+}
+
+pub async fn data_scope(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+) -> Res<RoleDataScope> {
+    role_data_scope(&state.conn, &claims.domain, id)
+        .await
+        .into()
+}
+
+pub async fn set_data_scope(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+    Json(req): Json<RoleDataScope>,
+) -> Res<RoleDataScope> {
+    do_set_data_scope(&state.conn, &claims, id, req)
+        .await
+        .into()
+}
+
+pub async fn list_roles(
+    conn: &DatabaseConnection,
+    domain: &str,
+    page: &PageParams,
+    filter: RoleFilter,
+) -> Result<PageData<sys_role::Model>> {
+    let mut query = sys_role::Entity::find().filter(sys_role::Column::Domain.eq(domain));
+    if let Some(name) = filter.name.filter(|v| !v.is_empty()) {
+        query = query.filter(contains(sys_role::Column::Name, &name));
+    }
+    if let Some(state) = filter.state {
+        query = query.filter(sys_role::Column::State.eq(state));
+    }
+
+    let paginator = query
+        .order_by_asc(sys_role::Column::Id)
+        .paginate(conn, page.page_size());
+    let totals = paginator.num_items_and_pages().await?;
+    let list = paginator.fetch_page(page.page_num() - 1).await?;
+    Ok(PageData::new(
+        list,
+        totals.number_of_items,
+        totals.number_of_pages,
+        page.page_num(),
+    ))
+}
+
+pub async fn role_tree(
+    conn: &DatabaseConnection,
+    domain: &str,
+) -> Result<Vec<Tree<sys_role::Model>>> {
+    Ok(tree::build(domain_roles(conn, domain).await?))
+}
+
+pub async fn find_role(
+    conn: &DatabaseConnection,
+    domain: &str,
+    id: i32,
+) -> Result<sys_role::Model> {
+    sys_role::Entity::find_by_id(id)
+        .filter(sys_role::Column::Domain.eq(domain))
+        .one(conn)
+        .await?
+        .ok_or(ERR_ROLE_NOT_FOUND)
+}
+
+pub async fn domain_roles(conn: &DatabaseConnection, domain: &str) -> Result<Vec<sys_role::Model>> {
+    let roles = sys_role::Entity::find()
+        .filter(sys_role::Column::Domain.eq(domain))
+        .order_by_asc(sys_role::Column::Id)
+        .all(conn)
+        .await?;
+    Ok(roles)
+}
+
+pub async fn role_members(
+    conn: &DatabaseConnection,
+    domain: &str,
+    id: i32,
+) -> Result<Vec<sys_user::Model>> {
+    let role = find_role(conn, domain, id).await?;
+    let user_ids: Vec<i32> = sys_user_role::Entity::find()
+        .filter(sys_user_role::Column::RoleId.eq(role.id))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|m| m.user_id)
+        .collect();
+    let users = sys_user::Entity::find()
+        .filter(sys_user::Column::Id.is_in(user_ids))
+        .order_by_asc(sys_user::Column::Id)
+        .all(conn)
+        .await?;
+    Ok(users)
+}
+
+pub async fn role_data_scope(
+    conn: &DatabaseConnection,
+    domain: &str,
+    id: i32,
+) -> Result<RoleDataScope> {
+    let role = find_role(conn, domain, id).await?;
+    Ok(RoleDataScope {
+        data_scope: role.data_scope,
+        dept_ids: data_scope::role_depts(conn, role.id).await?,
+    })
+}
+
+// 角色名和账号都是 casbin 中的 subject，共用同一个命名空间；内置的管理员角色名和 API key 的命名空间在所有域中保留
+pub async fn subject_taken(conn: &DatabaseConnection, domain: &str, name: &str) -> Result<bool> {
+    if name == CFG.auth.super_admin_role
+        || name == TENANT_ADMIN_ROLE
+        || name.starts_with(api_key::SUBJECT_PREFIX)
+    {
+        return Ok(true);
+    }
+    let role = sys_role::Entity::find()
+        .filter(sys_role::Column::Domain.eq(domain))
+        .filter(sys_role::Column::Name.eq(name))
+        .one(conn)
+        .await?;
+    Ok(role.is_some() || auth::find_user(conn, domain, name).await?.is_some())
+}
+
+async fn ensure_name_available(conn: &DatabaseConnection, domain: &str, name: &str) -> Result<()> {
+    if subject_taken(conn, domain, name).await? {
+        return Err(ERR_ROLE_EXISTS);
+    }
+    Ok(())
+}
+
+async fn do_create(
+    state: &AppState,
+    claims: &Claims,
+    req: CreateRoleReq,
+) -> Result<sys_role::Model> {
+    let conn = &state.conn;
+    let name = req.name.trim().to_string();
+    if name.is_empty() {
+        return Err(ERR_INVALID_PARAMS);
+    }
+    ensure_name_available(conn, &claims.domain, &name).await?;
+    tree::ensure_parent_valid::<sys_role::Entity>(conn, &claims.domain, None, req.parent_id)
+        .await?;
+
+    let role = sys_role::ActiveModel {
+        id: NotSet,
+        name: Set(name),
+        state: Set(req.state),
+        parent_id: Set(req.parent_id),
+        domain: Set(claims.domain.clone()),
+        data_scope: Set(req.data_scope),
+    }
+    .insert(conn)
+    .await?;
+    sync_domain(conn, &state.enforcer, &claims.domain, &[]).await?;
+    info!(
+        "user {} created role {} in domain {}",
+        claims.sub, role.name, role.domain
+    );
+    Ok(role)
+}
+
+async fn do_update(
+    state: &AppState,
+    claims: &Claims,
+    id: i32,
+    req: UpdateRoleReq,
+) -> Result<sys_role::Model> {
+    let conn = &state.conn;
+    let role = find_role(conn, &claims.domain, id).await?;
+    let old_name = role.name.clone();
+    let mut model: sys_role::ActiveModel = role.into();
+
+    let rename = match req.name.map(|n| n.trim().to_string()) {
+        Some(name) if name.is_empty() => return Err(ERR_INVALID_PARAMS),
+        Some(name) if name != old_name => {
+            ensure_name_available(conn, &claims.domain, &name).await?;
+            model.name = Set(name.clone());
+            Some(name)
+        }
+        _ => None,
+    };
+    if let Some(parent_id) = req.parent_id {
+        tree::ensure_parent_valid::<sys_role::Entity>(conn, &claims.domain, Some(id), parent_id)
+            .await?;
+        model.parent_id = Set(parent_id);
+    }
+    if let Some(state) = req.state {
+        model.state = Set(state);
+    }
+    let role = model.update(conn).await?;
+
+    // 改名时把旧名字上的权限转移到新名字，旧名字相关的 g 规则在同步时清理
+    let stale = match rename {
+        Some(name) => {
+            rename_policies(&state.enforcer, &claims.domain, &old_name, &name).await?;
+            vec![old_name]
+        }
+        None => vec![],
+    };
+    sync_domain(conn, &state.enforcer, &claims.domain, &stale).await?;
+    info!(
+        "user {} updated role {} in domain {}",
+        claims.sub, role.name, role.domain
+    );
+    Ok(role)
+}
+
+async fn do_remove(state: &AppState, claims: &Claims, id: i32) -> Result<()> {
+    let conn = &state.conn;
+    let role = find_role(conn, &claims.domain, id).await?;
+    let roles = domain_roles(conn, &claims.domain).await?;
+
+    // 以该角色为根的整棵子树
+    let ids = tree::subtree_ids(&roles, role.id);
+    let names: Vec<String> = roles
+        .into_iter()
+        .filter(|r| ids.contains(&r.id))
+        .map(|r| r.name)
+        .collect();
+
+    // 数据库提交后再修改 casbin 规则
+    let txn = conn.begin().await?;
+    sys_user_role::Entity::delete_many()
+        .filter(sys_user_role::Column::RoleId.is_in(ids.clone()))
+        .exec(&txn)
+        .await?;
+    sys_role_dept::Entity::delete_many()
+        .filter(sys_role_dept::Column::RoleId.is_in(ids.clone()))
+        .exec(&txn)
+        .await?;
+    sys_role::Entity::delete_many()
+        .filter(sys_role::Column::Id.is_in(ids))
+        .exec(&txn)
+        .await?;
+    txn.commit().await?;
+
+    {
+        let mut enforcer = state.enforcer.write().await;
+        let policies: Vec<Vec<String>> = names
+            .iter()
+            .flat_map(|name| {
+                enforcer.get_filtered_policy(0, vec![name.clone(), claims.domain.clone()])
+            })
+            .collect();
+        if !policies.is_empty() {
+            enforcer.remove_policies(policies).await?;
+        }
+    }
+    sync_domain(conn, &state.enforcer, &claims.domain, &names).await?;
+    info!(
+        "user {} deleted roles {:?} in domain {}",
+        claims.sub, names, claims.domain
+    );
+    Ok(())
+}
+
+async fn do_add_members(
+    state: &AppState,
+    claims: &Claims,
+    id: i32,
+    accounts: Vec<String>,
+) -> Result<()> {
+    let conn = &state.conn;
+    let role = find_role(conn, &claims.domain, id).await?;
+    let mut users = Vec::with_capacity(accounts.len());
+    for account in &accounts {
+        let user = auth::find_user(conn, &claims.domain, account)
+            .await?
+            .ok_or(ERR_USER_NOT_FOUND)?;
+        users.push(user);
+    }
+    assign_users(conn, &state.enforcer, &role, &users).await?;
+    info!(
+        "user {} added {:?} to role {} in domain {}",
+        claims.sub, accounts, role.name, role.domain
+    );
+    Ok(())
+}
+
+async fn do_remove_member(state: &AppState, claims: &Claims, id: i32, account: &str) -> Result<()> {
+    let conn = &state.conn;
+    let role = find_role(conn, &claims.domain, id).await?;
+    let user = auth::find_user(conn, &claims.domain, account)
+        .await?
+        .ok_or(ERR_USER_NOT_FOUND)?;
+    sys_user_role::Entity::delete_many()
+        .filter(sys_user_role::Column::RoleId.eq(role.id))
+        .filter(sys_user_role::Column::UserId.eq(user.id))
+        .exec(conn)
+        .await?;
+    sync_domain(conn, &state.enforcer, &claims.domain, &[]).await?;
+    info!(
+        "user {} removed {} from role {} in domain {}",
+        claims.sub, account, role.name, role.domain
+    );
+    Ok(())
+}
+
+// 自定义部门整体替换，其他数据范围会清空自定义部门
+async fn do_set_data_scope(
+    conn: &DatabaseConnection,
+    claims: &Claims,
+    id: i32,
+    req: RoleDataScope,
+) -> Result<RoleDataScope> {
+    let role = find_role(conn, &claims.domain, id).await?;
+    let dept_ids: HashSet<i32> = match req.data_scope {
+        DataScope::Custom => req.dept_ids.into_iter().collect(),
+        _ => HashSet::new(),
+    };
+    for &dept_id in &dept_ids {
+        dept::find_dept(conn, &claims.domain, dept_id).await?;
+    }
+
+    let txn = conn.begin().await?;
+    let mut model: sys_role::ActiveModel = role.into();
+    model.data_scope = Set(req.data_scope);
+    let role = model.update(&txn).await?;
+    sys_role_dept::Entity::delete_many()
+        .filter(sys_role_dept::Column::RoleId.eq(role.id))
+        .exec(&txn)
+        .await?;
+    if !dept_ids.is_empty() {
+        let models = dept_ids.iter().map(|&dept_id| sys_role_dept::ActiveModel {
+            id: NotSet,
+            role_id: Set(role.id),
+            dept_id: Set(dept_id),
+        });
+        sys_role_dept::Entity::insert_many(models)
+            .exec_without_returning(&txn)
+            .await?;
+    }
+    txn.commit().await?;
+    info!(
+        "user {} set data scope of role {} to {:?} {:?}",
+        claims.sub, role.name, role.data_scope, dept_ids
+    );
+    role_data_scope(conn, &claims.domain, role.id).await
+}
+
+async fn do_grant_menus(
+    state: &AppState,
+    claims: &Claims,
+    id: i32,
+    grants: Vec<MenuGrant>,
+) -> Result<Vec<MenuGrant>> {
+    let role = find_role(&state.conn, &claims.domain, id).await?;
+    let grants = menu::grant_role(
+        &state.conn,
+        &state.enforcer,
+        &claims.domain,
+        &role.name,
+        grants,
+    )
+    .await?;
+    info!(
+        "user {} granted menus {:?} to role {} in domain {}",
+        claims.sub, grants, role.name, role.domain
+    );
+    Ok(grants)
+}
+
+// 把用户加入角色并同步 casbin 中的 g 规则，已是成员的用户忽略
+pub async fn assign_users(
+    conn: &DatabaseConnection,
+    enforcer: &SharedEnforcer,
+    role: &sys_role::Model,
+    users: &[sys_user::Model],
+) -> Result<()> {
+    if users.is_empty() {
+        return Ok(());
+    }
+    let now = get_current_timestamp() as i64;
+    let models = users.iter().map(|user| sys_user_role::ActiveModel {
+        id: NotSet,
+        user_id: Set(user.id),
+        role_id: Set(role.id),
+        created_at: Set(now),
+    });
+    sys_user_role::Entity::insert_many(models)
+        .on_conflict(
+            OnConflict::columns([sys_user_role::Column::UserId, sys_user_role::Column::RoleId])
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(conn)
+        .await?;
+    sync_domain(conn, enforcer, &role.domain, &[]).await
+}
+
+// 删除用户时清理其角色关系
+pub async fn remove_user<C: ConnectionTrait>(conn: &C, user: &sys_user::Model) -> Result<()> {
+    sys_user_role::Entity::delete_many()
+        .filter(sys_user_role::Column::UserId.eq(user.id))
+        .exec(conn)
+        .await?;
+    Ok(())
+}
+
+async fn rename_policies(
+    enforcer: &SharedEnforcer,
+    domain: &str,
+    old: &str,
+    new: &str,
+) -> Result<()> {
+    let mut enforcer = enforcer.write().await;
+    let policies = enforcer.get_filtered_policy(0, vec![old.to_string(), domain.to_string()]);
+    if policies.is_empty() {
+        return Ok(());
+    }
+    // 先添加新名字上还没有的规则再删除旧规则，中途失败时只会多出规则，不会丢失权限
+    let renamed: Vec<Vec<String>> = policies
+        .iter()
+        .cloned()
+        .map(|mut p| {
+            p[0] = new.to_string();
+            p
+        })
+        .filter(|p| !enforcer.has_policy(p.clone()))
+        .collect();
+    if !renamed.is_empty() && !enforcer.add_policies(renamed).await? {
+        return Err(ERR_INTERNAL);
+    }
+    if !enforcer.remove_policies(policies).await? {
+        return Err(ERR_INTERNAL);
+    }
+    Ok(())
+}
+
+// 以数据库为准同步域内的 g 规则：启用的角色继承启用的父角色，用户属于启用的角色。
+// 只处理指向角色（以及 stale 中已删除或改名的角色）的规则，其他 g 规则保持不变
+pub async fn sync_domain(
+    conn: &DatabaseConnection,
+    enforcer: &SharedEnforcer,
+    domain: &str,
+    stale: &[String],
+) -> Result<()> {
+    let roles = domain_roles(conn, domain).await?;
+    let by_id: HashMap<i32, &sys_role::Model> = roles.iter().map(|r| (r.id, r)).collect();
+
+    let mut desired: HashSet<Vec<String>> = HashSet::new();
+    for role in roles.iter().filter(|r| r.state) {
+        if let Some(parent) = by_id.get(&role.parent_id).filter(|p| p.state) {
+            desired.insert(vec![
+                role.name.clone(),
+                parent.name.clone(),
+                domain.to_string(),
+            ]);
+        }
+    }
+
+    let enabled: Vec<i32> = roles.iter().filter(|r| r.state).map(|r| r.id).collect();
+    let memberships = sys_user_role::Entity::find()
+        .filter(sys_user_role::Column::RoleId.is_in(enabled))
+        .all(conn)
+        .await?;
+    let user_ids: Vec<i32> = memberships.iter().map(|m| m.user_id).collect();
+    let accounts: HashMap<i32, String> = sys_user::Entity::find()
+        .filter(sys_user::Column::Id.is_in(user_ids))
+        .filter(sys_user::Column::Domain.eq(domain))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|u| (u.id, u.account))
+        .collect();
+    for membership in &memberships {
+        if let (Some(account), Some(role)) = (
+            accounts.get(&membership.user_id),
+            by_id.get(&membership.role_id),
+        ) {
+            desired.insert(vec![account.clone(), role.name.clone(), domain.to_string()]);
+        }
+    }
+
+    let managed: HashSet<&str> = roles
+        .iter()
+        .map(|r| r.name.as_str())
+        .chain(stale.iter().map(String::as_str))
+        .collect();
+    let mut enforcer = enforcer.write().await;
+    let current: HashSet<Vec<String>> = enforcer
+        .get_filtered_grouping_policy(2, vec![domain.to_string()])
+        .into_iter()
+        .filter(|g| managed.contains(g[1].as_str()) || stale.contains(&g[0]))
+        .collect();
+
+    let removed: Vec<Vec<String>> = current.difference(&desired).cloned().collect();
+    let added: Vec<Vec<String>> = desired.difference(&current).cloned().collect();
+    if !removed.is_empty() {
+        enforcer.remove_grouping_policies(removed).await?;
+    }
+    if !added.is_empty() {
+        enforcer.add_grouping_policies(added).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{test_db, test_state, test_super_admin, test_user};
+    use casbin::CoreApi;
+    use sea_orm::ConnectionTrait;
+
+    #[tokio::test]
+    async fn role_names_can_not_shadow_accounts() {
+        let conn = test_db().await;
+        let domain = CFG.auth.platform_domain.as_str();
+        test_user(&conn, "paul").await;
+
+        for name in [
+            "paul",
+            CFG.auth.super_admin_role.as_str(),
+            TENANT_ADMIN_ROLE,
+            "apikey:ci",
+        ] {
+            let err = ensure_name_available(&conn, domain, name)
+                .await
+                .unwrap_err();
+            assert_eq!(err, ERR_ROLE_EXISTS);
+        }
+        ensure_name_available(&conn, domain, "auditor")
+            .await
+            .unwrap();
+        // 账号只在自己的域中占用名称
+        ensure_name_available(&conn, "other", "paul").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_remove_rolls_back() {
+        let state = test_state().await;
+        let claims = test_super_admin(&state, "root").await;
+        let req = CreateRoleReq {
+            name: "auditor".to_string(),
+            parent_id: 0,
+            state: true,
+            data_scope: DataScope::All,
+        };
+        let role = do_create(&state, &claims, req).await.unwrap();
+        do_add_members(&state, &claims, role.id, vec!["root".to_string()])
+            .await
+            .unwrap();
+
+        let conn = &state.conn;
+        conn.execute_unprepared("DROP TABLE sys_role_dept")
+            .await
+            .unwrap();
+        assert!(do_remove(&state, &claims, role.id).await.is_err());
+
+        let members = sys_user_role::Entity::find()
+            .filter(sys_user_role::Column::RoleId.eq(role.id))
+            .count(conn)
+            .await
+            .unwrap();
+        assert_eq!(members, 1);
+        let rule = vec!["root".to_string(), role.name, claims.domain.clone()];
+        assert!(state.enforcer.read().await.has_grouping_policy(rule));
+    }
+
+    fn rule(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn update(name: Option<&str>, state: Option<bool>) -> UpdateRoleReq {
+        UpdateRoleReq {
+            name: name.map(str::to_string),
+            parent_id: None,
+            state,
+        }
+    }
+
+    #[tokio::test]
+    async fn sync_follows_role_tree() {
+        let state = test_state().await;
+        let claims = test_super_admin(&state, "root").await;
+        let dom = claims.domain.as_str();
+        test_user(&state.conn, "ann").await;
+        let create = |name: &str, parent_id: i32| CreateRoleReq {
+            name: name.to_string(),
+            parent_id,
+            state: true,
+            data_scope: DataScope::All,
+        };
+        let manager = do_create(&state, &claims, create("manager", 0))
+            .await
+            .unwrap();
+        let clerk = do_create(&state, &claims, create("clerk", manager.id))
+            .await
+            .unwrap();
+        do_add_members(&state, &claims, clerk.id, vec!["ann".to_string()])
+            .await
+            .unwrap();
+        state
+            .enforcer
+            .write()
+            .await
+            .add_policies(vec![
+                rule(&["manager", dom, "/api/users", "GET"]),
+                rule(&["clerk", dom, "/api/depts", "GET"]),
+                rule(&["clerk", dom, "/api/menus", "GET"]),
+                // 新名字上已有的规则在改名时不会重复添加
+                rule(&["teller", dom, "/api/menus", "GET"]),
+            ])
+            .await
+            .unwrap();
+
+        let allowed = |obj: &'static str| {
+            let enforcer = state.enforcer.clone();
+            async move {
+                let mut enforcer = enforcer.write().await;
+                enforcer.enforce_mut(("ann", dom, obj, "GET")).unwrap()
+            }
+        };
+        let grouped = |values: &[&str]| {
+            let rule = rule(values);
+            let enforcer = state.enforcer.clone();
+            async move { enforcer.read().await.has_grouping_policy(rule) }
+        };
+        assert!(grouped(&["clerk", "manager", dom]).await);
+        assert!(allowed("/api/users").await);
+
+        // 禁用的父角色不再被继承，启用后恢复
+        do_update(&state, &claims, manager.id, update(None, Some(false)))
+            .await
+            .unwrap();
+        assert!(!grouped(&["clerk", "manager", dom]).await);
+        assert!(!allowed("/api/users").await);
+        assert!(allowed("/api/depts").await);
+        do_update(&state, &claims, manager.id, update(None, Some(true)))
+            .await
+            .unwrap();
+        assert!(allowed("/api/users").await);
+
+        // 禁用的角色不再关联成员和父角色
+        do_update(&state, &claims, clerk.id, update(None, Some(false)))
+            .await
+            .unwrap();
+        assert!(!grouped(&["ann", "clerk", dom]).await);
+        assert!(!grouped(&["clerk", "manager", dom]).await);
+        assert!(!allowed("/api/depts").await);
+        do_update(&state, &claims, clerk.id, update(None, Some(true)))
+            .await
+            .unwrap();
+        assert!(grouped(&["ann", "clerk", dom]).await);
+
+        // 改名后旧名字上的规则转移到新名字，旧名字的 g 规则被清理
+        do_update(&state, &claims, clerk.id, update(Some("teller"), None))
+            .await
+            .unwrap();
+        {
+            let enforcer = state.enforcer.read().await;
+            assert!(enforcer.get_filtered_policy(0, rule(&["clerk"])).is_empty());
+            assert_eq!(enforcer.get_filtered_policy(0, rule(&["teller"])).len(), 2);
+            let stale = enforcer.get_filtered_grouping_policy(0, rule(&["clerk"]));
+            assert!(stale.is_empty());
+            assert!(enforcer
+                .get_filtered_grouping_policy(1, rule(&["clerk"]))
+                .is_empty());
+        }
+        assert!(grouped(&["ann", "teller", dom]).await);
+        assert!(grouped(&["teller", "manager", dom]).await);
+        assert!(allowed("/api/users").await);
+        assert!(allowed("/api/depts").await);
+        assert!(allowed("/api/menus").await);
+    }
+}
@@ -0,0 +1,359 @@
+use std::{collections::HashMap, sync::RwLock, time::Duration};
+
+use axum::{
+    extract::{Path, State},
+    routing::{delete, get},
+    Extension, Router,
+};
+use entity::{sys_refresh_token, sys_revoked_token};
+use jsonwebtoken::get_current_timestamp;
+use once_cell::sync::Lazy;
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ActiveValue::NotSet,
+    ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+    TransactionTrait,
+};
+
+use crate::context::AppState;
+use crate::error::{Result, ERR_SESSION_NOT_FOUND, OK_KICK_SESSION};
+use crate::util::jwt::{Claims, JWT};
+use crate::util::res::Res;
+
+// 吊销列表的同步间隔，多实例部署时其他实例的吊销最多延迟这么久生效
+const SYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+// 已吊销的 access token：token_id -> 过期时间
+static REVOKED: Lazy<RwLock<HashMap<String, i64>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_online))
+        .route("/:token_id", delete(kick_session))
+        .route("/user/:account", delete(kick_user))
+}
+
+pub async fn list_online(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Res<Vec<sys_refresh_token::Model>> {
+    online_sessions(&state.conn, &claims.domain).await.into()
+}
+
+pub async fn kick_session(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(token_id): Path<String>,
+) -> Res<()> {
+    match revoke_session(&state.conn, &claims.domain, &token_id).await {
+        Ok(true) => Res::with_msg(&OK_KICK_SESSION),
+        Ok(false) => Res::with_err(&ERR_SESSION_NOT_FOUND),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+pub async fn kick_user(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(account): Path<String>,
+) -> Res<u64> {
+    match revoke_user(&state.conn, &claims.domain, &account).await {
+        Ok(count) => Res::with_data_msg(count, &OK_KICK_SESSION),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+// 认证中间件调用，只查内存
+pub fn is_revoked(token_id: &str) -> bool {
+    REVOKED
+        .read()
+        .unwrap()
+        .get(token_id)
+        .is_some_and(|exp| *exp > get_current_timestamp() as i64)
+}
+
+// 当前域内未过期且未吊销的会话，每个令牌族只有最新的一条
+pub async fn online_sessions(
+    conn: &DatabaseConnection,
+    domain: &str,
+) -> Result<Vec<sys_refresh_token::Model>> {
+    let sessions = sys_refresh_token::Entity::find()
+        .filter(sys_refresh_token::Column::Domain.eq(domain))
+        .filter(sys_refresh_token::Column::Revoked.eq(false))
+        .filter(sys_refresh_token::Column::ExpiresAt.gt(get_current_timestamp() as i64))
+        .order_by_desc(sys_refresh_token::Column::CreatedAt)
+        .all(conn)
+        .await?;
+    Ok(sessions)
+}
+
+// 吊销 token_id 所属的整个会话，返回会话是否存在
+pub async fn revoke_session(
+    conn: &DatabaseConnection,
+    domain: &str,
+    token_id: &str,
+) -> Result<bool> {
+    let row = sys_refresh_token::Entity::find()
+        .filter(sys_refresh_token::Column::Domain.eq(domain))
+        .filter(sys_refresh_token::Column::TokenId.eq(token_id))
+        .one(conn)
+        .await?;
+    match row {
+        Some(row) => {
+            revoke_family(conn, &row.family_id).await?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+// 强制下线用户的所有会话，返回吊销的会话数
+pub async fn revoke_user(conn: &DatabaseConnection, domain: &str, account: &str) -> Result<u64> {
+    let txn = conn.begin().await?;
+    let revoked = revoke_user_in(&txn, domain, account).await?;
+    txn.commit().await?;
+    Ok(revoked.cache())
+}
+
+// 已写入数据库、还没有加入内存吊销列表的会话，调用方提交事务后调用 cache
+#[must_use]
+pub struct RevokedSessions {
+    sessions: u64,
+    tokens: Vec<(String, i64)>,
+}
+
+impl RevokedSessions {
+    // 返回吊销的会话数
+    pub fn cache(self) -> u64 {
+        REVOKED.write().unwrap().extend(self.tokens);
+        self.sessions
+    }
+}
+
+// 在调用方的事务中吊销用户的所有会话
+pub async fn revoke_user_in<C: ConnectionTrait>(
+    conn: &C,
+    domain: &str,
+    account: &str,
+) -> Result<RevokedSessions> {
+    let sessions: Vec<String> = sys_refresh_token::Entity::find()
+        .filter(sys_refresh_token::Column::Domain.eq(domain))
+        .filter(sys_refresh_token::Column::Account.eq(account))
+        .filter(sys_refresh_token::Column::Revoked.eq(false))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|row| row.family_id)
+        .collect();
+    let mut tokens = vec![];
+    for family_id in &sessions {
+        tokens.extend(revoke_family_in(conn, family_id).await?);
+    }
+    info!(
+        "revoked {} sessions of user {} in domain {}",
+        sessions.len(),
+        account,
+        domain
+    );
+    Ok(RevokedSessions {
+        sessions: sessions.len() as u64,
+        tokens,
+    })
+}
+
+// 吊销令牌族中的刷新令牌，以及仍可能有效的 access token
+pub async fn revoke_family(conn: &DatabaseConnection, family_id: &str) -> Result<()> {
+    // 先提交到数据库再更新内存，写入失败时本实例不会与数据库及其他实例不一致
+    let txn = conn.begin().await?;
+    let tokens = revoke_family_in(&txn, family_id).await?;
+    txn.commit().await?;
+
+    REVOKED.write().unwrap().extend(tokens);
+    Ok(())
+}
+
+// 返回需要加入内存吊销列表的 access token
+async fn revoke_family_in<C: ConnectionTrait>(
+    conn: &C,
+    family_id: &str,
+) -> Result<Vec<(String, i64)>> {
+    let rows = sys_refresh_token::Entity::find()
+        .filter(sys_refresh_token::Column::FamilyId.eq(family_id))
+        .all(conn)
+        .await?;
+
+    let now = get_current_timestamp() as i64;
+    let revoked: Vec<sys_revoked_token::ActiveModel> = rows
+        .into_iter()
+        .map(|row| (row.created_at + JWT.expire() as i64, row))
+        .filter(|(exp, _)| *exp > now)
+        .map(|(exp, row)| sys_revoked_token::ActiveModel {
+            id: NotSet,
+            token_id: Set(row.token_id),
+            account: Set(row.account),
+            domain: Set(row.domain),
+            expires_at: Set(exp),
+            created_at: Set(now),
+        })
+        .collect();
+    let entries: Vec<(String, i64)> = revoked
+        .iter()
+        .map(|model| {
+            (
+                model.token_id.clone().unwrap(),
+                model.expires_at.clone().unwrap(),
+            )
+        })
+        .collect();
+
+    sys_refresh_token::Entity::update_many()
+        .col_expr(sys_refresh_token::Column::Revoked, Expr::value(true))
+        .filter(sys_refresh_token::Column::FamilyId.eq(family_id))
+        .exec(conn)
+        .await?;
+    if !revoked.is_empty() {
+        sys_revoked_token::Entity::insert_many(revoked)
+            .on_conflict(
+                OnConflict::column(sys_revoked_token::Column::TokenId)
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec_without_returning(conn)
+            .await?;
+    }
+    Ok(entries)
+}
+
+// 从数据库加载未过期的吊销记录并清理已过期的记录
+pub async fn load_revoked(conn: &DatabaseConnection) -> Result<()> {
+    let now = get_current_timestamp() as i64;
+    sys_revoked_token::Entity::delete_many()
+        .filter(sys_revoked_token::Column::ExpiresAt.lte(now))
+        .exec(conn)
+        .await?;
+    let rows = sys_revoked_token::Entity::find().all(conn).await?;
+
+    let mut cache = REVOKED.write().unwrap();
+    cache.retain(|_, exp| *exp > now);
+    cache.extend(rows.into_iter().map(|row| (row.token_id, row.expires_at)));
+    Ok(())
+}
+
+pub async fn sync_revoked(conn: DatabaseConnection) {
+    let mut interval = tokio::time::interval(SYNC_INTERVAL);
+    loop {
+        interval.tick().await;
+        if load_revoked(&conn).await.is_err() {
+            warn!("failed to sync revoked tokens");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{test_db, test_user};
+    use crate::service::token::{issue_pair, rotate};
+    use sea_orm::ConnectionTrait;
+
+    fn token_id(token: &str) -> String {
+        JWT.verify(token).unwrap().token_id
+    }
+
+    #[tokio::test]
+    async fn kick_single_session() {
+        let conn = test_db().await;
+        let user = test_user(&conn, "frank").await;
+        let first = issue_pair(&conn, &user, None).await.unwrap();
+        let rotated = rotate(&conn, first.refresh_token.as_ref().unwrap())
+            .await
+            .unwrap();
+        let other = issue_pair(&conn, &user, None).await.unwrap();
+
+        let online = online_sessions(&conn, &user.domain).await.unwrap();
+        assert_eq!(online.len(), 2);
+
+        // 用令牌族中任意一个 token_id 都能踢掉整个会话
+        let first_id = token_id(&first.token);
+        assert!(revoke_session(&conn, &user.domain, &first_id)
+            .await
+            .unwrap());
+        assert!(is_revoked(&token_id(&rotated.token)));
+        assert!(!is_revoked(&token_id(&other.token)));
+        assert_eq!(online_sessions(&conn, &user.domain).await.unwrap().len(), 1);
+
+        assert!(!revoke_session(&conn, &user.domain, "missing")
+            .await
+            .unwrap());
+        assert!(!revoke_session(&conn, "other", &first_id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn kick_all_sessions_of_user() {
+        let conn = test_db().await;
+        let user = test_user(&conn, "grace").await;
+        let bystander = test_user(&conn, "heidi").await;
+        let a = issue_pair(&conn, &user, None).await.unwrap();
+        let b = issue_pair(&conn, &user, None).await.unwrap();
+        let c = issue_pair(&conn, &bystander, None).await.unwrap();
+
+        assert_eq!(
+            revoke_user(&conn, &user.domain, &user.account)
+                .await
+                .unwrap(),
+            2
+        );
+        assert!(is_revoked(&token_id(&a.token)));
+        assert!(is_revoked(&token_id(&b.token)));
+        assert!(!is_revoked(&token_id(&c.token)));
+        assert_eq!(
+            revoke_user(&conn, &user.domain, &user.account)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_revocation_leaves_cache_untouched() {
+        let conn = test_db().await;
+        let user = test_user(&conn, "judy").await;
+        let body = issue_pair(&conn, &user, None).await.unwrap();
+        conn.execute_unprepared("DROP TABLE sys_revoked_token")
+            .await
+            .unwrap();
+
+        let access = token_id(&body.token);
+        assert!(revoke_session(&conn, &user.domain, &access).await.is_err());
+        assert!(!is_revoked(&access));
+        let rows = sys_refresh_token::Entity::find().all(&conn).await.unwrap();
+        assert!(!rows[0].revoked);
+    }
+
+    #[tokio::test]
+    async fn load_revoked_prunes_expired() {
+        let conn = test_db().await;
+        let now = get_current_timestamp() as i64;
+        let rows = [("expired-token", now - 1), ("live-token", now + 600)].map(|(id, exp)| {
+            sys_revoked_token::ActiveModel {
+                id: NotSet,
+                token_id: Set(id.to_string()),
+                account: Set("ivan".to_string()),
+                domain: Set("default".to_string()),
+                expires_at: Set(exp),
+                created_at: Set(now),
+            }
+        });
+        sys_revoked_token::Entity::insert_many(rows)
+            .exec(&conn)
+            .await
+            .unwrap();
+
+        load_revoked(&conn).await.unwrap();
+        assert!(is_revoked("live-token"));
+        assert!(!is_revoked("expired-token"));
+        let remaining = sys_revoked_token::Entity::find().all(&conn).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].token_id, "live-token");
+    }
+}
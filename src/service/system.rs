@@ -0,0 +1,32 @@
+use axum::{extract::State, routing::post, Extension, Router};
+
+use crate::context::AppState;
+use crate::error::{ERR_TLS_DISABLED, ERR_TLS_RELOAD_FAILED, OK_TLS_RELOAD};
+use crate::util::jwt::Claims;
+use crate::util::res::Res;
+use crate::util::tls;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/tls/reload", post(reload_tls))
+}
+
+// 证书轮换后手动触发重新加载，新证书无效时继续使用旧证书
+pub async fn reload_tls(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Res<()> {
+    let Some(config) = state.tls else {
+        return Res::with_err(&ERR_TLS_DISABLED);
+    };
+    info!("user {} requested tls certificate reload", claims.sub);
+    match tls::reload(&config) {
+        Ok(()) => Res::with_msg(&OK_TLS_RELOAD),
+        Err(err) => {
+            error!(
+                "failed to reload tls certificate, keep using the old one: {}",
+                err
+            );
+            Res::with_err(&ERR_TLS_RELOAD_FAILED)
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use entity::{sys_refresh_token, sys_user};
+use jsonwebtoken::get_current_timestamp;
+use rand::RngCore;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, DatabaseConnection,
+    EntityTrait, QueryFilter, Set,
+};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::error::{Result, ERR_INVALID_TOKEN, ERR_TOKEN_REUSED, ERR_USER_DISABLED};
+use crate::service::auth::find_user;
+use crate::service::domain;
+use crate::service::session::revoke_family;
+use crate::util::jwt::{AuthBody, JWT};
+
+// 为用户签发 access token 和刷新令牌，family_id 为空时开启新的令牌族
+pub async fn issue_pair(
+    conn: &DatabaseConnection,
+    user: &sys_user::Model,
+    family_id: Option<String>,
+) -> Result<AuthBody> {
+    let (claims, body) = JWT.issue(user)?;
+
+    // 刷新令牌格式为 "<token_id>.<secret>"，数据库只保存 secret 的 sha256
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    let secret = hex::encode(secret);
+    let refresh_token = format!("{}.{}", claims.token_id, secret);
+
+    let now = get_current_timestamp() as i64;
+    let model = sys_refresh_token::ActiveModel {
+        id: NotSet,
+        token_id: Set(claims.token_id),
+        family_id: Set(family_id.unwrap_or_else(|| Uuid::new_v4().to_string())),
+        token_hash: Set(sha256(&secret)),
+        account: Set(user.account.clone()),
+        domain: Set(user.domain.clone()),
+        expires_at: Set(now + JWT.refresh_expire() as i64),
+        revoked: Set(false),
+        created_at: Set(now),
+    };
+    model.insert(conn).await?;
+
+    Ok(body.with_refresh_token(refresh_token))
+}
+
+// 使用刷新令牌换取新的令牌对，旧令牌立即作废；已作废的令牌再次使用视为泄露，整个令牌族全部吊销
+pub async fn rotate(conn: &DatabaseConnection, refresh_token: &str) -> Result<AuthBody> {
+    let (token_id, secret) = refresh_token.split_once('.').ok_or(ERR_INVALID_TOKEN)?;
+    let row = sys_refresh_token::Entity::find()
+        .filter(sys_refresh_token::Column::TokenId.eq(token_id))
+        .one(conn)
+        .await?
+        .ok_or(ERR_INVALID_TOKEN)?;
+
+    if row.token_hash != sha256(secret) {
+        return Err(ERR_INVALID_TOKEN);
+    }
+    if row.revoked {
+        warn!(
+            "refresh token reuse detected for user {} in domain {}, revoking family {}",
+            row.account, row.domain, row.family_id
+        );
+        revoke_family(conn, &row.family_id).await?;
+        return Err(ERR_TOKEN_REUSED);
+    }
+    if row.expires_at <= get_current_timestamp() as i64 {
+        return Err(ERR_INVALID_TOKEN);
+    }
+    domain::ensure_enabled(&row.domain)?;
+
+    // 条件更新保证并发请求中只有一个能够完成轮换
+    let updated = sys_refresh_token::Entity::update_many()
+        .col_expr(sys_refresh_token::Column::Revoked, Expr::value(true))
+        .filter(sys_refresh_token::Column::Id.eq(row.id))
+        .filter(sys_refresh_token::Column::Revoked.eq(false))
+        .exec(conn)
+        .await?;
+    if updated.rows_affected != 1 {
+        revoke_family(conn, &row.family_id).await?;
+        return Err(ERR_TOKEN_REUSED);
+    }
+
+    let user = find_user(conn, &row.domain, &row.account)
+        .await?
+        .ok_or(ERR_INVALID_TOKEN)?;
+    if !user.state {
+        revoke_family(conn, &row.family_id).await?;
+        return Err(ERR_USER_DISABLED);
+    }
+
+    issue_pair(conn, &user, Some(row.family_id)).await
+}
+
+pub fn sha256(value: &str) -> String {
+    hex::encode(Sha256::digest(value.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{test_db, test_user};
+    use crate::error::ERR_USER_DISABLED;
+    use crate::service::session::is_revoked;
+    use sea_orm::ActiveModelTrait;
+
+    fn token_id(body: &AuthBody) -> String {
+        JWT.verify(&body.token).unwrap().token_id
+    }
+
+    #[tokio::test]
+    async fn rotate_issues_new_pair_in_same_family() {
+        let conn = test_db().await;
+        let user = test_user(&conn, "alice").await;
+        let first = issue_pair(&conn, &user, None).await.unwrap();
+        let second = rotate(&conn, first.refresh_token.as_ref().unwrap())
+            .await
+            .unwrap();
+        assert_ne!(first.refresh_token, second.refresh_token);
+
+        let rows = sys_refresh_token::Entity::find().all(&conn).await.unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].family_id, rows[1].family_id);
+        assert!(rows[0].revoked);
+        assert!(!rows[1].revoked);
+        assert!(!is_revoked(&token_id(&second)));
+    }
+
+    #[tokio::test]
+    async fn reuse_revokes_whole_family() {
+        let conn = test_db().await;
+        let user = test_user(&conn, "bob").await;
+        let first = issue_pair(&conn, &user, None).await.unwrap();
+        let first_refresh = first.refresh_token.clone().unwrap();
+        let second = rotate(&conn, &first_refresh).await.unwrap();
+
+        assert_eq!(
+            rotate(&conn, &first_refresh).await.unwrap_err(),
+            ERR_TOKEN_REUSED
+        );
+        let second_refresh = second.refresh_token.clone().unwrap();
+        assert_eq!(
+            rotate(&conn, &second_refresh).await.unwrap_err(),
+            ERR_TOKEN_REUSED
+        );
+        assert!(is_revoked(&token_id(&first)));
+        assert!(is_revoked(&token_id(&second)));
+
+        // 其他令牌族不受影响
+        let other = issue_pair(&conn, &user, None).await.unwrap();
+        assert!(rotate(&conn, other.refresh_token.as_ref().unwrap())
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn invalid_refresh_tokens() {
+        let conn = test_db().await;
+        let user = test_user(&conn, "carol").await;
+        let body = issue_pair(&conn, &user, None).await.unwrap();
+        let refresh = body.refresh_token.unwrap();
+        let (token_id, _) = refresh.split_once('.').unwrap();
+
+        for token in [
+            "",
+            "garbage",
+            &format!("{}.wrong", token_id),
+            "missing.secret",
+        ] {
+            assert_eq!(rotate(&conn, token).await.unwrap_err(), ERR_INVALID_TOKEN);
+        }
+        // 伪造的 secret 不会吊销真实的令牌族
+        assert!(rotate(&conn, &refresh).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn expired_refresh_token() {
+        let conn = test_db().await;
+        let user = test_user(&conn, "dave").await;
+        let body = issue_pair(&conn, &user, None).await.unwrap();
+        sys_refresh_token::Entity::update_many()
+            .col_expr(sys_refresh_token::Column::ExpiresAt, Expr::value(0))
+            .exec(&conn)
+            .await
+            .unwrap();
+        let err = rotate(&conn, body.refresh_token.as_ref().unwrap())
+            .await
+            .unwrap_err();
+        assert_eq!(err, ERR_INVALID_TOKEN);
+    }
+
+    #[tokio::test]
+    async fn disabled_user_cannot_refresh() {
+        let conn = test_db().await;
+        let user = test_user(&conn, "erin").await;
+        let body = issue_pair(&conn, &user, None).await.unwrap();
+        let access = token_id(&body);
+        let mut model: sys_user::ActiveModel = user.into();
+        model.state = Set(false);
+        model.update(&conn).await.unwrap();
+
+        let refresh = body.refresh_token.unwrap();
+        assert_eq!(
+            rotate(&conn, &refresh).await.unwrap_err(),
+            ERR_USER_DISABLED
+        );
+        assert!(is_revoked(&access));
+        assert_eq!(rotate(&conn, &refresh).await.unwrap_err(), ERR_TOKEN_REUSED);
+    }
+}
@@ -0,0 +1,486 @@
+use axum::{extract::State, routing::post, Extension, Json, Router};
+use entity::{sys_recovery_code, sys_user, sys_user_totp};
+use jsonwebtoken::get_current_timestamp;
+use rand::Rng;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, ConnectionTrait,
+    DatabaseConnection, EntityTrait, QueryFilter, Set,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::config::CFG;
+use crate::context::AppState;
+use crate::error::{
+    Result, ERR_ACCOUNT_LOCKED, ERR_INVALID_TOKEN, ERR_TOTP_ALREADY_ENABLED, ERR_TOTP_INVALID,
+    ERR_TOTP_NOT_ENROLLED, ERR_USER_DISABLED, ERR_USER_NOT_FOUND, OK_SIGNIN,
+};
+use crate::service::{auth, domain, token};
+use crate::util::jwt::{AuthBody, Claims, JWT};
+use crate::util::res::Res;
+use crate::util::totp;
+
+const RECOVERY_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/enroll", post(enroll))
+        .route("/activate", post(activate))
+        .route("/verify", post(verify))
+}
+
+#[derive(Serialize, Debug)]
+pub struct EnrollRes {
+    pub secret: String,
+    pub provisioning_uri: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ActivateReq {
+    pub code: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct VerifyReq {
+    pub mfa_token: String,
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+// 生成新的密钥，验证码校验通过之前不生效
+pub async fn enroll(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Res<EnrollRes> {
+    do_enroll(&state.conn, &claims).await.into()
+}
+
+// 使用认证器上的验证码确认绑定，返回只显示一次的恢复码
+pub async fn activate(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<ActivateReq>,
+) -> Res<Vec<String>> {
+    do_activate(&state.conn, &claims, &req.code).await.into()
+}
+
+// 登录的第二步，用中间 token 和验证码或恢复码换取正式 token
+pub async fn verify(State(state): State<AppState>, Json(req): Json<VerifyReq>) -> Res<AuthBody> {
+    match do_verify(&state.conn, req).await {
+        Ok(body) => Res::with_data_msg(body, &OK_SIGNIN),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+pub async fn is_enabled(conn: &DatabaseConnection, user_id: i32) -> Result<bool> {
+    let row = find_totp(conn, user_id).await?;
+    Ok(row.is_some_and(|r| r.enabled))
+}
+
+// 管理员重置用户的两步验证，删除密钥和恢复码
+pub async fn reset<C: ConnectionTrait>(conn: &C, user_id: i32) -> Result<()> {
+    sys_user_totp::Entity::delete_many()
+        .filter(sys_user_totp::Column::UserId.eq(user_id))
+        .exec(conn)
+        .await?;
+    sys_recovery_code::Entity::delete_many()
+        .filter(sys_recovery_code::Column::UserId.eq(user_id))
+        .exec(conn)
+        .await?;
+    Ok(())
+}
+
+async fn find_totp(
+    conn: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Option<sys_user_totp::Model>> {
+    let row = sys_user_totp::Entity::find()
+        .filter(sys_user_totp::Column::UserId.eq(user_id))
+        .one(conn)
+        .await?;
+    Ok(row)
+}
+
+async fn current_user(conn: &DatabaseConnection, claims: &Claims) -> Result<sys_user::Model> {
+    auth::find_user(conn, &claims.domain, &claims.sub)
+        .await?
+        .ok_or(ERR_USER_NOT_FOUND)
+}
+
+async fn do_enroll(conn: &DatabaseConnection, claims: &Claims) -> Result<EnrollRes> {
+    let user = current_user(conn, claims).await?;
+    let secret = totp::generate_secret();
+    match find_totp(conn, user.id).await? {
+        Some(row) if row.enabled => return Err(ERR_TOTP_ALREADY_ENABLED),
+        Some(row) => {
+            let mut model: sys_user_totp::ActiveModel = row.into();
+            model.secret = Set(secret.clone());
+            model.last_used_step = Set(0);
+            model.update(conn).await?;
+        }
+        None => {
+            sys_user_totp::ActiveModel {
+                id: NotSet,
+                user_id: Set(user.id),
+                secret: Set(secret.clone()),
+                enabled: Set(false),
+                last_used_step: Set(0),
+                created_at: Set(get_current_timestamp() as i64),
+            }
+            .insert(conn)
+            .await?;
+        }
+    }
+
+    let label = format!("{}@{}", user.account, user.domain);
+    Ok(EnrollRes {
+        provisioning_uri: totp::provisioning_uri(&CFG.totp.issuer, &label, &secret),
+        secret,
+    })
+}
+
+async fn do_activate(
+    conn: &DatabaseConnection,
+    claims: &Claims,
+    code: &str,
+) -> Result<Vec<String>> {
+    let user = current_user(conn, claims).await?;
+    let row = find_totp(conn, user.id)
+        .await?
+        .ok_or(ERR_TOTP_NOT_ENROLLED)?;
+    if row.enabled {
+        return Err(ERR_TOTP_ALREADY_ENABLED);
+    }
+    let step = totp::verify(&row.secret, code, get_current_timestamp(), CFG.totp.skew)
+        .ok_or(ERR_TOTP_INVALID)?;
+
+    let mut model: sys_user_totp::ActiveModel = row.into();
+    model.enabled = Set(true);
+    model.last_used_step = Set(step as i64);
+    model.update(conn).await?;
+
+    let codes = regenerate_recovery_codes(conn, user.id).await?;
+    info!(
+        "user {} in domain {} enabled 2fa",
+        user.account, user.domain
+    );
+    Ok(codes)
+}
+
+async fn do_verify(conn: &DatabaseConnection, req: VerifyReq) -> Result<AuthBody> {
+    let claims = JWT
+        .verify_mfa(&req.mfa_token)
+        .map_err(|_| ERR_INVALID_TOKEN)?;
+    let user = auth::find_user(conn, &claims.domain, &claims.sub)
+        .await?
+        .ok_or(ERR_INVALID_TOKEN)?;
+    // 输入密码之后域可能已被禁用
+    domain::ensure_enabled(&user.domain)?;
+    let now = get_current_timestamp();
+    if user.locked_until > now as i64 {
+        return Err(ERR_ACCOUNT_LOCKED);
+    }
+    if !user.state {
+        return Err(ERR_USER_DISABLED);
+    }
+    let row = find_totp(conn, user.id)
+        .await?
+        .filter(|r| r.enabled)
+        .ok_or(ERR_TOTP_NOT_ENROLLED)?;
+
+    check_second_factor(conn, &user, &row, req.code, req.recovery_code, now).await?;
+
+    let body = token::issue_pair(conn, &user, None).await?;
+    info!(
+        "user {} signed in to domain {} with 2fa",
+        user.account, user.domain
+    );
+    Ok(body)
+}
+
+// 开启了两步验证的用户在没有 token 的情况下修改密码时，同样需要验证码或恢复码
+pub async fn ensure_second_factor(
+    conn: &DatabaseConnection,
+    user: &sys_user::Model,
+    code: Option<String>,
+    recovery_code: Option<String>,
+) -> Result<()> {
+    let Some(row) = find_totp(conn, user.id).await?.filter(|r| r.enabled) else {
+        return Ok(());
+    };
+    check_second_factor(
+        conn,
+        user,
+        &row,
+        code,
+        recovery_code,
+        get_current_timestamp(),
+    )
+    .await
+}
+
+async fn check_second_factor(
+    conn: &DatabaseConnection,
+    user: &sys_user::Model,
+    row: &sys_user_totp::Model,
+    code: Option<String>,
+    recovery_code: Option<String>,
+    now: u64,
+) -> Result<()> {
+    let passed = match (code, recovery_code) {
+        (Some(code), _) => consume_code(conn, row, &code, now).await?,
+        (None, Some(recovery_code)) => consume_recovery_code(conn, user.id, &recovery_code).await?,
+        (None, None) => false,
+    };
+    if !passed {
+        // 验证码只有 6 位，失败次数计入登录锁定
+        auth::record_failure(conn, user, now as i64).await?;
+        return Err(ERR_TOTP_INVALID);
+    }
+    Ok(())
+}
+
+// 同一个时间片的验证码只能使用一次
+async fn consume_code(
+    conn: &DatabaseConnection,
+    row: &sys_user_totp::Model,
+    code: &str,
+    now: u64,
+) -> Result<bool> {
+    let Some(step) = totp::verify(&row.secret, code, now, CFG.totp.skew) else {
+        return Ok(false);
+    };
+    let updated = sys_user_totp::Entity::update_many()
+        .col_expr(
+            sys_user_totp::Column::LastUsedStep,
+            Expr::value(step as i64),
+        )
+        .filter(sys_user_totp::Column::Id.eq(row.id))
+        .filter(sys_user_totp::Column::LastUsedStep.lt(step as i64))
+        .exec(conn)
+        .await?;
+    Ok(updated.rows_affected == 1)
+}
+
+async fn consume_recovery_code(
+    conn: &DatabaseConnection,
+    user_id: i32,
+    code: &str,
+) -> Result<bool> {
+    let updated = sys_recovery_code::Entity::update_many()
+        .col_expr(sys_recovery_code::Column::Used, Expr::value(true))
+        .filter(sys_recovery_code::Column::UserId.eq(user_id))
+        .filter(sys_recovery_code::Column::CodeHash.eq(hash_recovery_code(code)))
+        .filter(sys_recovery_code::Column::Used.eq(false))
+        .exec(conn)
+        .await?;
+    if updated.rows_affected == 1 {
+        warn!("user {} signed in with a recovery code", user_id);
+    }
+    Ok(updated.rows_affected == 1)
+}
+
+async fn regenerate_recovery_codes(conn: &DatabaseConnection, user_id: i32) -> Result<Vec<String>> {
+    sys_recovery_code::Entity::delete_many()
+        .filter(sys_recovery_code::Column::UserId.eq(user_id))
+        .exec(conn)
+        .await?;
+
+    let now = get_current_timestamp() as i64;
+    let codes: Vec<String> = (0..CFG.totp.recovery_codes)
+        .map(|_| generate_recovery_code())
+        .collect();
+    let models = codes.iter().map(|code| sys_recovery_code::ActiveModel {
+        id: NotSet,
+        user_id: Set(user_id),
+        code_hash: Set(hash_recovery_code(code)),
+        used: Set(false),
+        created_at: Set(now),
+    });
+    if !codes.is_empty() {
+        sys_recovery_code::Entity::insert_many(models)
+            .exec(conn)
+            .await?;
+    }
+    Ok(codes)
+}
+
+// 形如 ABCDE-FGHJK，恢复码本身随机性足够，用 sha256 保存即可
+fn generate_recovery_code() -> String {
+    let mut rng = rand::thread_rng();
+    let chars: String = (0..10)
+        .map(|_| RECOVERY_CODE_ALPHABET[rng.gen_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+        .collect();
+    format!("{}-{}", &chars[..5], &chars[5..])
+}
+
+fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    token::sha256(&normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{test_db, test_state, test_super_admin, test_user};
+    use crate::error::ERR_DOMAIN_DISABLED;
+    use crate::service::domain::DomainStateReq;
+    use axum::extract::Path;
+
+    // 认证器 App 在 offset 个时间片之后显示的验证码
+    fn code_for(secret: &str, offset: u64) -> String {
+        let secret = base32::decode(base32::Alphabet::RFC4648 { padding: false }, secret).unwrap();
+        let step = get_current_timestamp() / 30 + offset;
+        format!("{:06}", totp::code_at(&secret, step))
+    }
+
+    fn verify_req(
+        user: &sys_user::Model,
+        code: Option<String>,
+        recovery: Option<&str>,
+    ) -> VerifyReq {
+        VerifyReq {
+            mfa_token: JWT.issue_mfa(user).unwrap().0,
+            code,
+            recovery_code: recovery.map(str::to_string),
+        }
+    }
+
+    // 绑定并开启两步验证，返回密钥、开启时使用的验证码和恢复码
+    async fn enable(conn: &DatabaseConnection, claims: &Claims) -> (String, String, Vec<String>) {
+        let enrolled = do_enroll(conn, claims).await.unwrap();
+        let code = code_for(&enrolled.secret, 0);
+        let codes = do_activate(conn, claims, &code).await.unwrap();
+        (enrolled.secret, code, codes)
+    }
+
+    #[tokio::test]
+    async fn enroll_and_activate() {
+        let conn = test_db().await;
+        let user = test_user(&conn, "olga").await;
+        let claims = JWT.claims_for(&user);
+
+        assert_eq!(
+            do_activate(&conn, &claims, "000000").await.unwrap_err(),
+            ERR_TOTP_NOT_ENROLLED
+        );
+        let enrolled = do_enroll(&conn, &claims).await.unwrap();
+        assert!(enrolled
+            .provisioning_uri
+            .contains(&format!("secret={}", enrolled.secret)));
+        assert!(enrolled.provisioning_uri.contains("olga%40"));
+
+        assert_eq!(
+            do_activate(&conn, &claims, "abcdef").await.unwrap_err(),
+            ERR_TOTP_INVALID
+        );
+        // 未开启时可以重新绑定，密钥随之更换
+        let renewed = do_enroll(&conn, &claims).await.unwrap();
+        assert_ne!(renewed.secret, enrolled.secret);
+        assert!(!is_enabled(&conn, user.id).await.unwrap());
+
+        let codes = do_activate(&conn, &claims, &code_for(&renewed.secret, 0))
+            .await
+            .unwrap();
+        assert_eq!(codes.len(), CFG.totp.recovery_codes);
+        assert!(is_enabled(&conn, user.id).await.unwrap());
+        assert_eq!(
+            do_enroll(&conn, &claims).await.unwrap_err(),
+            ERR_TOTP_ALREADY_ENABLED
+        );
+        assert_eq!(
+            do_activate(&conn, &claims, "000000").await.unwrap_err(),
+            ERR_TOTP_ALREADY_ENABLED
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_rejects_replayed_codes() {
+        let conn = test_db().await;
+        let user = test_user(&conn, "piet").await;
+        let (secret, used, _) = enable(&conn, &JWT.claims_for(&user)).await;
+
+        // (验证码, 是否通过)
+        let cases = [
+            (None, false),
+            (Some(used), false),
+            (Some(code_for(&secret, 1)), true),
+            (Some(code_for(&secret, 1)), false),
+        ];
+        for (code, passed) in cases {
+            let res = do_verify(&conn, verify_req(&user, code.clone(), None)).await;
+            assert_eq!(res.is_ok(), passed, "{:?}", code);
+            if !passed {
+                assert_eq!(res.unwrap_err(), ERR_TOTP_INVALID);
+            }
+        }
+
+        let mut req = verify_req(&user, Some(code_for(&secret, 1)), None);
+        req.mfa_token = JWT.issue(&user).unwrap().1.token;
+        assert_eq!(do_verify(&conn, req).await.unwrap_err(), ERR_INVALID_TOKEN);
+    }
+
+    #[tokio::test]
+    async fn recovery_codes_are_single_use() {
+        let conn = test_db().await;
+        let user = test_user(&conn, "quinn").await;
+        let (_, _, codes) = enable(&conn, &JWT.claims_for(&user)).await;
+
+        let lowercase = codes[0].to_lowercase();
+        let body = do_verify(&conn, verify_req(&user, None, Some(&lowercase)))
+            .await
+            .unwrap();
+        assert!(JWT.verify(&body.token).is_ok());
+        assert_eq!(
+            do_verify(&conn, verify_req(&user, None, Some(&codes[0])))
+                .await
+                .unwrap_err(),
+            ERR_TOTP_INVALID
+        );
+        do_verify(&conn, verify_req(&user, None, Some(&codes[1])))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn verify_rejects_disabled_domains() {
+        let state = test_state().await;
+        let root = test_super_admin(&state, "root").await;
+        let code = "totp-off";
+        domain::insert_domain(&state.conn, code, code)
+            .await
+            .unwrap();
+        let user = auth::create_user(
+            &state.conn,
+            auth::SignupReq {
+                account: "rosa".to_string(),
+                password: "Secret-123".to_string(),
+                name: "rosa".to_string(),
+                domain: code.to_string(),
+                email: String::new(),
+                phone: String::new(),
+            },
+        )
+        .await
+        .unwrap();
+        let (secret, _, _) = enable(&state.conn, &JWT.claims_for(&user)).await;
+
+        // 输入密码后、提交验证码前禁用域
+        let req = verify_req(&user, Some(code_for(&secret, 1)), None);
+        domain::set_state(
+            State(state.clone()),
+            Extension(root),
+            Path(code.to_string()),
+            Json(DomainStateReq { state: false }),
+        )
+        .await;
+        assert!(!domain::is_enabled(code));
+        assert_eq!(
+            do_verify(&state.conn, req).await.unwrap_err(),
+            ERR_DOMAIN_DISABLED
+        );
+    }
+}
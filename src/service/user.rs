@@ -0,0 +1,452 @@
+use axum::{
+    extract::{Path, Query, State},
+    routing::{delete, get, put},
+    Extension, Json, Router,
+};
+use entity::{sys_password_history, sys_user};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, Set, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::context::AppState;
+use crate::error::{
+    Result, ERR_CAN_NOT_DELETE_SELF, ERR_PASSWORD_TOO_WEAK, ERR_UPDATE_USER, ERR_USER_NOT_FOUND,
+    OK_DELETE_USER, OK_PASSWORD_RESET, OK_SIGNUP, OK_TOTP_RESET, OK_UPDATE_USER, OK_USER_STATE,
+};
+use crate::service::auth::{self, SignupReq};
+use crate::service::data_scope::{self, Scope};
+use crate::service::{api_key, dept, role, session, totp};
+use crate::util::jwt::Claims;
+use crate::util::password;
+use crate::util::query::contains;
+use crate::util::res::{PageData, PageParams, Res};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list).post(create))
+        .route("/:account", get(detail).put(update).delete(remove))
+        .route("/:account/state", put(set_state))
+        .route("/:account/password", put(reset_password))
+        .route("/:account/2fa", delete(reset_2fa))
+}
+
+// 列表查询条件，字符串字段为模糊匹配
+#[derive(Deserialize, Debug, Default)]
+pub struct UserFilter {
+    pub account: Option<String>,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub state: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateUserReq {
+    pub account: String,
+    pub password: String,
+    pub name: String,
+    #[serde(default)]
+    pub email: String,
+    #[serde(default)]
+    pub phone: String,
+}
+
+// 只修改传入的字段
+#[derive(Deserialize, Debug, Default)]
+pub struct UpdateUserReq {
+    pub name: Option<String>,
+    pub avatar: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UserStateReq {
+    pub state: bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ResetPasswordReq {
+    // 为空时生成随机密码
+    pub password: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResetPasswordRes {
+    pub password: String,
+}
+
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(page): Query<PageParams>,
+    Query(filter): Query<UserFilter>,
+) -> Res<PageData<sys_user::Model>> {
+    let conn = &state.conn;
+    match data_scope::resolve(conn, &claims).await {
+        Ok(scope) => list_users(conn, &claims.domain, &scope, &page, filter)
+            .await
+            .into(),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+pub async fn detail(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(account): Path<String>,
+) -> Res<sys_user::Model> {
+    domain_user(&state.conn, &claims, &account).await.into()
+}
+
+// 管理员在当前域内新建用户
+pub async fn create(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<CreateUserReq>,
+) -> Res<sys_user::Model> {
+    let req = SignupReq {
+        account: req.account,
+        password: req.password,
+        name: req.name,
+        domain: claims.domain.clone(),
+        email: req.email,
+        phone: req.phone,
+    };
+    match auth::create_user(&state.conn, req).await {
+        Ok(user) => {
+            info!(
+                "user {} created user {} in domain {}",
+                claims.sub, user.account, user.domain
+            );
+            Res::with_data_msg(user, &OK_SIGNUP)
+        }
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+pub async fn update(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(account): Path<String>,
+    Json(req): Json<UpdateUserReq>,
+) -> Res<sys_user::Model> {
+    match do_update(&state.conn, &claims, &account, req).await {
+        Ok(user) => Res::with_data_msg(user, &OK_UPDATE_USER),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+// 启用或禁用用户，禁用后立即踢下线
+pub async fn set_state(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(account): Path<String>,
+    Json(req): Json<UserStateReq>,
+) -> Res<()> {
+    match do_set_state(&state.conn, &claims, &account, req.state).await {
+        Ok(()) => Res::with_msg(&OK_USER_STATE),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+pub async fn remove(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(account): Path<String>,
+) -> Res<()> {
+    match do_remove(&state, &claims, &account).await {
+        Ok(()) => Res::with_msg(&OK_DELETE_USER),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+// 管理员重置当前域内用户的密码，用户下次登录前必须修改密码
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(account): Path<String>,
+    Json(req): Json<ResetPasswordReq>,
+) -> Res<ResetPasswordRes> {
+    match do_reset_password(&state.conn, &claims, &account, req).await {
+        Ok(res) => Res::with_data_msg(res, &OK_PASSWORD_RESET),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+// 管理员重置用户的两步验证，用户丢失认证器时使用
+pub async fn reset_2fa(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(account): Path<String>,
+) -> Res<()> {
+    match do_reset_2fa(&state.conn, &claims, &account).await {
+        Ok(()) => Res::with_msg(&OK_TOTP_RESET),
+        Err(err) => Res::with_err(&err),
+    }
+}
+
+async fn do_reset_password(
+    conn: &DatabaseConnection,
+    claims: &Claims,
+    account: &str,
+    req: ResetPasswordReq,
+) -> Result<ResetPasswordRes> {
+    let user = domain_user(conn, claims, account).await?;
+    let plain = match req.password {
+        Some(plain) if !password::check_policy(&plain) => return Err(ERR_PASSWORD_TOO_WEAK),
+        Some(plain) => plain,
+        None => password::generate(),
+    };
+
+    auth::set_password(conn, &user, plain.clone(), true).await?;
+    session::revoke_user(conn, &user.domain, &user.account).await?;
+    info!(
+        "user {} reset password of user {} in domain {}",
+        claims.sub, user.account, user.domain
+    );
+    Ok(ResetPasswordRes { password: plain })
+}
+
+async fn do_reset_2fa(conn: &DatabaseConnection, claims: &Claims, account: &str) -> Result<()> {
+    let user = domain_user(conn, claims, account).await?;
+    totp::reset(conn, user.id).await?;
+    info!(
+        "user {} reset 2fa of user {} in domain {}",
+        claims.sub, user.account, user.domain
+    );
+    Ok(())
+}
+
+pub async fn list_users(
+    conn: &DatabaseConnection,
+    domain: &str,
+    scope: &Scope,
+    page: &PageParams,
+    filter: UserFilter,
+) -> Result<PageData<sys_user::Model>> {
+    let mut query = sys_user::Entity::find()
+        .filter(sys_user::Column::Domain.eq(domain))
+        .filter(scope.condition(sys_user::Column::Id));
+    if let Some(account) = filter.account.filter(|v| !v.is_empty()) {
+        query = query.filter(contains(sys_user::Column::Account, &account));
+    }
+    if let Some(name) = filter.name.filter(|v| !v.is_empty()) {
+        query = query.filter(contains(sys_user::Column::Name, &name));
+    }
+    if let Some(email) = filter.email.filter(|v| !v.is_empty()) {
+        query = query.filter(contains(sys_user::Column::Email, &email));
+    }
+    if let Some(phone) = filter.phone.filter(|v| !v.is_empty()) {
+        query = query.filter(contains(sys_user::Column::Phone, &phone));
+    }
+    if let Some(state) = filter.state {
+        query = query.filter(sys_user::Column::State.eq(state));
+    }
+
+    let paginator = query
+        .order_by_asc(sys_user::Column::Id)
+        .paginate(conn, page.page_size());
+    let totals = paginator.num_items_and_pages().await?;
+    let list = paginator.fetch_page(page.page_num() - 1).await?;
+    Ok(PageData::new(
+        list,
+        totals.number_of_items,
+        totals.number_of_pages,
+        page.page_num(),
+    ))
+}
+
+// 数据范围之外的用户视为不存在
+async fn domain_user(
+    conn: &DatabaseConnection,
+    claims: &Claims,
+    account: &str,
+) -> Result<sys_user::Model> {
+    let scope = data_scope::resolve(conn, claims).await?;
+    sys_user::Entity::find()
+        .filter(sys_user::Column::Domain.eq(&claims.domain))
+        .filter(sys_user::Column::Account.eq(account))
+        .filter(scope.condition(sys_user::Column::Id))
+        .one(conn)
+        .await?
+        .ok_or(ERR_USER_NOT_FOUND)
+}
+
+async fn do_update(
+    conn: &DatabaseConnection,
+    claims: &Claims,
+    account: &str,
+    req: UpdateUserReq,
+) -> Result<sys_user::Model> {
+    let user = domain_user(conn, claims, account).await?;
+    let mut model: sys_user::ActiveModel = user.into();
+    if let Some(name) = req.name {
+        model.name = Set(name);
+    }
+    if let Some(avatar) = req.avatar {
+        model.avatar = Set(avatar);
+    }
+    if let Some(email) = req.email {
+        model.email = Set(email);
+    }
+    if let Some(phone) = req.phone {
+        model.phone = Set(phone);
+    }
+    model.update(conn).await.map_err(|err| {
+        error!(
+            "failed to update user {} in domain {}: {}",
+            account, claims.domain, err
+        );
+        ERR_UPDATE_USER
+    })
+}
+
+async fn do_set_state(
+    conn: &DatabaseConnection,
+    claims: &Claims,
+    account: &str,
+    state: bool,
+) -> Result<()> {
+    if !state && account == claims.sub {
+        return Err(ERR_CAN_NOT_DELETE_SELF);
+    }
+    let user = domain_user(conn, claims, account).await?;
+    let mut model: sys_user::ActiveModel = user.into();
+    model.state = Set(state);
+    model.update(conn).await?;
+    if !state {
+        session::revoke_user(conn, &claims.domain, account).await?;
+    }
+    info!(
+        "user {} set state of user {} in domain {} to {}",
+        claims.sub, account, claims.domain, state
+    );
+    Ok(())
+}
+
+// 删除用户及其会话、两步验证、角色关系、历史密码和 API key
+async fn do_remove(state: &AppState, claims: &Claims, account: &str) -> Result<()> {
+    let conn = &state.conn;
+    if account == claims.sub {
+        return Err(ERR_CAN_NOT_DELETE_SELF);
+    }
+    let user = domain_user(conn, claims, account).await?;
+
+    // 数据库提交后再更新吊销列表和 casbin 规则
+    let txn = conn.begin().await?;
+    let revoked = session::revoke_user_in(&txn, &user.domain, &user.account).await?;
+    api_key::revoke_account(&txn, &user.domain, &user.account).await?;
+    totp::reset(&txn, user.id).await?;
+    role::remove_user(&txn, &user).await?;
+    dept::remove_user(&txn, &user).await?;
+    sys_password_history::Entity::delete_many()
+        .filter(sys_password_history::Column::UserId.eq(user.id))
+        .exec(&txn)
+        .await?;
+    sys_user::Entity::delete_by_id(user.id).exec(&txn).await?;
+    txn.commit().await?;
+
+    revoked.cache();
+    role::sync_domain(conn, &state.enforcer, &user.domain, &[]).await?;
+    info!(
+        "user {} deleted user {} in domain {}",
+        claims.sub, account, claims.domain
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{test_state, test_super_admin, test_user};
+    use crate::error::ERR_INTERNAL;
+    use crate::service::token;
+    use casbin::RbacApi;
+    use entity::{sys_refresh_token, sys_role, sys_role::DataScope};
+    use sea_orm::{ActiveValue::NotSet, ConnectionTrait};
+
+    // 创建 ann 并和 root 一起加入 clerk 角色，返回 ann 的会话的 token_id
+    async fn setup(state: &AppState) -> String {
+        let ann = test_user(&state.conn, "ann").await;
+        let root = auth::find_user(&state.conn, &ann.domain, "root")
+            .await
+            .unwrap()
+            .unwrap();
+        let role = sys_role::ActiveModel {
+            id: NotSet,
+            name: Set("clerk".to_string()),
+            state: Set(true),
+            parent_id: Set(0),
+            domain: Set(ann.domain.clone()),
+            data_scope: Set(DataScope::All),
+        }
+        .insert(&state.conn)
+        .await
+        .unwrap();
+        role::assign_users(&state.conn, &state.enforcer, &role, &[ann.clone(), root])
+            .await
+            .unwrap();
+        token::issue_pair(&state.conn, &ann, None).await.unwrap();
+        sys_refresh_token::Entity::find()
+            .one(&state.conn)
+            .await
+            .unwrap()
+            .unwrap()
+            .token_id
+    }
+
+    async fn has_role(state: &AppState, domain: &str) -> bool {
+        let roles = state
+            .enforcer
+            .write()
+            .await
+            .get_roles_for_user("ann", Some(domain));
+        roles.contains(&"clerk".to_string())
+    }
+
+    #[tokio::test]
+    async fn remove_user_and_sessions() {
+        let state = test_state().await;
+        let claims = test_super_admin(&state, "root").await;
+        let token_id = setup(&state).await;
+
+        do_remove(&state, &claims, "ann").await.unwrap();
+        let conn = &state.conn;
+        assert!(auth::find_user(conn, &claims.domain, "ann")
+            .await
+            .unwrap()
+            .is_none());
+        assert!(session::is_revoked(&token_id));
+        assert!(!has_role(&state, &claims.domain).await);
+    }
+
+    #[tokio::test]
+    async fn failed_remove_rolls_back() {
+        let state = test_state().await;
+        let claims = test_super_admin(&state, "root").await;
+        let token_id = setup(&state).await;
+
+        let conn = &state.conn;
+        conn.execute_unprepared("DROP TABLE sys_password_history")
+            .await
+            .unwrap();
+        let err = do_remove(&state, &claims, "ann").await.unwrap_err();
+        assert_eq!(err, ERR_INTERNAL);
+
+        assert!(auth::find_user(conn, &claims.domain, "ann")
+            .await
+            .unwrap()
+            .is_some());
+        let session = sys_refresh_token::Entity::find()
+            .one(conn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!session.revoked);
+        assert!(!session::is_revoked(&token_id));
+        assert!(has_role(&state, &claims.domain).await);
+    }
+}
@@ -1,24 +1,342 @@
-use serde::{Serialize, Deserialize};
-use jsonwebtoken::{encode, decode, Header, Algorithm, Validation, EncodingKey, DecodingKey};
+use std::{fs, str::FromStr};
 
-use crate::constants;
+use entity::sys_user;
+use jsonwebtoken::{
+    decode, encode, errors::ErrorKind, get_current_timestamp, Algorithm, DecodingKey, EncodingKey,
+    Header, Validation,
+};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::config::{self, CFG};
+
+// 全局 token 服务，按配置文件中的 jwt 节点初始化一次
+pub static JWT: Lazy<Jwt> = Lazy::new(|| Jwt::from_config(&CFG.jwt).expect("invalid jwt config"));
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub token_id: String,
+    pub sub: String,
+    pub domain: String,
+    pub name: String,
+    pub iss: String,
+    pub aud: String,
+    pub iat: u64,
+    pub exp: u64,
+    // 超级管理员通过 X-Domain 跨域操作时为其所在的平台域，此时 domain 为目标域
+    #[serde(skip)]
+    pub home_domain: Option<String>,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
-struct Claims {
-    token_id: String,
-    sub: String,
-    domain: String,
-    name: String,
+pub struct AuthBody {
+    pub token: String,
+    pub token_type: String,
+    pub expires_in: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
 }
 
+impl AuthBody {
+    pub fn new(token: String, expires_in: u64) -> Self {
+        Self {
+            token,
+            token_type: "Bearer".to_string(),
+            expires_in,
+            refresh_token: None,
+        }
+    }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct AuthBody {
-    token: String,
+    pub fn with_refresh_token(mut self, refresh_token: String) -> Self {
+        self.refresh_token = Some(refresh_token);
+        self
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum JwtError {
+    #[error("unsupported jwt algorithm: {0}")]
+    UnsupportedAlgorithm(String),
+    #[error("invalid jwt key: {0}")]
+    InvalidKey(String),
+    #[error("token expired")]
+    Expired,
+    #[error("token not yet valid")]
+    Immature,
+    #[error("invalid token signature")]
+    InvalidSignature,
+    #[error("invalid token issuer")]
+    InvalidIssuer,
+    #[error("invalid token audience")]
+    InvalidAudience,
+    #[error("invalid token: {0}")]
+    InvalidToken(String),
+    #[error("failed to sign token: {0}")]
+    Encode(String),
+}
+
+impl From<jsonwebtoken::errors::Error> for JwtError {
+    fn from(err: jsonwebtoken::errors::Error) -> Self {
+        match err.kind() {
+            ErrorKind::ExpiredSignature => JwtError::Expired,
+            ErrorKind::ImmatureSignature => JwtError::Immature,
+            ErrorKind::InvalidSignature => JwtError::InvalidSignature,
+            ErrorKind::InvalidIssuer => JwtError::InvalidIssuer,
+            ErrorKind::InvalidAudience => JwtError::InvalidAudience,
+            _ => JwtError::InvalidToken(err.to_string()),
+        }
+    }
+}
+
+pub struct Jwt {
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    validation: Validation,
+    mfa_validation: Validation,
+    issuer: String,
+    audience: String,
+    expire: u64,
+    refresh_expire: u64,
+    mfa_expire: u64,
+}
+
+impl Jwt {
+    pub fn from_config(cfg: &config::Jwt) -> Result<Self, JwtError> {
+        let algorithm = Algorithm::from_str(&cfg.algorithm)
+            .map_err(|_| JwtError::UnsupportedAlgorithm(cfg.algorithm.clone()))?;
+
+        let (encoding_key, decoding_key) = match algorithm {
+            Algorithm::HS256 => {
+                if cfg.secret.is_empty() {
+                    return Err(JwtError::InvalidKey("secret is empty".to_string()));
+                }
+                (
+                    EncodingKey::from_secret(cfg.secret.as_bytes()),
+                    DecodingKey::from_secret(cfg.secret.as_bytes()),
+                )
+            }
+            Algorithm::RS256 => {
+                let (private_pem, public_pem) = read_key_pair(cfg)?;
+                (
+                    EncodingKey::from_rsa_pem(&private_pem)
+                        .map_err(|e| JwtError::InvalidKey(e.to_string()))?,
+                    DecodingKey::from_rsa_pem(&public_pem)
+                        .map_err(|e| JwtError::InvalidKey(e.to_string()))?,
+                )
+            }
+            Algorithm::ES256 => {
+                let (private_pem, public_pem) = read_key_pair(cfg)?;
+                (
+                    EncodingKey::from_ec_pem(&private_pem)
+                        .map_err(|e| JwtError::InvalidKey(e.to_string()))?,
+                    DecodingKey::from_ec_pem(&public_pem)
+                        .map_err(|e| JwtError::InvalidKey(e.to_string()))?,
+                )
+            }
+            _ => return Err(JwtError::UnsupportedAlgorithm(cfg.algorithm.clone())),
+        };
+
+        let mut validation = Validation::new(algorithm);
+        validation.leeway = cfg.leeway;
+        validation.set_issuer(&[&cfg.issuer]);
+        validation.set_audience(&[&cfg.audience]);
+        validation.set_required_spec_claims(&["exp", "sub", "iss", "aud"]);
+
+        // 两步验证的中间 token 使用单独的 audience，不能当作 access token 使用
+        let mut mfa_validation = validation.clone();
+        mfa_validation.set_audience(&[mfa_audience(&cfg.audience)]);
+
+        Ok(Self {
+            algorithm,
+            encoding_key,
+            decoding_key,
+            validation,
+            mfa_validation,
+            issuer: cfg.issuer.clone(),
+            audience: cfg.audience.clone(),
+            expire: cfg.expire,
+            refresh_expire: cfg.refresh_expire,
+            mfa_expire: cfg.mfa_expire,
+        })
+    }
+
+    // 为登录成功的用户生成 claims，token_id 每次签发都不同
+    pub fn claims_for(&self, user: &sys_user::Model) -> Claims {
+        let now = get_current_timestamp();
+        Claims {
+            token_id: Uuid::new_v4().to_string(),
+            sub: user.account.clone(),
+            domain: user.domain.clone(),
+            name: user.name.clone(),
+            iss: self.issuer.clone(),
+            aud: self.audience.clone(),
+            iat: now,
+            exp: now + self.expire,
+            home_domain: None,
+        }
+    }
+
+    pub fn encode(&self, claims: &Claims) -> Result<String, JwtError> {
+        encode(&Header::new(self.algorithm), claims, &self.encoding_key)
+            .map_err(|e| JwtError::Encode(e.to_string()))
+    }
+
+    // 返回签发的 claims，调用方需要用其中的 token_id 关联刷新令牌
+    pub fn issue(&self, user: &sys_user::Model) -> Result<(Claims, AuthBody), JwtError> {
+        let claims = self.claims_for(user);
+        let token = self.encode(&claims)?;
+        Ok((claims, AuthBody::new(token, self.expire)))
+    }
+
+    pub fn expire(&self) -> u64 {
+        self.expire
+    }
+
+    pub fn refresh_expire(&self) -> u64 {
+        self.refresh_expire
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Claims, JwtError> {
+        let data = decode::<Claims>(token, &self.decoding_key, &self.validation)?;
+        Ok(data.claims)
+    }
+
+    // 密码校验通过但还需要两步验证时签发的短期 token
+    pub fn issue_mfa(&self, user: &sys_user::Model) -> Result<(String, u64), JwtError> {
+        let mut claims = self.claims_for(user);
+        claims.aud = mfa_audience(&self.audience);
+        claims.exp = claims.iat + self.mfa_expire;
+        Ok((self.encode(&claims)?, self.mfa_expire))
+    }
+
+    pub fn verify_mfa(&self, token: &str) -> Result<Claims, JwtError> {
+        let data = decode::<Claims>(token, &self.decoding_key, &self.mfa_validation)?;
+        Ok(data.claims)
+    }
 }
 
+fn mfa_audience(audience: &str) -> String {
+    format!("{}#mfa", audience)
+}
 
+fn read_key_pair(cfg: &config::Jwt) -> Result<(Vec<u8>, Vec<u8>), JwtError> {
+    let private_pem = fs::read(&cfg.private_key_path)
+        .map_err(|e| JwtError::InvalidKey(format!("{}: {}", cfg.private_key_path, e)))?;
+    let public_pem = fs::read(&cfg.public_key_path)
+        .map_err(|e| JwtError::InvalidKey(format!("{}: {}", cfg.public_key_path, e)))?;
+    Ok((private_pem, public_pem))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(secret: &str) -> config::Jwt {
+        config::Jwt {
+            algorithm: "HS256".to_string(),
+            secret: secret.to_string(),
+            issuer: "rust-admin".to_string(),
+            audience: "web".to_string(),
+            expire: 3600,
+            refresh_expire: 7200,
+            mfa_expire: 300,
+            ..Default::default()
+        }
+    }
+
+    fn claims(jwt: &Jwt) -> Claims {
+        let now = get_current_timestamp();
+        Claims {
+            token_id: Uuid::new_v4().to_string(),
+            sub: "bob".to_string(),
+            domain: "default".to_string(),
+            name: "Bob".to_string(),
+            iss: jwt.issuer.clone(),
+            aud: jwt.audience.clone(),
+            iat: now,
+            exp: now + jwt.expire,
+            home_domain: None,
+        }
+    }
 
+    #[test]
+    fn verify_round_trip() {
+        let jwt = Jwt::from_config(&config("secret")).unwrap();
+        let token = jwt.encode(&claims(&jwt)).unwrap();
+        assert_eq!(jwt.verify(&token).unwrap().sub, "bob");
+    }
 
+    #[test]
+    fn verify_rejects_invalid_tokens() {
+        let jwt = Jwt::from_config(&config("secret")).unwrap();
+        let other = Jwt::from_config(&config("other")).unwrap();
+        let now = get_current_timestamp();
 
+        let mut expired = claims(&jwt);
+        expired.iat = now - 7200;
+        expired.exp = now - 3600;
+        let mut audience = claims(&jwt);
+        audience.aud = "app".to_string();
+        let mut issuer = claims(&jwt);
+        issuer.iss = "someone".to_string();
+        let mut mfa = claims(&jwt);
+        mfa.aud = mfa_audience(&jwt.audience);
 
+        let verify = |token: String| jwt.verify(&token).unwrap_err();
+        assert!(matches!(
+            verify(jwt.encode(&expired).unwrap()),
+            JwtError::Expired
+        ));
+        assert!(matches!(
+            verify(jwt.encode(&audience).unwrap()),
+            JwtError::InvalidAudience
+        ));
+        assert!(matches!(
+            verify(jwt.encode(&issuer).unwrap()),
+            JwtError::InvalidIssuer
+        ));
+        assert!(matches!(
+            verify(jwt.encode(&mfa).unwrap()),
+            JwtError::InvalidAudience
+        ));
+        assert!(matches!(
+            verify(other.encode(&claims(&jwt)).unwrap()),
+            JwtError::InvalidSignature
+        ));
+        assert!(matches!(
+            verify("not a token".to_string()),
+            JwtError::InvalidToken(_)
+        ));
+    }
+
+    #[test]
+    fn mfa_token_is_not_an_access_token() {
+        let jwt = Jwt::from_config(&config("secret")).unwrap();
+        let mut mfa = claims(&jwt);
+        mfa.aud = mfa_audience(&jwt.audience);
+        let token = jwt.encode(&mfa).unwrap();
+        assert_eq!(jwt.verify_mfa(&token).unwrap().sub, "bob");
+
+        let token = jwt.encode(&claims(&jwt)).unwrap();
+        assert!(matches!(
+            jwt.verify_mfa(&token),
+            Err(JwtError::InvalidAudience)
+        ));
+    }
+
+    #[test]
+    fn from_config_rejects_bad_settings() {
+        assert!(matches!(
+            Jwt::from_config(&config("")),
+            Err(JwtError::InvalidKey(_))
+        ));
+        let mut cfg = config("secret");
+        cfg.algorithm = "none".to_string();
+        assert!(matches!(
+            Jwt::from_config(&cfg),
+            Err(JwtError::UnsupportedAlgorithm(_))
+        ));
+    }
+}
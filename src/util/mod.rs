@@ -0,0 +1,7 @@
+pub mod jwt;
+pub mod password;
+pub mod query;
+pub mod res;
+pub mod tls;
+pub mod totp;
+pub mod tree;
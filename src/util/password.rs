@@ -0,0 +1,275 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+use once_cell::sync::Lazy;
+use pbkdf2::Pbkdf2;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::config::{PasswordPolicy, CFG};
+use crate::error::{self, ERR_INTERNAL};
+
+// 用户不存在时用来比对的假 hash，保证登录耗时与用户存在时一致
+static DUMMY_HASH: Lazy<String> =
+    Lazy::new(|| hash("dummy-password").expect("failed to hash dummy password"));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verified {
+    // 密码错误或 hash 格式无法识别
+    Invalid,
+    // 密码正确，hash 为当前配置的 Argon2id 参数
+    Valid,
+    // 密码正确，但 hash 是旧算法或旧参数，需要重新 hash
+    NeedsRehash,
+}
+
+impl Verified {
+    pub fn is_valid(&self) -> bool {
+        !matches!(self, Verified::Invalid)
+    }
+}
+
+fn argon2() -> Result<Argon2<'static>, argon2::Error> {
+    let cfg = &CFG.password;
+    let params = Params::new(cfg.memory_cost, cfg.time_cost, cfg.parallelism, None)?;
+    Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+}
+
+// 生成 Argon2id PHC 字符串
+pub fn hash(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = argon2()?.hash_password(password.as_bytes(), &salt)?;
+    Ok(hash.to_string())
+}
+
+// 校验密码，支持 Argon2 以及导入用户使用的 bcrypt/PBKDF2 hash
+pub fn verify(password: &str, hash: &str) -> Verified {
+    if hash.starts_with("$2a$") || hash.starts_with("$2b$") || hash.starts_with("$2y$") {
+        return match bcrypt::verify(password, hash) {
+            Ok(true) => Verified::NeedsRehash,
+            _ => Verified::Invalid,
+        };
+    }
+
+    let Ok(parsed) = PasswordHash::new(hash) else {
+        return Verified::Invalid;
+    };
+
+    match parsed.algorithm.as_str() {
+        "argon2id" | "argon2i" | "argon2d" => {
+            let Ok(hasher) = argon2() else {
+                return Verified::Invalid;
+            };
+            if hasher
+                .verify_password(password.as_bytes(), &parsed)
+                .is_err()
+            {
+                return Verified::Invalid;
+            }
+            if is_current_argon2(&parsed) {
+                Verified::Valid
+            } else {
+                Verified::NeedsRehash
+            }
+        }
+        "pbkdf2" | "pbkdf2-sha256" | "pbkdf2-sha512" => {
+            match Pbkdf2.verify_password(password.as_bytes(), &parsed) {
+                Ok(()) => Verified::NeedsRehash,
+                Err(_) => Verified::Invalid,
+            }
+        }
+        _ => Verified::Invalid,
+    }
+}
+
+const LOWERCASE: &[u8] = b"abcdefghijkmnopqrstuvwxyz";
+const UPPERCASE: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
+const DIGITS: &[u8] = b"23456789";
+const SYMBOLS: &[u8] = b"!@#$%^&*-_=+";
+
+// 检查密码是否满足配置的长度和字符种类要求
+pub fn check_policy(password: &str) -> bool {
+    meets_policy(password, &CFG.password.policy)
+}
+
+fn meets_policy(password: &str, policy: &PasswordPolicy) -> bool {
+    let classes = [
+        password.chars().any(|c| c.is_ascii_lowercase()),
+        password.chars().any(|c| c.is_ascii_uppercase()),
+        password.chars().any(|c| c.is_ascii_digit()),
+        password.chars().any(|c| !c.is_ascii_alphanumeric()),
+    ]
+    .iter()
+    .filter(|x| **x)
+    .count();
+    password.chars().count() >= policy.min_length && classes >= policy.min_classes
+}
+
+// 生成包含全部字符种类的随机密码，用于管理员重置
+pub fn generate() -> String {
+    let mut rng = rand::thread_rng();
+    let all = [LOWERCASE, UPPERCASE, DIGITS, SYMBOLS].concat();
+    let len = CFG.password.policy.min_length.max(12);
+    let mut chars: Vec<u8> = [LOWERCASE, UPPERCASE, DIGITS, SYMBOLS]
+        .iter()
+        .map(|set| set[rng.gen_range(0..set.len())])
+        .collect();
+    while chars.len() < len {
+        chars.push(all[rng.gen_range(0..all.len())]);
+    }
+    chars.shuffle(&mut rng);
+    String::from_utf8(chars).unwrap()
+}
+
+// 用户不存在时调用，消耗与正常校验相同的时间
+fn verify_dummy(password: &str) {
+    let _ = verify(password, &DUMMY_HASH);
+}
+
+// 以下异步版本在阻塞线程池中计算，避免占用 tokio 工作线程
+pub async fn hash_blocking(password: String) -> error::Result<String> {
+    tokio::task::spawn_blocking(move || hash(&password))
+        .await
+        .map_err(|_| ERR_INTERNAL)?
+        .map_err(|err| {
+            error!("failed to hash password: {}", err);
+            ERR_INTERNAL
+        })
+}
+
+// 判断密码是否与给定的任意一个 hash 匹配，用于密码历史检查
+pub async fn matches_any_blocking(password: String, hashes: Vec<String>) -> error::Result<bool> {
+    tokio::task::spawn_blocking(move || hashes.iter().any(|h| verify(&password, h).is_valid()))
+        .await
+        .map_err(|_| ERR_INTERNAL)
+}
+
+pub async fn verify_blocking(password: String, hash: Option<String>) -> error::Result<Verified> {
+    tokio::task::spawn_blocking(move || match hash {
+        Some(hash) => verify(&password, &hash),
+        None => {
+            verify_dummy(&password);
+            Verified::Invalid
+        }
+    })
+    .await
+    .map_err(|_| ERR_INTERNAL)
+}
+
+fn is_current_argon2(parsed: &PasswordHash) -> bool {
+    let cfg = &CFG.password;
+    let Ok(params) = Params::try_from(parsed) else {
+        return false;
+    };
+    parsed.algorithm == argon2::ARGON2ID_IDENT
+        && parsed.version == Some(Version::V0x13.into())
+        && params.m_cost() == cfg.memory_cost
+        && params.t_cost() == cfg.time_cost
+        && params.p_cost() == cfg.parallelism
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pbkdf2::password_hash::PasswordHasher;
+
+    fn argon2_hash(algorithm: Algorithm, params: Params, password: &str) -> String {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::new(algorithm, Version::V0x13, params)
+            .hash_password(password.as_bytes(), &salt)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn verify_current_argon2() {
+        let hash = hash("Secret-123").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert_eq!(verify("Secret-123", &hash), Verified::Valid);
+        assert_eq!(verify("secret-123", &hash), Verified::Invalid);
+    }
+
+    #[test]
+    fn outdated_argon2_needs_rehash() {
+        let cfg = &CFG.password;
+        let params = Params::new(cfg.memory_cost, cfg.time_cost + 1, cfg.parallelism, None);
+        let hash = argon2_hash(Algorithm::Argon2id, params.unwrap(), "Secret-123");
+        assert_eq!(verify("Secret-123", &hash), Verified::NeedsRehash);
+        assert_eq!(verify("wrong", &hash), Verified::Invalid);
+
+        let params = Params::new(cfg.memory_cost, cfg.time_cost, cfg.parallelism, None);
+        let hash = argon2_hash(Algorithm::Argon2i, params.unwrap(), "Secret-123");
+        assert_eq!(verify("Secret-123", &hash), Verified::NeedsRehash);
+    }
+
+    #[test]
+    fn legacy_hashes_need_rehash() {
+        let hash = bcrypt::hash("Secret-123", 4).unwrap();
+        assert_eq!(verify("Secret-123", &hash), Verified::NeedsRehash);
+        assert_eq!(verify("wrong", &hash), Verified::Invalid);
+
+        let salt = SaltString::generate(&mut OsRng);
+        let params = pbkdf2::Params {
+            rounds: 1000,
+            output_length: 32,
+        };
+        let hash = Pbkdf2
+            .hash_password_customized(b"Secret-123", None, None, params, &salt)
+            .unwrap()
+            .to_string();
+        assert_eq!(verify("Secret-123", &hash), Verified::NeedsRehash);
+        assert_eq!(verify("wrong", &hash), Verified::Invalid);
+    }
+
+    #[test]
+    fn unknown_hashes_are_invalid() {
+        for hash in [
+            "",
+            "plain-text",
+            "$5$rounds=1000$salt$hash",
+            "$2b$04$broken",
+        ] {
+            assert_eq!(verify("plain-text", hash), Verified::Invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn blocking_helpers() {
+        let hash = hash_blocking("Secret-123".to_string()).await.unwrap();
+        let verified = verify_blocking("Secret-123".to_string(), Some(hash.clone())).await;
+        assert_eq!(verified.unwrap(), Verified::Valid);
+        let verified = verify_blocking("Secret-123".to_string(), None).await;
+        assert_eq!(verified.unwrap(), Verified::Invalid);
+
+        let hashes = vec![bcrypt::hash("Old-123", 4).unwrap(), hash];
+        assert!(matches_any_blocking("Old-123".to_string(), hashes.clone())
+            .await
+            .unwrap());
+        assert!(!matches_any_blocking("New-123".to_string(), hashes)
+            .await
+            .unwrap());
+    }
+
+    #[test]
+    fn password_policy() {
+        let policy = PasswordPolicy {
+            min_length: 8,
+            min_classes: 3,
+            history: 0,
+        };
+        assert!(meets_policy("abcDEF12", &policy));
+        assert!(meets_policy("abcdef1!", &policy));
+        assert!(!meets_policy("abcDE12", &policy));
+        assert!(!meets_policy("abcdefgh12", &policy));
+        assert!(!meets_policy("ABCDEFGH!!", &policy));
+    }
+
+    #[test]
+    fn generated_passwords_meet_policy() {
+        for _ in 0..20 {
+            let password = generate();
+            assert!(password.len() >= CFG.password.policy.min_length.max(12));
+            assert!(check_policy(&password));
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use sea_orm::{
+    sea_query::{Expr, LikeExpr, SimpleExpr},
+    ColumnTrait,
+};
+
+const LIKE_ESCAPE: char = '\\';
+
+// 子串模糊匹配，用户输入中的 % 和 _ 按普通字符处理
+pub fn contains<C: ColumnTrait>(column: C, value: &str) -> SimpleExpr {
+    let pattern = format!("%{}%", escape_like(value));
+    Expr::col((column.entity_name(), column)).like(LikeExpr::new(pattern).escape(LIKE_ESCAPE))
+}
+
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_' | LIKE_ESCAPE) {
+            escaped.push(LIKE_ESCAPE);
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{test_db, test_user};
+    use entity::sys_user;
+    use sea_orm::{EntityTrait, QueryFilter, QueryOrder};
+
+    #[test]
+    fn escape_wildcards() {
+        assert_eq!(escape_like("alice"), "alice");
+        assert_eq!(escape_like("a_b%c\\d"), "a\\_b\\%c\\\\d");
+    }
+
+    #[tokio::test]
+    async fn contains_matches_literal_wildcards() {
+        let conn = test_db().await;
+        for account in ["a_b", "axb", "100%", "1000", "c\\d"] {
+            test_user(&conn, account).await;
+        }
+        let find = |value: &'static str| {
+            let conn = conn.clone();
+            async move {
+                sys_user::Entity::find()
+                    .filter(contains(sys_user::Column::Account, value))
+                    .order_by_asc(sys_user::Column::Id)
+                    .all(&conn)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|u| u.account)
+                    .collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(find("_").await, ["a_b"]);
+        assert_eq!(find("%").await, ["100%"]);
+        assert_eq!(find("\\").await, ["c\\d"]);
+        assert_eq!(find("x").await, ["axb"]);
+        assert_eq!(find("").await.len(), 5);
+    }
+}
@@ -6,6 +6,12 @@ use axum::{
     response::{IntoResponse, Response},
 };
 use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result, ERR_OK};
+
+const DEFAULT_PAGE_SIZE: u64 = 10;
+const MAX_PAGE_SIZE: u64 = 100;
+
 #[derive(Debug, Serialize)]
 /// 查数据返回
 pub struct PageData<T> {
@@ -14,6 +20,18 @@ pub struct PageData<T> {
     pub total_pages: u64,
     pub page_num: u64,
 }
+
+impl<T> PageData<T> {
+    pub fn new(list: Vec<T>, total: u64, total_pages: u64, page_num: u64) -> Self {
+        Self {
+            list,
+            total,
+            total_pages,
+            page_num,
+        }
+    }
+}
+
 /// 分页参数
 #[derive(Deserialize, Clone, Debug, Serialize, Default)]
 pub struct PageParams {
@@ -21,6 +39,19 @@ pub struct PageParams {
     pub page_size: Option<u64>,
 }
 
+impl PageParams {
+    /// 页码从 1 开始
+    pub fn page_num(&self) -> u64 {
+        self.page_num.unwrap_or(1).max(1)
+    }
+
+    pub fn page_size(&self) -> u64 {
+        self.page_size
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+}
+
 /// 数据统一返回格式
 #[derive(Debug, Serialize, Default)]
 pub struct Res<T> {
@@ -49,7 +80,10 @@ where
             Err(e) => {
                 return Response::builder()
                     .status(StatusCode::INTERNAL_SERVER_ERROR)
-                    .header(header::CONTENT_TYPE, HeaderValue::from_static(mime::TEXT_PLAIN_UTF_8.as_ref()))
+                    .header(
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static(mime::TEXT_PLAIN_UTF_8.as_ref()),
+                    )
                     .body(Body::from(e.to_string()))
                     .unwrap();
             }
@@ -64,26 +98,38 @@ where
 impl<T: Serialize> Res<T> {
     pub fn with_data(data: T) -> Self {
         Self {
-            code: 0,
+            code: Some(ERR_OK.code()),
             data: Some(data),
-            msg: "success",
+            msg: Some(ERR_OK.msg().to_string()),
         }
     }
 
     pub fn with_err(err: &Error) -> Self {
         Self {
-            code: err.code(),
+            code: Some(err.code()),
             data: None,
-            msg: err.msg(),
+            msg: Some(err.msg().to_string()),
         }
     }
-    
-    #[allow(dead_code)]
+
+    pub fn with_msg(msg: &Error) -> Self {
+        Self::with_err(msg)
+    }
+
     pub fn with_data_msg(data: T, err: &Error) -> Self {
         Self {
-            code: err.code(),
+            code: Some(err.code()),
             data: Some(data),
-            msg: err.msg(),
+            msg: Some(err.msg().to_string()),
         }
     }
-}
\ No newline at end of file
+}
+
+impl<T: Serialize> From<Result<T>> for Res<T> {
+    fn from(result: Result<T>) -> Self {
+        match result {
+            Ok(data) => Res::with_data(data),
+            Err(err) => Res::with_err(&err),
+        }
+    }
+}
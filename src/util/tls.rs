@@ -0,0 +1,349 @@
+use std::{
+    fs, io,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use axum::{middleware::AddExtension, Extension};
+use axum_server::{
+    accept::Accept,
+    tls_rustls::{RustlsAcceptor, RustlsConfig},
+};
+use futures::future::BoxFuture;
+use rustls::{
+    server::{AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient},
+    sign, Certificate, PrivateKey, RootCertStore, ServerConfig, SignatureScheme,
+};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::server::TlsStream;
+use tower::Layer;
+use x509_parser::{certificate::X509Certificate, extensions::GeneralName, prelude::FromDer};
+
+use crate::config::{self, CertIdentity, CFG};
+
+// 按配置构造 rustls 配置，开启 mtls 时附带客户端证书校验
+pub fn server_config(server: &config::Server) -> io::Result<ServerConfig> {
+    let certs = read_certs(&server.pem_cert_path)?;
+    let key = read_key(&server.pem_key_path)?;
+    check_certificate(&certs[0], &key)?;
+
+    let builder = ServerConfig::builder().with_safe_defaults();
+    let builder = if server.mtls.enabled {
+        let mut roots = RootCertStore::empty();
+        for ca in read_certs(&server.mtls.client_ca_path)? {
+            roots.add(&ca).map_err(io::Error::other)?;
+        }
+        let verifier = match server.mtls.required {
+            true => AllowAnyAuthenticatedClient::new(roots).boxed(),
+            false => AllowAnyAnonymousOrAuthenticatedClient::new(roots).boxed(),
+        };
+        builder.with_client_cert_verifier(verifier)
+    } else {
+        builder.with_no_client_auth()
+    };
+
+    let mut config = builder
+        .with_single_cert(certs, key)
+        .map_err(io::Error::other)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+pub fn rustls_config(server: &config::Server) -> io::Result<RustlsConfig> {
+    Ok(RustlsConfig::from_config(Arc::new(server_config(server)?)))
+}
+
+// 用当前配置重新加载证书，新证书校验失败时保留旧证书继续服务
+pub fn reload(config: &RustlsConfig) -> io::Result<()> {
+    reload_from(config, &CFG.server)
+}
+
+fn reload_from(config: &RustlsConfig, server: &config::Server) -> io::Result<()> {
+    let new_config = server_config(server)?;
+    config.reload_from_config(Arc::new(new_config));
+    info!("tls certificate reloaded from {}", server.pem_cert_path);
+    Ok(())
+}
+
+// 轮询证书文件的修改时间，变化后自动重新加载
+pub async fn watch(config: RustlsConfig, interval: Duration) {
+    let mut interval = tokio::time::interval(interval);
+    let mut last = modified_times();
+    loop {
+        interval.tick().await;
+        let current = modified_times();
+        if current == last {
+            continue;
+        }
+        last = current;
+        if let Err(err) = reload(&config) {
+            error!(
+                "failed to reload tls certificate, keep using the old one: {}",
+                err
+            );
+        }
+    }
+}
+
+// 收到 SIGHUP 时重新加载证书
+#[cfg(unix)]
+pub async fn reload_on_sighup(config: RustlsConfig) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = signal(SignalKind::hangup()).expect("failed to install SIGHUP handler");
+    while hangup.recv().await.is_some() {
+        info!("received SIGHUP, reloading tls certificate");
+        if let Err(err) = reload(&config) {
+            error!(
+                "failed to reload tls certificate, keep using the old one: {}",
+                err
+            );
+        }
+    }
+}
+
+fn modified_times() -> Vec<Option<SystemTime>> {
+    let server = &CFG.server;
+    let mut paths = vec![&server.pem_cert_path, &server.pem_key_path];
+    if server.mtls.enabled {
+        paths.push(&server.mtls.client_ca_path);
+    }
+    paths
+        .into_iter()
+        .map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+// 证书必须与私钥匹配：用私钥签名后再用证书中的公钥验证
+fn check_certificate(cert: &Certificate, key: &PrivateKey) -> io::Result<()> {
+    let (_, parsed) = X509Certificate::from_der(&cert.0).map_err(io::Error::other)?;
+    let validity = parsed.validity();
+
+    let signing_key = sign::any_supported_type(key).map_err(io::Error::other)?;
+    let signer = signing_key
+        .choose_scheme(&[
+            SignatureScheme::ECDSA_NISTP256_SHA256,
+            SignatureScheme::ECDSA_NISTP384_SHA384,
+            SignatureScheme::ED25519,
+            SignatureScheme::RSA_PKCS1_SHA256,
+        ])
+        .ok_or_else(|| io::Error::other("unsupported private key type"))?;
+    let algorithm = match signer.scheme() {
+        SignatureScheme::ECDSA_NISTP256_SHA256 => &webpki::ECDSA_P256_SHA256,
+        SignatureScheme::ECDSA_NISTP384_SHA384 => &webpki::ECDSA_P384_SHA384,
+        SignatureScheme::ED25519 => &webpki::ED25519,
+        _ => &webpki::RSA_PKCS1_2048_8192_SHA256,
+    };
+    let message = b"rust-admin tls key pair check";
+    let signature = signer.sign(message).map_err(io::Error::other)?;
+    webpki::EndEntityCert::try_from(cert.0.as_slice())
+        .and_then(|end_entity| end_entity.verify_signature(algorithm, message, &signature))
+        .map_err(|_| io::Error::other("private key does not match the certificate"))?;
+
+    info!(
+        "tls certificate {} expires at {}",
+        parsed.subject(),
+        validity.not_after
+    );
+    // 过期的证书只告警，不阻止启动，便于开发环境使用自签名证书
+    match validity.time_to_expiration() {
+        Some(remaining) if remaining.whole_days() < 14 => {
+            warn!("tls certificate expires in {} days", remaining.whole_days())
+        }
+        None => warn!("tls certificate is expired or not yet valid"),
+        _ => {}
+    }
+    Ok(())
+}
+
+fn read_certs(path: &str) -> io::Result<Vec<Certificate>> {
+    let pem = fs::read(path)?;
+    let certs = rustls_pemfile::certs(&mut pem.as_slice())
+        .map(|cert| cert.map(|cert| Certificate(cert.to_vec())))
+        .collect::<io::Result<Vec<_>>>()?;
+    if certs.is_empty() {
+        return Err(io::Error::other(format!(
+            "no certificate found in {}",
+            path
+        )));
+    }
+    Ok(certs)
+}
+
+fn read_key(path: &str) -> io::Result<PrivateKey> {
+    let pem = fs::read(path)?;
+    rustls_pemfile::private_key(&mut pem.as_slice())?
+        .map(|key| PrivateKey(key.secret_der().to_vec()))
+        .ok_or_else(|| io::Error::other(format!("no private key found in {}", path)))
+}
+
+// 握手阶段已经由 rustls 校验过的客户端证书
+#[derive(Debug, Clone, Default)]
+pub struct ClientCert {
+    // 证书 DER 的 sha256，用于日志和审计
+    pub fingerprint: String,
+    pub common_name: Option<String>,
+    pub dns: Vec<String>,
+    pub email: Vec<String>,
+    pub uri: Vec<String>,
+}
+
+impl ClientCert {
+    pub fn parse(der: &[u8]) -> Option<Self> {
+        let (_, cert) = X509Certificate::from_der(der).ok()?;
+        let mut client = ClientCert {
+            fingerprint: hex::encode(Sha256::digest(der)),
+            common_name: cert
+                .subject()
+                .iter_common_name()
+                .next()
+                .and_then(|cn| cn.as_str().ok())
+                .map(str::to_string),
+            ..Default::default()
+        };
+        if let Ok(Some(san)) = cert.subject_alternative_name() {
+            for name in &san.value.general_names {
+                match name {
+                    GeneralName::DNSName(v) => client.dns.push(v.to_string()),
+                    GeneralName::RFC822Name(v) => client.email.push(v.to_string()),
+                    GeneralName::URI(v) => client.uri.push(v.to_string()),
+                    _ => {}
+                }
+            }
+        }
+        Some(client)
+    }
+
+    pub fn identity(&self, kind: CertIdentity) -> Option<&str> {
+        match kind {
+            CertIdentity::CommonName => self.common_name.as_deref(),
+            CertIdentity::Dns => self.dns.first().map(String::as_str),
+            CertIdentity::Email => self.email.first().map(String::as_str),
+            CertIdentity::Uri => self.uri.first().map(String::as_str),
+        }
+    }
+}
+
+// 每个 tls 连接的信息，注入到该连接上的所有请求中
+#[derive(Debug, Clone, Default)]
+pub struct TlsInfo {
+    pub client_cert: Option<ClientCert>,
+}
+
+// 在 RustlsAcceptor 完成握手后取出客户端证书
+#[derive(Debug, Clone)]
+pub struct ClientCertAcceptor {
+    inner: RustlsAcceptor,
+}
+
+impl ClientCertAcceptor {
+    pub fn new(config: RustlsConfig) -> Self {
+        ClientCertAcceptor {
+            inner: RustlsAcceptor::new(config),
+        }
+    }
+}
+
+impl<I, S> Accept<I, S> for ClientCertAcceptor
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = TlsStream<I>;
+    type Service = AddExtension<S, TlsInfo>;
+    type Future = BoxFuture<'static, io::Result<(Self::Stream, Self::Service)>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let acceptor = self.inner.clone();
+
+        Box::pin(async move {
+            let (stream, service) = acceptor.accept(stream, service).await?;
+            let client_cert = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .and_then(|cert| ClientCert::parse(&cert.0));
+            let service = Extension(TlsInfo { client_cert }).layer(service);
+            Ok((stream, service))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(cert: &str, key: &str) -> config::Server {
+        config::Server {
+            pem_cert_path: cert.to_string(),
+            pem_key_path: key.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn check_key_pairs() {
+        // (证书, 私钥, 是否通过)
+        let cases = [
+            (
+                "resource/cert/test/server.pem",
+                "resource/cert/test/server_key.pem",
+                true,
+            ),
+            // 自带的开发证书已经过期，只告警
+            ("resource/cert/cert.pem", "resource/cert/key.pem", true),
+            (
+                "resource/cert/cert.pem",
+                "resource/cert/test/other_key.pem",
+                false,
+            ),
+            (
+                "resource/cert/test/server.pem",
+                "resource/cert/key.pem",
+                false,
+            ),
+        ];
+        for (cert, key, passed) in cases {
+            let certs = read_certs(cert).unwrap();
+            let key = read_key(key).unwrap();
+            assert_eq!(
+                check_certificate(&certs[0], &key).is_ok(),
+                passed,
+                "{}",
+                cert
+            );
+        }
+    }
+
+    #[test]
+    fn reload_keeps_old_config_on_error() {
+        let config = rustls_config(&server(
+            "resource/cert/test/server.pem",
+            "resource/cert/test/server_key.pem",
+        ))
+        .unwrap();
+        let before = config.get_inner();
+
+        // (证书, 私钥, 是否替换)
+        let cases = [
+            (
+                "resource/cert/cert.pem",
+                "resource/cert/test/other_key.pem",
+                false,
+            ),
+            ("resource/cert/missing.pem", "resource/cert/key.pem", false),
+            ("resource/cert/cert.pem", "resource/cert/key.pem", true),
+        ];
+        for (cert, key, replaced) in cases {
+            assert_eq!(reload_from(&config, &server(cert, key)).is_ok(), replaced);
+            assert_eq!(
+                !Arc::ptr_eq(&before, &config.get_inner()),
+                replaced,
+                "{}",
+                cert
+            );
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+
+use entity::{sys_dept, sys_menu, sys_role};
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QuerySelect};
+use serde::Serialize;
+
+use crate::error::{
+    Error, Result, ERR_DEPT_PARENT_INVALID, ERR_MENU_PARENT_INVALID, ERR_ROLE_PARENT_INVALID,
+};
+
+// 以 parent_id 组织成树的数据，parent_id 为 0 表示根节点
+pub trait TreeNode {
+    fn id(&self) -> i32;
+    fn parent_id(&self) -> i32;
+}
+
+impl TreeNode for sys_role::Model {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn parent_id(&self) -> i32 {
+        self.parent_id
+    }
+}
+
+impl TreeNode for sys_dept::Model {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn parent_id(&self) -> i32 {
+        self.parent_id
+    }
+}
+
+impl TreeNode for sys_menu::Model {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn parent_id(&self) -> i32 {
+        self.parent_id
+    }
+}
+
+// 按域保存、以 parent_id 组织成树的表
+pub trait TreeEntity: EntityTrait<Model: TreeNode> {
+    const DOMAIN: Self::Column;
+    const PARENT_ID: Self::Column;
+    // 父节点不存在或形成环时返回的错误
+    const PARENT_INVALID: Error;
+}
+
+// 同级节点按 sort 排序的表
+pub trait SortedEntity: TreeEntity {
+    const SORT: Self::Column;
+}
+
+impl TreeEntity for sys_role::Entity {
+    const DOMAIN: sys_role::Column = sys_role::Column::Domain;
+    const PARENT_ID: sys_role::Column = sys_role::Column::ParentId;
+    const PARENT_INVALID: Error = ERR_ROLE_PARENT_INVALID;
+}
+
+impl TreeEntity for sys_dept::Entity {
+    const DOMAIN: sys_dept::Column = sys_dept::Column::Domain;
+    const PARENT_ID: sys_dept::Column = sys_dept::Column::ParentId;
+    const PARENT_INVALID: Error = ERR_DEPT_PARENT_INVALID;
+}
+
+impl SortedEntity for sys_dept::Entity {
+    const SORT: sys_dept::Column = sys_dept::Column::Sort;
+}
+
+impl TreeEntity for sys_menu::Entity {
+    const DOMAIN: sys_menu::Column = sys_menu::Column::Domain;
+    const PARENT_ID: sys_menu::Column = sys_menu::Column::ParentId;
+    const PARENT_INVALID: Error = ERR_MENU_PARENT_INVALID;
+}
+
+impl SortedEntity for sys_menu::Entity {
+    const SORT: sys_menu::Column = sys_menu::Column::Sort;
+}
+
+#[derive(Serialize, Debug)]
+pub struct Tree<T> {
+    #[serde(flatten)]
+    pub node: T,
+    pub children: Vec<Tree<T>>,
+}
+
+// 按输入顺序构造树，父节点不在输入中的节点会被丢弃
+pub fn build<T: TreeNode>(nodes: Vec<T>) -> Vec<Tree<T>> {
+    let mut children: HashMap<i32, Vec<T>> = HashMap::new();
+    for node in nodes {
+        children.entry(node.parent_id()).or_default().push(node);
+    }
+    attach(&mut children, 0)
+}
+
+fn attach<T: TreeNode>(children: &mut HashMap<i32, Vec<T>>, parent_id: i32) -> Vec<Tree<T>> {
+    children
+        .remove(&parent_id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|node| Tree {
+            children: attach(children, node.id()),
+            node,
+        })
+        .collect()
+}
+
+// 只保留满足条件的节点，以及有子孙节点被保留的节点
+pub fn prune<T>(trees: Vec<Tree<T>>, keep: &mut impl FnMut(&T) -> bool) -> Vec<Tree<T>> {
+    trees
+        .into_iter()
+        .filter_map(|tree| {
+            let children = prune(tree.children, keep);
+            (keep(&tree.node) || !children.is_empty()).then_some(Tree {
+                node: tree.node,
+                children,
+            })
+        })
+        .collect()
+}
+
+// 把 id 移动到 parent_id 下是否会形成环，即 parent_id 是 id 自己或其子孙
+pub fn creates_cycle<T: TreeNode>(nodes: &[T], id: i32, parent_id: i32) -> bool {
+    let parents: HashMap<i32, i32> = nodes.iter().map(|n| (n.id(), n.parent_id())).collect();
+    let mut current = parent_id;
+    // 数据本身有环时也能结束
+    for _ in 0..=parents.len() {
+        if current == 0 {
+            return false;
+        }
+        if current == id {
+            return true;
+        }
+        current = parents.get(&current).copied().unwrap_or(0);
+    }
+    true
+}
+
+// id 及其所有子孙节点
+pub fn subtree_ids<T: TreeNode>(nodes: &[T], id: i32) -> Vec<i32> {
+    let mut ids = vec![id];
+    let mut i = 0;
+    while i < ids.len() {
+        let parent_id = ids[i];
+        ids.extend(
+            nodes
+                .iter()
+                .filter(|n| n.parent_id() == parent_id)
+                .map(|n| n.id()),
+        );
+        i += 1;
+    }
+    ids
+}
+
+// 父节点必须存在于同一个域，并且不能是自己或自己的子孙；id 为空表示新建节点
+pub async fn ensure_parent_valid<E: TreeEntity>(
+    conn: &impl ConnectionTrait,
+    domain: &str,
+    id: Option<i32>,
+    parent_id: i32,
+) -> Result<()> {
+    if parent_id == 0 {
+        return Ok(());
+    }
+    let nodes = E::find().filter(E::DOMAIN.eq(domain)).all(conn).await?;
+    if !nodes.iter().any(|n| n.id() == parent_id) {
+        return Err(E::PARENT_INVALID);
+    }
+    if id.is_some_and(|id| creates_cycle(&nodes, id, parent_id)) {
+        return Err(E::PARENT_INVALID);
+    }
+    Ok(())
+}
+
+// 同级节点中最大的 sort 加一
+pub async fn next_sort<E: SortedEntity>(
+    conn: &impl ConnectionTrait,
+    domain: &str,
+    parent_id: i32,
+) -> Result<i32> {
+    let last = E::find()
+        .select_only()
+        .column_as(E::SORT.max(), "sort")
+        .filter(E::DOMAIN.eq(domain))
+        .filter(E::PARENT_ID.eq(parent_id))
+        .into_tuple::<Option<i32>>()
+        .one(conn)
+        .await?
+        .flatten();
+    Ok(last.map_or(0, |sort| sort + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (id, parent_id)
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Node(i32, i32);
+
+    impl TreeNode for Node {
+        fn id(&self) -> i32 {
+            self.0
+        }
+
+        fn parent_id(&self) -> i32 {
+            self.1
+        }
+    }
+
+    //  1      5
+    //  ├─ 2
+    //  │  └─ 4
+    //  └─ 3
+    fn nodes() -> Vec<Node> {
+        vec![Node(1, 0), Node(2, 1), Node(3, 1), Node(4, 2), Node(5, 0)]
+    }
+
+    // 按先序遍历输出 (id, 深度)
+    fn flatten(trees: &[Tree<Node>], depth: usize, out: &mut Vec<(i32, usize)>) {
+        for tree in trees {
+            out.push((tree.node.0, depth));
+            flatten(&tree.children, depth + 1, out);
+        }
+    }
+
+    fn shape(trees: &[Tree<Node>]) -> Vec<(i32, usize)> {
+        let mut out = vec![];
+        flatten(trees, 0, &mut out);
+        out
+    }
+
+    #[test]
+    fn build_tree() {
+        let trees = build(nodes());
+        assert_eq!(shape(&trees), [(1, 0), (2, 1), (4, 2), (3, 1), (5, 0)]);
+
+        // 父节点不在输入中的节点被丢弃
+        let trees = build(vec![Node(2, 1), Node(4, 2), Node(5, 0)]);
+        assert_eq!(shape(&trees), [(5, 0)]);
+        assert!(build(Vec::<Node>::new()).is_empty());
+    }
+
+    #[test]
+    fn prune_tree() {
+        let trees = prune(build(nodes()), &mut |n| n.0 == 4);
+        assert_eq!(shape(&trees), [(1, 0), (2, 1), (4, 2)]);
+
+        let trees = prune(build(nodes()), &mut |n| n.0 == 1 || n.0 == 5);
+        assert_eq!(shape(&trees), [(1, 0), (5, 0)]);
+
+        assert!(prune(build(nodes()), &mut |_| false).is_empty());
+    }
+
+    #[test]
+    fn cycle_detection() {
+        let nodes = nodes();
+        let cases = [
+            // (id, 新的 parent_id, 是否形成环)
+            (2, 0, false),
+            (2, 3, false),
+            (2, 5, false),
+            (2, 2, true),
+            (2, 4, true),
+            (1, 4, true),
+            (4, 1, false),
+            // 不存在的父节点视为根节点
+            (2, 9, false),
+        ];
+        for (id, parent_id, expected) in cases {
+            assert_eq!(
+                creates_cycle(&nodes, id, parent_id),
+                expected,
+                "{} -> {}",
+                id,
+                parent_id
+            );
+        }
+
+        // 数据本身有环时也能结束
+        let looped = [Node(1, 2), Node(2, 1)];
+        assert!(creates_cycle(&looped, 3, 1));
+    }
+
+    #[test]
+    fn subtree() {
+        let nodes = nodes();
+        assert_eq!(subtree_ids(&nodes, 1), [1, 2, 3, 4]);
+        assert_eq!(subtree_ids(&nodes, 2), [2, 4]);
+        assert_eq!(subtree_ids(&nodes, 5), [5]);
+        assert_eq!(subtree_ids(&nodes, 9), [9]);
+    }
+}